@@ -555,19 +555,19 @@ pub(super) fn elixir_task_context() -> ContextProviderWithTasks {
         TaskTemplate {
             label: "mix test".to_owned(),
             command: "mix".to_owned(),
-            args: vec!["test".to_owned()],
+            args: vec!["test".into()],
             ..TaskTemplate::default()
         },
         TaskTemplate {
             label: "mix test --failed".to_owned(),
             command: "mix".to_owned(),
-            args: vec!["test".to_owned(), "--failed".to_owned()],
+            args: vec!["test".into(), "--failed".into()],
             ..TaskTemplate::default()
         },
         TaskTemplate {
             label: format!("mix test {}", VariableName::Symbol.template_value()),
             command: "mix".to_owned(),
-            args: vec!["test".to_owned(), VariableName::Symbol.template_value()],
+            args: vec!["test".into(), VariableName::Symbol.template_value().into()],
             ..TaskTemplate::default()
         },
         TaskTemplate {
@@ -578,12 +578,13 @@ pub(super) fn elixir_task_context() -> ContextProviderWithTasks {
             ),
             command: "mix".to_owned(),
             args: vec![
-                "test".to_owned(),
+                "test".into(),
                 format!(
                     "{}:{}",
                     VariableName::File.template_value(),
                     VariableName::Row.template_value()
-                ),
+                )
+                .into(),
             ],
             ..TaskTemplate::default()
         },
@@ -591,15 +592,16 @@ pub(super) fn elixir_task_context() -> ContextProviderWithTasks {
             label: "Elixir: break line".to_owned(),
             command: "iex".to_owned(),
             args: vec![
-                "-S".to_owned(),
-                "mix".to_owned(),
-                "test".to_owned(),
-                "-b".to_owned(),
+                "-S".into(),
+                "mix".into(),
+                "test".into(),
+                "-b".into(),
                 format!(
                     "{}:{}",
                     VariableName::File.template_value(),
                     VariableName::Row.template_value()
-                ),
+                )
+                .into(),
             ],
             ..TaskTemplate::default()
         },
@@ -363,7 +363,7 @@ impl ContextProvider for RustContextProvider {
                 args: vec![
                     "check".into(),
                     "-p".into(),
-                    RUST_PACKAGE_TASK_VARIABLE.template_value(),
+                    RUST_PACKAGE_TASK_VARIABLE.template_value().into(),
                 ],
                 ..TaskTemplate::default()
             },
@@ -383,8 +383,8 @@ impl ContextProvider for RustContextProvider {
                 args: vec![
                     "test".into(),
                     "-p".into(),
-                    RUST_PACKAGE_TASK_VARIABLE.template_value(),
-                    VariableName::Symbol.template_value(),
+                    RUST_PACKAGE_TASK_VARIABLE.template_value().into(),
+                    VariableName::Symbol.template_value().into(),
                     "--".into(),
                     "--nocapture".into(),
                 ],
@@ -399,7 +399,7 @@ impl ContextProvider for RustContextProvider {
                 args: vec![
                     "test".into(),
                     "-p".into(),
-                    RUST_PACKAGE_TASK_VARIABLE.template_value(),
+                    RUST_PACKAGE_TASK_VARIABLE.template_value().into(),
                 ],
                 ..TaskTemplate::default()
             },
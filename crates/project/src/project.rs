@@ -95,7 +95,7 @@ use std::{
     },
     time::{Duration, Instant},
 };
-use task::static_source::{StaticSource, TrackedFile};
+use task::static_source::{StaticSource, TrackedFile, TASKS_OVERLAY_FILE_NAME};
 use terminals::Terminals;
 use text::{Anchor, BufferId, RopeFingerprint};
 use util::{
@@ -7024,8 +7024,8 @@ impl Project {
         })
         .detach();
 
-        self.task_inventory().update(cx, |inventory, _| {
-            inventory.remove_worktree_sources(id_to_remove);
+        self.task_inventory().update(cx, |inventory, cx| {
+            inventory.remove_worktree_sources(id_to_remove, cx);
         });
 
         self.worktrees.retain(|worktree| {
@@ -7464,10 +7464,11 @@ impl Project {
             } else if abs_path.ends_with(&*LOCAL_TASKS_RELATIVE_PATH) {
                 self.task_inventory().update(cx, |task_inventory, cx| {
                     if removed {
-                        task_inventory.remove_local_static_source(&abs_path);
+                        task_inventory.remove_local_static_source(&abs_path, cx);
                     } else {
                         let fs = self.fs.clone();
                         let task_abs_path = abs_path.clone();
+                        let overlay_abs_path = abs_path.with_file_name(TASKS_OVERLAY_FILE_NAME);
                         task_inventory.add_source(
                             TaskSourceKind::Worktree {
                                 id: remote_worktree_id,
@@ -7475,9 +7476,24 @@ impl Project {
                                 id_base: "local_tasks_for_worktree",
                             },
                             |cx| {
-                                let tasks_file_rx =
-                                    watch_config_file(&cx.background_executor(), fs, task_abs_path);
-                                StaticSource::new(TrackedFile::new(tasks_file_rx, cx), cx)
+                                let tasks_file_rx = watch_config_file(
+                                    &cx.background_executor(),
+                                    fs.clone(),
+                                    task_abs_path,
+                                );
+                                let overlay_file_rx = watch_config_file(
+                                    &cx.background_executor(),
+                                    fs,
+                                    overlay_abs_path,
+                                );
+                                StaticSource::new(
+                                    TrackedFile::new_tasks_file_with_overlay(
+                                        tasks_file_rx,
+                                        overlay_file_rx,
+                                        cx,
+                                    ),
+                                    cx,
+                                )
                             },
                             cx,
                         );
@@ -7486,7 +7502,7 @@ impl Project {
             } else if abs_path.ends_with(&*LOCAL_VSCODE_TASKS_RELATIVE_PATH) {
                 self.task_inventory().update(cx, |task_inventory, cx| {
                     if removed {
-                        task_inventory.remove_local_static_source(&abs_path);
+                        task_inventory.remove_local_static_source(&abs_path, cx);
                     } else {
                         let fs = self.fs.clone();
                         let task_abs_path = abs_path.clone();
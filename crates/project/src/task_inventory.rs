@@ -5,6 +5,7 @@ use std::{
     cmp,
     path::{Path, PathBuf},
     sync::Arc,
+    time::SystemTime,
 };
 
 use collections::{HashMap, VecDeque};
@@ -107,6 +108,7 @@ impl Inventory {
         }
 
         let source = create_source(cx);
+        source.update(cx, |source, cx| source.on_register(cx));
         let type_id = source.read(cx).type_id();
         let source = SourceInInventory {
             _subscription: cx.observe(&source, |_, _, cx| {
@@ -124,16 +126,31 @@ impl Inventory {
     /// making corresponding task definitions unavailable in the fetch results.
     ///
     /// Now, entry for this path can be re-added again.
-    pub fn remove_local_static_source(&mut self, abs_path: &Path) {
-        self.sources.retain(|s| s.kind.abs_path() != Some(abs_path));
+    pub fn remove_local_static_source(&mut self, abs_path: &Path, cx: &mut ModelContext<Self>) {
+        self.remove_sources(|s| s.kind.abs_path() == Some(abs_path), cx);
     }
 
     /// If present, removes the worktree source entry that has the given worktree id,
     /// making corresponding task definitions unavailable in the fetch results.
     ///
     /// Now, entry for this path can be re-added again.
-    pub fn remove_worktree_sources(&mut self, worktree: WorktreeId) {
-        self.sources.retain(|s| s.kind.worktree() != Some(worktree));
+    pub fn remove_worktree_sources(&mut self, worktree: WorktreeId, cx: &mut ModelContext<Self>) {
+        self.remove_sources(|s| s.kind.worktree() == Some(worktree), cx);
+    }
+
+    fn remove_sources(
+        &mut self,
+        should_remove: impl Fn(&SourceInInventory) -> bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        self.sources.retain(|s| {
+            if should_remove(s) {
+                s.source.update(cx, |source, cx| source.on_unregister(cx));
+                false
+            } else {
+                true
+            }
+        });
     }
 
     pub fn source<T: TaskSource>(&self) -> Option<(Model<Box<dyn TaskSource>>, TaskSourceKind)> {
@@ -278,8 +295,9 @@ impl Inventory {
     pub fn task_scheduled(
         &mut self,
         task_source_kind: TaskSourceKind,
-        resolved_task: ResolvedTask,
+        mut resolved_task: ResolvedTask,
     ) {
+        resolved_task.last_run = Some(SystemTime::now());
         self.last_scheduled_tasks
             .push_back((task_source_kind, resolved_task));
         if self.last_scheduled_tasks.len() > 5_000 {
@@ -335,6 +353,8 @@ fn task_source_kind_preference(kind: &TaskSourceKind) -> u32 {
 
 #[cfg(test)]
 mod test_inventory {
+    use std::{cell::RefCell, rc::Rc};
+
     use gpui::{AppContext, Context as _, Model, ModelContext, TestAppContext};
     use itertools::Itertools;
     use task::{TaskContext, TaskId, TaskSource, TaskTemplate, TaskTemplates};
@@ -397,6 +417,40 @@ mod test_inventory {
         }
     }
 
+    pub struct LifecycleTestSource {
+        events: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl LifecycleTestSource {
+        pub(super) fn new(
+            events: Rc<RefCell<Vec<&'static str>>>,
+            cx: &mut AppContext,
+        ) -> Model<Box<dyn TaskSource>> {
+            cx.new_model(|_| Box::new(Self { events }) as Box<dyn TaskSource>)
+        }
+    }
+
+    impl TaskSource for LifecycleTestSource {
+        fn tasks_to_schedule(
+            &mut self,
+            _cx: &mut ModelContext<Box<dyn TaskSource>>,
+        ) -> TaskTemplates {
+            TaskTemplates::default()
+        }
+
+        fn as_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+
+        fn on_register(&mut self, _cx: &mut ModelContext<Box<dyn TaskSource>>) {
+            self.events.borrow_mut().push("register");
+        }
+
+        fn on_unregister(&mut self, _cx: &mut ModelContext<Box<dyn TaskSource>>) {
+            self.events.borrow_mut().push("unregister");
+        }
+    }
+
     pub(super) fn task_template_names(
         inventory: &Model<Inventory>,
         worktree: Option<WorktreeId>,
@@ -475,11 +529,52 @@ mod test_inventory {
 
 #[cfg(test)]
 mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
     use gpui::TestAppContext;
 
     use super::test_inventory::*;
     use super::*;
 
+    #[gpui::test]
+    fn test_source_lifecycle_hooks(cx: &mut TestAppContext) {
+        let inventory = cx.update(Inventory::new);
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let path = Path::new("lifecycle_path");
+        inventory.update(cx, |inventory, cx| {
+            inventory.add_source(
+                TaskSourceKind::AbsPath {
+                    id_base: "test source",
+                    abs_path: path.to_path_buf(),
+                },
+                |cx| LifecycleTestSource::new(events.clone(), cx),
+                cx,
+            );
+        });
+        assert_eq!(*events.borrow(), vec!["register"]);
+
+        inventory.update(cx, |inventory, cx| {
+            inventory.add_source(
+                TaskSourceKind::AbsPath {
+                    id_base: "test source",
+                    abs_path: path.to_path_buf(),
+                },
+                |cx| LifecycleTestSource::new(events.clone(), cx),
+                cx,
+            );
+        });
+        assert_eq!(
+            *events.borrow(),
+            vec!["register"],
+            "Source for the same path should not be registered twice"
+        );
+
+        inventory.update(cx, |inventory, cx| {
+            inventory.remove_local_static_source(path, cx);
+        });
+        assert_eq!(*events.borrow(), vec!["register", "unregister"]);
+    }
+
     #[gpui::test]
     fn test_task_list_sorting(cx: &mut TestAppContext) {
         let inventory = cx.update(Inventory::new);
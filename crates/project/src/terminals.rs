@@ -3,12 +3,14 @@ use collections::HashMap;
 use gpui::{AnyWindowHandle, Context, Entity, Model, ModelContext, WeakModel};
 use settings::Settings;
 use smol::channel::bounded;
-use std::path::{Path, PathBuf};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 use terminal::{
     terminal_settings::{self, Shell, TerminalSettings, VenvSettingsContent},
     SpawnTask, TaskState, TaskStatus, Terminal, TerminalBuilder,
 };
-use util::ResultExt;
 
 // #[cfg(target_os = "macos")]
 // use std::os::unix::ffi::OsStrExt;
@@ -32,6 +34,10 @@ impl Project {
 
         let is_terminal = spawn_task.is_none();
         let settings = TerminalSettings::get_global(cx);
+        let max_scroll_history_lines = spawn_task
+            .as_ref()
+            .and_then(|spawn_task| spawn_task.scrollback)
+            .or(settings.max_scroll_history_lines);
         let python_settings = settings.detect_venv.clone();
         let (completion_tx, completion_rx) = bounded(1);
 
@@ -47,7 +53,12 @@ impl Project {
             env.extend(spawn_task.env);
             // Activate minimal Python virtual environment
             if let Some(python_settings) = &python_settings.as_option() {
-                self.set_python_venv_path_for_tasks(python_settings, venv_base_directory, &mut env);
+                self.set_python_venv_path_for_tasks(
+                    python_settings,
+                    venv_base_directory,
+                    spawn_task.strip_zed_path_additions,
+                    &mut env,
+                );
             }
             (
                 Some(TaskState {
@@ -73,7 +84,7 @@ impl Project {
             env,
             Some(settings.blinking.clone()),
             settings.alternate_scroll,
-            settings.max_scroll_history_lines,
+            max_scroll_history_lines,
             window,
             completion_tx,
         )
@@ -147,6 +158,7 @@ impl Project {
         &mut self,
         settings: &VenvSettingsContent,
         venv_base_directory: &Path,
+        strip_zed_path_additions: bool,
         env: &mut HashMap<String, String>,
     ) {
         let activate_path = settings
@@ -158,25 +170,7 @@ impl Project {
             });
 
         if let Some(path) = activate_path {
-            // Some tools use VIRTUAL_ENV to detect the virtual environment
-            env.insert(
-                "VIRTUAL_ENV".to_string(),
-                path.to_string_lossy().to_string(),
-            );
-
-            let path_bin = path.join("bin");
-            // We need to set the PATH to include the virtual environment's bin directory
-            if let Some(paths) = std::env::var_os("PATH") {
-                let paths = std::iter::once(path_bin).chain(std::env::split_paths(&paths));
-                if let Some(new_path) = std::env::join_paths(paths).log_err() {
-                    env.insert("PATH".to_string(), new_path.to_string_lossy().to_string());
-                }
-            } else {
-                env.insert(
-                    "PATH".to_string(),
-                    path.join("bin").to_string_lossy().to_string(),
-                );
-            }
+            add_python_venv_to_task_env(&path, strip_zed_path_additions, env);
         }
     }
 
@@ -211,4 +205,63 @@ impl Project {
     }
 }
 
+/// Activates the virtual environment for the task, unless the task asked for the env without Zed's additions.
+fn add_python_venv_to_task_env(
+    venv: &Path,
+    strip_zed_path_additions: bool,
+    env: &mut HashMap<String, String>,
+) {
+    if !strip_zed_path_additions {
+        // Some tools use VIRTUAL_ENV to detect the virtual environment,
+        // so it is only set together with the venv's bin directory in the PATH.
+        env.insert(
+            "VIRTUAL_ENV".to_string(),
+            venv.to_string_lossy().to_string(),
+        );
+    }
+
+    // We need to set the PATH to include the virtual environment's bin directory,
+    // unless the task asked for the PATH without Zed's additions.
+    // The PATH the task env already has, merged with Zed's one, is kept after the venv directory.
+    let base_path = env
+        .get("PATH")
+        .map(OsString::from)
+        .or_else(|| std::env::var_os("PATH"));
+    if let Some(new_path) = task::task_path(
+        base_path.as_deref(),
+        [venv.join("bin")],
+        strip_zed_path_additions,
+    ) {
+        if strip_zed_path_additions {
+            // Keep the task's own PATH, if it has one.
+            env.entry("PATH".to_string()).or_insert(new_path);
+        } else {
+            env.insert("PATH".to_string(), new_path);
+        }
+    }
+}
+
 // TODO: Add a few tests for adding and removing terminal tabs
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_venv_in_task_env() {
+        let venv = Path::new("/project/.venv");
+        let task_env = || HashMap::from_iter([("PATH".to_string(), "/usr/bin".to_string())]);
+
+        let mut env = task_env();
+        add_python_venv_to_task_env(venv, false, &mut env);
+        assert_eq!(env["VIRTUAL_ENV"], "/project/.venv");
+        assert!(env["PATH"].starts_with(venv.join("bin").to_string_lossy().as_ref()));
+
+        let mut env = task_env();
+        add_python_venv_to_task_env(venv, true, &mut env);
+        assert_eq!(
+            env,
+            task_env(),
+            "The venv should not be activated for the tasks stripping Zed's PATH additions"
+        );
+    }
+}
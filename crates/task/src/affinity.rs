@@ -0,0 +1,120 @@
+//! Rules of terminal tab reuse between the resolved tasks.
+
+use collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::ResolvedTask;
+
+/// Which resolved tasks share a terminal tab when spawned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReuseScope {
+    /// Tasks with the same [`crate::TaskId`] only.
+    #[default]
+    SameTask,
+    /// Tasks with the same id, or the same command and arguments, even if those come from different templates or contexts.
+    SameCommand,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum AffinityKey<'a> {
+    Id(&'a str),
+    Command(&'a str, &'a [String]),
+}
+
+impl<'a> AffinityKey<'a> {
+    fn new(task: &'a ResolvedTask, reuse: ReuseScope) -> Self {
+        match (reuse, &task.resolved) {
+            (ReuseScope::SameCommand, Some(spawn)) => Self::Command(&spawn.command, &spawn.args),
+            _ => Self::Id(&task.id.0),
+        }
+    }
+}
+
+/// Clusters the tasks that would share a terminal tab under the `reuse` scope.
+/// Groups are ordered by the first appearance of their tasks, tasks in each group keep their order too.
+pub fn group_by_affinity(tasks: &[ResolvedTask], reuse: ReuseScope) -> Vec<Vec<&ResolvedTask>> {
+    let mut group_indices = HashMap::default();
+    let mut groups = Vec::<Vec<&ResolvedTask>>::new();
+    for task in tasks {
+        let group_index = *group_indices
+            .entry(AffinityKey::new(task, reuse))
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[group_index].push(task);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaskContext, TaskTemplate, TaskVariables, VariableName};
+
+    use super::*;
+
+    #[test]
+    fn test_grouping_by_reuse_scope() {
+        let template = |label: &str, command: &str| TaskTemplate {
+            label: label.to_string(),
+            command: command.to_string(),
+            args: vec!["$ZED_FILE".into()],
+            ..TaskTemplate::default()
+        };
+        let context = |file: &str| TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(VariableName::File, file.to_string())]),
+        };
+        let resolve = |template: &TaskTemplate, file: &str| {
+            template.resolve_task("test", context(file)).unwrap()
+        };
+        let check = template("check", "cargo check");
+        let check_alias = template("check alias", "cargo check");
+        let build = template("build", "cargo build");
+        let tasks = vec![
+            resolve(&check, "a.rs"),
+            resolve(&build, "a.rs"),
+            resolve(&check_alias, "a.rs"),
+            resolve(&check, "a.rs"),
+            resolve(&check, "b.rs"),
+        ];
+        fn labels(groups: Vec<Vec<&ResolvedTask>>) -> Vec<Vec<&str>> {
+            groups
+                .into_iter()
+                .map(|group| {
+                    group
+                        .into_iter()
+                        .map(|task| task.resolved_label.as_str())
+                        .collect()
+                })
+                .collect()
+        }
+
+        let same_task = group_by_affinity(&tasks, ReuseScope::SameTask);
+        assert_eq!(same_task.len(), 4);
+        assert_eq!(same_task[0], vec![&tasks[0], &tasks[3]]);
+        assert_eq!(
+            labels(same_task),
+            vec![
+                vec!["check", "check"],
+                vec!["build"],
+                vec!["check alias"],
+                vec!["check"],
+            ]
+        );
+
+        let same_command = group_by_affinity(&tasks, ReuseScope::SameCommand);
+        assert_eq!(same_command.len(), 3);
+        assert_eq!(same_command[2], vec![&tasks[4]]);
+        assert_eq!(
+            labels(same_command),
+            vec![
+                vec!["check", "check alias", "check"],
+                vec!["build"],
+                vec!["check"],
+            ]
+        );
+        assert!(group_by_affinity(&[], ReuseScope::SameCommand).is_empty());
+    }
+}
@@ -0,0 +1,317 @@
+//! Command line length and env size limits, and response files (`@file` arguments) for the command lines too long to pass as is.
+//!
+//! Many toolchains (GCC, Clang, MSVC, javac, rustc and others) accept a single `@path` argument instead of their actual arguments:
+//! the file at `path` contains the arguments, one per line, double-quoted if they contain whitespace, quotes or backslashes,
+//! with the quotes and backslashes escaped by a backslash.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use sha2::{Digest, Sha256};
+
+use crate::{DiagnosticSeverity, SpawnInTerminal, TaskDiagnostic};
+
+/// The default total length of the arguments, after which they are moved into a response file, see [`SpawnInTerminal::use_args_file`].
+/// Slightly below the Windows' `cmd.exe` command line limit of 8191 characters, leaving room for the command itself.
+pub const DEFAULT_ARGS_FILE_THRESHOLD: usize = 8000;
+
+/// The platform limit of the command line length: the total length of the command, the arguments and the env variables on Unix (`ARG_MAX`),
+/// and of the command with the arguments on Windows (`CreateProcess` limit).
+/// Common defaults are used, the actual system configuration is not queried.
+pub const MAX_COMMAND_LENGTH: usize = if cfg!(windows) {
+    32_767
+} else if cfg!(target_os = "macos") {
+    262_144
+} else {
+    2_097_152
+};
+
+/// The default limit of the task env size, see [`SpawnInTerminal::validate_env_size`] and [`crate::TaskTemplates::validate`]:
+/// half of the [`MAX_COMMAND_LENGTH`], leaving room for the command, its arguments and the env inherited from the system.
+pub const DEFAULT_ENV_SIZE_LIMIT: usize = MAX_COMMAND_LENGTH / 2;
+
+/// How many of the largest env variables to name when the env is too large.
+const LARGEST_ENV_ENTRIES_SHOWN: usize = 3;
+
+/// Describes the env being too large, if its `KEY=value` entries take more than `limit` bytes in total, naming the largest ones.
+pub(crate) fn env_size_problem<'a>(
+    env: impl IntoIterator<Item = (&'a str, usize)>,
+    limit: usize,
+) -> Option<String> {
+    let mut entries = env
+        .into_iter()
+        .map(|(key, value_length)| (key, key.len() + value_length + 2))
+        .collect::<Vec<_>>();
+    let size = entries.iter().map(|(_, size)| size).sum::<usize>();
+    if size <= limit {
+        return None;
+    }
+    entries.sort_by(|(key_a, size_a), (key_b, size_b)| size_b.cmp(size_a).then(key_a.cmp(key_b)));
+    let largest = entries
+        .iter()
+        .take(LARGEST_ENV_ENTRIES_SHOWN)
+        .map(|(key, size)| format!("`{key}` ({size} bytes)"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(format!(
+        "The task env takes {size} bytes, exceeding the limit of {limit}, \
+        so the spawn would likely fail; the largest variables: {largest}"
+    ))
+}
+
+impl SpawnInTerminal {
+    /// Warns if the task env, e.g. after the [`SpawnInTerminal::apply_env_command`], takes more than `limit` bytes as `KEY=value` strings,
+    /// usually [`DEFAULT_ENV_SIZE_LIMIT`], naming the largest variables. The env inherited from the settings and the system is not counted.
+    pub fn validate_env_size(&self, limit: usize) -> Option<TaskDiagnostic> {
+        let env = self
+            .env
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.len()));
+        let message = env_size_problem(env, limit)?;
+        Some(TaskDiagnostic {
+            severity: DiagnosticSeverity::Warning,
+            task_index: None,
+            label: Some(self.full_label.clone()),
+            message,
+        })
+    }
+
+    /// An estimate of what the command takes of the [`MAX_COMMAND_LENGTH`]: the command and its arguments, with the separators,
+    /// and on Unix also the task env as `KEY=value` strings. The env inherited from the settings and the system is not counted.
+    pub fn estimated_command_length(&self) -> usize {
+        let command_line = self.command.len() + 1 + self.args_length();
+        if cfg!(windows) {
+            command_line
+        } else {
+            command_line
+                + self
+                    .env
+                    .iter()
+                    .map(|(key, value)| key.len() + value.len() + 2)
+                    .sum::<usize>()
+        }
+    }
+
+    /// Warns if the [`SpawnInTerminal::estimated_command_length`] exceeds the `limit`, usually [`MAX_COMMAND_LENGTH`],
+    /// so that the spawn would likely fail, suggesting to use a response file instead.
+    pub fn validate_command_length(&self, limit: usize) -> Option<TaskDiagnostic> {
+        let length = self.estimated_command_length();
+        (length > limit).then(|| TaskDiagnostic {
+            severity: DiagnosticSeverity::Warning,
+            task_index: None,
+            label: Some(self.full_label.clone()),
+            message: format!(
+                "The command line is about {length} characters long, exceeding the platform limit of {limit}; \
+                consider passing the arguments in a response file with `args_file`"
+            ),
+        })
+    }
+
+    /// Total length of the arguments on the command line, with the separating spaces.
+    pub fn args_length(&self) -> usize {
+        self.args.iter().map(|arg| arg.len() + 1).sum()
+    }
+
+    /// Moves the arguments into a response file created in `dir`, replacing them with a single `@path` argument,
+    /// if [`SpawnInTerminal::args_file`] is set, or if their [`SpawnInTerminal::args_length`] exceeds the `threshold`.
+    /// Returns the path of the response file, if the arguments were moved. Files with the same arguments are reused.
+    pub fn use_args_file(
+        &mut self,
+        threshold: usize,
+        dir: &Path,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        if self.args.is_empty() || !(self.args_file || self.args_length() > threshold) {
+            return Ok(None);
+        }
+        let contents = self
+            .args
+            .iter()
+            .map(|arg| quote_response_file_arg(arg))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut hasher = Sha256::new();
+        hasher.update(contents.as_bytes());
+        let digest = hex::encode(hasher.finalize());
+        let path = dir.join(format!("zed-task-args-{}.rsp", &digest[..16]));
+        std::fs::write(&path, contents)
+            .with_context(|| format!("writing task arguments file {path:?}"))?;
+        self.args = vec![format!("@{}", path.display())];
+        Ok(Some(path))
+    }
+}
+
+fn quote_response_file_arg(arg: &str) -> String {
+    if !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\'' || c == '\\')
+    {
+        return arg.to_string();
+    }
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaskArg, TaskContext, TaskTemplate};
+
+    use super::*;
+
+    #[test]
+    fn test_long_args_are_moved_into_response_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sources = (0..2000)
+            .map(|i| format!("src/file_{i}.c"))
+            .collect::<Vec<_>>();
+        let template = TaskTemplate {
+            label: "compile".to_string(),
+            command: "cc".to_string(),
+            args: sources.iter().cloned().map(TaskArg::from).collect(),
+            ..TaskTemplate::default()
+        };
+        let resolve = |template: &TaskTemplate| {
+            template
+                .resolve_task("test", TaskContext::default())
+                .unwrap()
+                .resolved
+                .unwrap()
+        };
+
+        let mut spawn = resolve(&template);
+        assert!(spawn.args_length() > DEFAULT_ARGS_FILE_THRESHOLD);
+        let path = spawn
+            .use_args_file(DEFAULT_ARGS_FILE_THRESHOLD, temp_dir.path())
+            .unwrap()
+            .expect("Long argument lists should be moved into a response file");
+        assert_eq!(spawn.args, vec![format!("@{}", path.display())]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), sources.join("\n"));
+
+        let mut spawn = resolve(&TaskTemplate {
+            args: template.args[..3].to_vec(),
+            ..template.clone()
+        });
+        assert_eq!(
+            spawn
+                .use_args_file(DEFAULT_ARGS_FILE_THRESHOLD, temp_dir.path())
+                .unwrap(),
+            None
+        );
+        assert_eq!(spawn.args, sources[..3].to_vec());
+        assert!(
+            spawn.use_args_file(10, temp_dir.path()).unwrap().is_some(),
+            "The threshold should be configurable"
+        );
+    }
+
+    #[test]
+    fn test_forced_response_file_quoting() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut spawn = TaskTemplate {
+            label: "compile".to_string(),
+            command: "cc".to_string(),
+            args: vec![
+                "-DNAME=\"my app\"".into(),
+                "C:\\include dir".into(),
+                String::new().into(),
+                "-O2".into(),
+            ],
+            args_file: true,
+            ..TaskTemplate::default()
+        }
+        .resolve_task("test", TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        let path = spawn
+            .use_args_file(DEFAULT_ARGS_FILE_THRESHOLD, temp_dir.path())
+            .unwrap()
+            .expect("Arguments should always be moved with `args_file` set");
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "\"-DNAME=\\\"my app\\\"\"\n\"C:\\\\include dir\"\n\"\"\n-O2"
+        );
+    }
+
+    #[test]
+    fn test_command_length_validation() {
+        let mut spawn = TaskTemplate {
+            label: "test".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["test".into(), "--".into(), "my test".into()],
+            ..TaskTemplate::default()
+        }
+        .resolve_task("test", TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert_eq!(
+            spawn.estimated_command_length(),
+            "cargo test -- my test ".len()
+        );
+        assert_eq!(spawn.validate_command_length(MAX_COMMAND_LENGTH), None);
+
+        spawn.args = vec!["x".repeat(1000); MAX_COMMAND_LENGTH / 1000 + 1];
+        let diagnostic = spawn
+            .validate_command_length(MAX_COMMAND_LENGTH)
+            .expect("Huge argument lists should be reported");
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Warning);
+        assert!(
+            diagnostic.message.contains("`args_file`"),
+            "Unexpected message: {}",
+            diagnostic.message
+        );
+
+        spawn.args_file = true;
+        spawn
+            .use_args_file(
+                DEFAULT_ARGS_FILE_THRESHOLD,
+                tempfile::tempdir().unwrap().path(),
+            )
+            .unwrap();
+        assert_eq!(spawn.validate_command_length(MAX_COMMAND_LENGTH), None);
+    }
+
+    #[test]
+    fn test_env_size_validation() {
+        let spawn_with_env = |env: &[(&str, usize)]| SpawnInTerminal {
+            full_label: "deploy".to_string(),
+            env: env
+                .iter()
+                .map(|(key, length)| (key.to_string(), "x".repeat(*length)))
+                .collect(),
+            ..SpawnInTerminal::default()
+        };
+
+        let normal = spawn_with_env(&[("HOME", 10), ("AWS_REGION", 9)]);
+        assert_eq!(normal.validate_env_size(DEFAULT_ENV_SIZE_LIMIT), None);
+
+        let oversized = spawn_with_env(&[
+            ("SMALL", 10),
+            ("HUGE_TOKEN", 60_000),
+            ("BIG_LIST", 30_000),
+            ("MEDIUM", 500),
+            ("TINY", 1),
+        ]);
+        let diagnostic = oversized.validate_env_size(32_000).unwrap();
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostic.label.as_deref(), Some("deploy"));
+        assert!(
+            diagnostic.message.contains(
+                "the largest variables: `HUGE_TOKEN` (60012 bytes), `BIG_LIST` (30010 bytes), `MEDIUM` (508 bytes)"
+            ),
+            "Unexpected message: {}",
+            diagnostic.message
+        );
+        assert!(!diagnostic.message.contains("SMALL"));
+        assert_eq!(oversized.validate_env_size(usize::MAX), None);
+    }
+}
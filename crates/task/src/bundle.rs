@@ -0,0 +1,223 @@
+//! A self-contained description of a task run to share, e.g. in bug reports, see [`ResolvedTask::to_bundle`].
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    is_secret_like, relative_file_path, EnvValue, ResolvedTask, SpawnInTerminal, TaskId, TaskKind,
+    TaskTemplate, REDACTED_VALUE,
+};
+
+/// Everything needed to reproduce a task run elsewhere: the resolved command line, the env and the template the task came from.
+/// Values of the secret-looking env variables, e.g. `GITHUB_TOKEN`, are replaced with `***`, keeping the variables themselves,
+/// and the cwd is relative to the worktree root, if it is inside of it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskBundle {
+    /// The [`ResolvedTask::id`].
+    pub id: TaskId,
+    /// The [`ResolvedTask::resolved_label`].
+    pub label: String,
+    /// The resolved command.
+    pub command: String,
+    /// The resolved arguments.
+    pub args: Vec<String>,
+    /// The resolved working directory, relative to the worktree root if it is inside of it, absolute otherwise.
+    pub cwd: Option<PathBuf>,
+    /// The resolved env, sorted, with the secret values redacted.
+    pub env: BTreeMap<String, String>,
+    /// The template the task got resolved from, with the secret values redacted in all of its env variables,
+    /// including the platform overrides and the locale ones.
+    pub template: TaskTemplate,
+}
+
+impl ResolvedTask {
+    /// Exports the task run to share, see [`TaskBundle`]; the cwd is made relative to the `worktree_root`, if given.
+    /// Tasks running actions have no command line, their bundles only have the label and the template.
+    pub fn to_bundle(&self, worktree_root: Option<&Path>) -> TaskBundle {
+        let spawn = self.resolved.clone().unwrap_or_default();
+        let cwd = spawn.cwd.map(|cwd| {
+            worktree_root
+                .and_then(|worktree_root| relative_file_path(&cwd, worktree_root))
+                .unwrap_or(cwd)
+        });
+        let env = spawn
+            .env
+            .into_iter()
+            .map(|(key, value)| {
+                let value = if is_secret_like(&key) {
+                    REDACTED_VALUE.to_string()
+                } else {
+                    value
+                };
+                (key, value)
+            })
+            .collect();
+        let mut template = self.original_task.clone();
+        let platform_envs = template
+            .platform_overrides
+            .values_mut()
+            .flat_map(|platform_override| &mut platform_override.env);
+        for (key, value) in template.env.iter_mut().chain(platform_envs) {
+            if is_secret_like(key) {
+                *value = EnvValue::String(REDACTED_VALUE.to_string());
+            }
+        }
+        for (key, value) in template.locale_env.iter_mut().flatten() {
+            if is_secret_like(key) {
+                *value = REDACTED_VALUE.to_string();
+            }
+        }
+        TaskBundle {
+            id: self.id.clone(),
+            label: self.resolved_label.clone(),
+            command: spawn.command,
+            args: spawn.args,
+            cwd,
+            env,
+            template,
+        }
+    }
+}
+
+impl TaskBundle {
+    /// Imports the bundle as a task to re-run, with the relative cwd joined to the `worktree_root`, if given.
+    /// The redacted env values are `***`, replace them in the [`SpawnInTerminal::env`] before running, if the task needs them.
+    pub fn into_resolved_task(self, worktree_root: Option<&Path>) -> ResolvedTask {
+        let cwd = match (self.cwd, worktree_root) {
+            (Some(cwd), Some(worktree_root)) if cwd.is_relative() => Some(worktree_root.join(cwd)),
+            (cwd, _) => cwd,
+        };
+        let kind = match &self.template.action {
+            Some(action) => TaskKind::Action(action.clone()),
+            None => TaskKind::Spawn,
+        };
+        let resolved = (kind == TaskKind::Spawn).then(|| SpawnInTerminal {
+            id: self.id.clone(),
+            full_label: self.label.clone(),
+            label: self.label.clone(),
+            command: self.command,
+            args: self.args,
+            cwd,
+            env: self.env.into_iter().collect(),
+            ..SpawnInTerminal::default()
+        });
+        ResolvedTask {
+            id: self.id,
+            original_task: self.template,
+            resolved_label: self.label,
+            resolved,
+            last_run: None,
+            kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use collections::HashMap;
+
+    use crate::{Platform, PlatformOverride, TaskContext, TaskVariables, VariableName};
+
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip_and_redaction() {
+        let template = TaskTemplate {
+            label: "publish ${ZED_CUSTOM_PACKAGE}".to_string(),
+            command: "cargo".to_string(),
+            args: vec![
+                "publish".into(),
+                "-p".into(),
+                "${ZED_CUSTOM_PACKAGE}".into(),
+            ],
+            cwd: Some("${ZED_WORKTREE_ROOT}/crates/task".to_string()),
+            env: HashMap::from_iter([
+                ("CARGO_REGISTRY_TOKEN".to_string(), "s3cr3t".into()),
+                ("RUST_LOG".to_string(), "debug".into()),
+            ]),
+            platform_overrides: BTreeMap::from_iter([(
+                Platform::Windows,
+                PlatformOverride {
+                    env: BTreeMap::from_iter([
+                        ("GITHUB_TOKEN".to_string(), "ghp_s3cr3t".into()),
+                        ("CARGO_HOME".to_string(), "C:\\cargo".into()),
+                    ]),
+                    ..PlatformOverride::default()
+                },
+            )]),
+            locale_env: Some(BTreeMap::from_iter([
+                ("LC_ALL".to_string(), "C".to_string()),
+                ("LOCALE_PASSWORD".to_string(), "hunter2".to_string()),
+            ])),
+            ..TaskTemplate::default()
+        };
+        let task = template
+            .resolve_task(
+                "test",
+                TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([
+                        (VariableName::WorktreeRoot, "/project".to_string()),
+                        (VariableName::Custom("PACKAGE".into()), "task".to_string()),
+                    ]),
+                },
+            )
+            .unwrap();
+
+        let bundle = task.to_bundle(Some(Path::new("/project")));
+        assert_eq!(bundle.label, "publish task");
+        assert_eq!(bundle.command, "cargo");
+        assert_eq!(bundle.args, vec!["publish", "-p", "task"]);
+        assert_eq!(bundle.cwd, Some(PathBuf::from("crates/task")));
+        assert_eq!(
+            bundle.env,
+            BTreeMap::from_iter([
+                ("CARGO_REGISTRY_TOKEN".to_string(), "***".to_string()),
+                ("LC_ALL".to_string(), "C".to_string()),
+                ("LOCALE_PASSWORD".to_string(), "***".to_string()),
+                ("RUST_LOG".to_string(), "debug".to_string()),
+                ("ZED_CUSTOM_PACKAGE".to_string(), "task".to_string()),
+                ("ZED_WORKTREE_ROOT".to_string(), "/project".to_string()),
+            ])
+        );
+        assert_eq!(
+            bundle.template.env["CARGO_REGISTRY_TOKEN"],
+            EnvValue::String("***".to_string())
+        );
+        assert_eq!(bundle.template.args, template.args);
+        let windows_env = &bundle.template.platform_overrides[&Platform::Windows].env;
+        assert_eq!(
+            windows_env["GITHUB_TOKEN"],
+            EnvValue::String("***".to_string())
+        );
+        assert_eq!(
+            windows_env["CARGO_HOME"],
+            EnvValue::from("C:\\cargo".to_string())
+        );
+        let locale_env = bundle.template.locale_env.as_ref().unwrap();
+        assert_eq!(locale_env["LOCALE_PASSWORD"], "***");
+        assert_eq!(locale_env["LC_ALL"], "C");
+
+        let json = serde_json_lenient::to_string(&bundle).unwrap();
+        assert!(!json.contains("s3cr3t"));
+        assert!(!json.contains("hunter2"));
+        let imported = serde_json_lenient::from_str::<TaskBundle>(&json).unwrap();
+        assert_eq!(imported, bundle);
+
+        let rerun = imported.into_resolved_task(Some(Path::new("/elsewhere")));
+        let spawn = rerun.resolved.as_ref().unwrap();
+        assert_eq!(spawn.command, "cargo");
+        assert_eq!(spawn.args, vec!["publish", "-p", "task"]);
+        assert_eq!(spawn.cwd, Some(PathBuf::from("/elsewhere/crates/task")));
+        assert_eq!(spawn.env["RUST_LOG"], "debug");
+        assert_eq!(rerun.kind, TaskKind::Spawn);
+        assert_eq!(rerun.id, task.id);
+
+        let outside = task.to_bundle(Some(Path::new("/other")));
+        assert_eq!(outside.cwd, Some(PathBuf::from("/project/crates/task")));
+    }
+}
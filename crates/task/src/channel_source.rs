@@ -0,0 +1,69 @@
+//! A source of tasks, fed by other threads through a channel.
+
+use futures::{
+    channel::mpsc::{unbounded, UnboundedSender},
+    StreamExt,
+};
+use gpui::{AppContext, Context, Model, ModelContext};
+
+use crate::{TaskSource, TaskTemplates};
+
+/// The source of tasks, produced elsewhere (e.g. on a background thread) and sent to it via [`ChannelSource::new`]'s sender.
+/// Every templates sent replace the previous ones.
+pub struct ChannelSource {
+    tasks: TaskTemplates,
+}
+
+impl ChannelSource {
+    /// Initializes the source, returning it along with the sender to update its templates with.
+    /// The sender is [`Send`], so it can be moved into background tasks or threads; the source stops updating once all senders are dropped.
+    pub fn new(
+        cx: &mut AppContext,
+    ) -> (Model<Box<dyn TaskSource>>, UnboundedSender<TaskTemplates>) {
+        let (sender, mut receiver) = unbounded::<TaskTemplates>();
+        let source = cx.new_model(|cx| {
+            cx.spawn(|source, mut cx| async move {
+                while let Some(new_tasks) = receiver.next().await {
+                    source.update(&mut cx, |source: &mut Box<dyn TaskSource>, cx| {
+                        if let Some(channel_source) = source.as_any().downcast_mut::<Self>() {
+                            channel_source.tasks = new_tasks;
+                            cx.notify();
+                        }
+                    })?;
+                }
+                anyhow::Ok(())
+            })
+            .detach_and_log_err(cx);
+            Box::new(Self {
+                tasks: TaskTemplates::default(),
+            }) as Box<dyn TaskSource>
+        });
+        (source, sender)
+    }
+}
+
+impl TaskSource for ChannelSource {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn tasks_to_schedule(&mut self, _: &mut ModelContext<Box<dyn TaskSource>>) -> TaskTemplates {
+        self.tasks.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn test_thread_safety_bounds() {
+        assert_send::<TaskTemplates>();
+        assert_sync::<TaskTemplates>();
+        assert_send::<UnboundedSender<TaskTemplates>>();
+        assert_send::<ChannelSource>();
+    }
+}
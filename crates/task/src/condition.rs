@@ -0,0 +1,115 @@
+//! A mini-expression language of the conditions deciding what a template includes at resolution time,
+//! e.g. the conditional arguments, see [`TaskTemplate::args`](crate::TaskTemplate::args).
+//!
+//! A condition is a variable name, e.g. `verbose` for the `ZED_CUSTOM_verbose` custom variable or `ZED_FILE` for the builtin one,
+//! true when the variable is set to anything but an empty string, `0` or `false`.
+//! Names may be negated with `!`, compared with a literal with `==` and `!=`, e.g. `profile == release`,
+//! and combined with `&&` and `||`, the former binding tighter; there are no parentheses.
+
+use anyhow::{bail, Context as _};
+use collections::HashMap;
+
+use crate::{VariableName, ZED_VARIABLE_NAME_PREFIX};
+
+/// Evaluates the condition against the env form of the task variables, failing on malformed conditions.
+pub(crate) fn evaluate_condition(
+    condition: &str,
+    task_variables: &HashMap<String, String>,
+) -> anyhow::Result<bool> {
+    let mut result = false;
+    for alternative in condition.split("||") {
+        let mut alternative_holds = true;
+        for term in alternative.split("&&") {
+            alternative_holds &= evaluate_term(term, task_variables)
+                .with_context(|| format!("evaluating condition `{condition}`"))?;
+        }
+        result |= alternative_holds;
+    }
+    Ok(result)
+}
+
+fn evaluate_term(term: &str, task_variables: &HashMap<String, String>) -> anyhow::Result<bool> {
+    let term = term.trim();
+    if let Some(negated) = term.strip_prefix('!') {
+        return Ok(!evaluate_term(negated, task_variables)?);
+    }
+    let comparison = term
+        .split_once("!=")
+        .map(|(name, literal)| (name, literal, false))
+        .or_else(|| {
+            term.split_once("==")
+                .map(|(name, literal)| (name, literal, true))
+        });
+    match comparison {
+        Some((name, literal, equal)) => {
+            let value = variable_value(name, task_variables)?.unwrap_or_default();
+            Ok((value == unquote(literal.trim())) == equal)
+        }
+        None => Ok(variable_value(term, task_variables)?.is_some_and(|value| {
+            !value.is_empty() && value != "0" && !value.eq_ignore_ascii_case("false")
+        })),
+    }
+}
+
+fn variable_value<'a>(
+    name: &str,
+    task_variables: &'a HashMap<String, String>,
+) -> anyhow::Result<Option<&'a str>> {
+    let name = name.trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        bail!("expected a variable name, got `{name}`");
+    }
+    let value = if name.starts_with(ZED_VARIABLE_NAME_PREFIX) {
+        task_variables.get(name)
+    } else {
+        task_variables.get(&VariableName::Custom(name.to_string().into()).to_string())
+    };
+    Ok(value.map(String::as_str))
+}
+
+fn unquote(literal: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| {
+            literal
+                .strip_prefix(quote)
+                .and_then(|literal| literal.strip_suffix(quote))
+        })
+        .unwrap_or(literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conditions() {
+        let task_variables = HashMap::from_iter([
+            ("ZED_CUSTOM_verbose".to_string(), "1".to_string()),
+            ("ZED_CUSTOM_quiet".to_string(), "false".to_string()),
+            ("ZED_CUSTOM_profile".to_string(), "release".to_string()),
+            ("ZED_FILE".to_string(), "/project/main.rs".to_string()),
+        ]);
+        let evaluate = |condition| evaluate_condition(condition, &task_variables).unwrap();
+        assert!(evaluate("verbose"));
+        assert!(!evaluate("quiet"));
+        assert!(!evaluate("missing"));
+        assert!(evaluate("!missing"));
+        assert!(evaluate("ZED_FILE"));
+        assert!(evaluate("profile == release"));
+        assert!(evaluate("profile == 'release'"));
+        assert!(!evaluate("profile != release"));
+        assert!(evaluate("missing != release"));
+        assert!(!evaluate("verbose && quiet"));
+        assert!(evaluate("quiet || verbose && profile == release"));
+        assert!(!evaluate("quiet || verbose && missing"));
+
+        assert!(evaluate_condition("", &task_variables).is_err());
+        assert!(evaluate_condition("verbose &&", &task_variables).is_err());
+        assert!(evaluate_condition("${ZED_FILE}", &task_variables).is_err());
+    }
+}
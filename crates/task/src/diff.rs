@@ -0,0 +1,182 @@
+//! Comparison of the task sets by their behavior, ignoring the incidental differences, see [`TaskTemplates::diff`] and [`task_files_equivalent`].
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    parse_task_file, static_source::normalize_line_endings, ParseError, TaskTemplate,
+    TaskTemplates, VsCodeTaskFile,
+};
+
+/// A format of a tasks file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TaskFileFormat {
+    /// Zed's `tasks.json`, a list of [`TaskTemplate`]s.
+    #[default]
+    Zed,
+    /// Code's `.vscode/tasks.json`, converted into Zed templates, see [`VsCodeTaskFile`].
+    VsCode,
+}
+
+/// The labels of the tasks that differ between two sets of templates, see [`TaskTemplates::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DiffSummary {
+    /// Labels of the tasks present only in the new set, sorted.
+    pub added: Vec<String>,
+    /// Labels of the tasks present only in the old set, sorted.
+    pub removed: Vec<String>,
+    /// Labels of the tasks present in both sets, but doing different things, sorted.
+    pub changed: Vec<String>,
+}
+
+impl DiffSummary {
+    /// Whether both sets of tasks behave the same, that is, they differ only cosmetically, if at all.
+    pub fn is_equivalent(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl TaskTemplates {
+    /// Compares the templates with the `new` ones by their labels, ignoring the order of the templates:
+    /// the templates with the same label are changed if any of their fields differ, see [`TaskTemplate::content_hash`].
+    /// The inheritance is flattened first, so that moving the fields between a template and its parent is not a change.
+    pub fn diff(&self, new: &TaskTemplates) -> DiffSummary {
+        let by_label = |templates: &TaskTemplates| {
+            let mut by_label = BTreeMap::<String, Vec<u64>>::new();
+            for template in templates.resolve_inheritance().0 {
+                by_label.entry(template.label.clone()).or_default().push(
+                    TaskTemplate {
+                        order: None,
+                        ..template
+                    }
+                    .content_hash(),
+                );
+            }
+            for hashes in by_label.values_mut() {
+                hashes.sort_unstable();
+            }
+            by_label
+        };
+        let old = by_label(self);
+        let new = by_label(new);
+        let mut summary = DiffSummary::default();
+        for (label, old_hashes) in &old {
+            match new.get(label) {
+                Some(new_hashes) if new_hashes == old_hashes => {}
+                Some(_) => summary.changed.push(label.clone()),
+                None => summary.removed.push(label.clone()),
+            }
+        }
+        summary.added = new
+            .keys()
+            .filter(|label| !old.contains_key(*label))
+            .cloned()
+            .collect();
+        summary
+    }
+}
+
+/// Parses two versions of a tasks file and compares their tasks, e.g. to tell whether a change of the file is cosmetic only:
+/// reordering the tasks or their fields, reformatting, or adding comments, are not reported, see [`TaskTemplates::diff`].
+pub fn task_files_equivalent(
+    old: &str,
+    new: &str,
+    format: TaskFileFormat,
+) -> Result<DiffSummary, ParseError> {
+    let parse = |contents: &str| match format {
+        TaskFileFormat::Zed => parse_task_file(contents),
+        TaskFileFormat::VsCode => {
+            serde_json_lenient::from_str::<VsCodeTaskFile>(&normalize_line_endings(contents))
+                .map_err(|e| ParseError::new(format!("Failed to parse the file: {e}")))
+                .and_then(|file| {
+                    TaskTemplates::try_from(file)
+                        .map_err(|e| ParseError::new(format!("Failed to convert the tasks: {e}")))
+                })
+        }
+    };
+    Ok(parse(old)?.diff(&parse(new)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASKS: &str = r#"[
+        {"label": "build", "command": "cargo", "args": ["build"]},
+        {"label": "test", "command": "cargo", "args": ["test"], "env": {"A": "1", "B": "2"}},
+        {"label": "lint", "command": "cargo", "args": ["clippy"]}
+    ]"#;
+
+    #[test]
+    fn test_cosmetic_changes_are_equivalent() {
+        let reformatted = r#"
+            // Tasks of the project.
+            [
+                {
+                    "args": ["clippy"],
+                    "label": "lint",
+                    "command": "cargo",
+                },
+                {"label": "build", "command": "cargo", "args": ["build"]},
+                {"env": {"B": "2", "A": "1"}, "label": "test", "command": "cargo", "args": ["test"]},
+            ]
+        "#;
+        let summary = task_files_equivalent(TASKS, reformatted, TaskFileFormat::Zed).unwrap();
+        assert!(summary.is_equivalent(), "Unexpected changes: {summary:?}");
+
+        let with_parent = r#"[
+            {"label": "cargo", "command": "cargo", "hidden": true},
+            {"label": "build", "extends": "cargo", "args": ["build"]}
+        ]"#;
+        let summary = task_files_equivalent(
+            r#"[
+                {"label": "cargo", "command": "cargo", "hidden": true},
+                {"label": "build", "command": "cargo", "extends": "cargo", "args": ["build"]}
+            ]"#,
+            with_parent,
+            TaskFileFormat::Zed,
+        )
+        .unwrap();
+        assert!(summary.is_equivalent(), "Unexpected changes: {summary:?}");
+    }
+
+    #[test]
+    fn test_behavioral_changes() {
+        let changed = r#"[
+            {"label": "build", "command": "cargo", "args": ["build", "--release"]},
+            {"label": "test", "command": "cargo", "args": ["test"], "env": {"A": "1", "B": "2"}},
+            {"label": "fmt", "command": "cargo", "args": ["fmt"]}
+        ]"#;
+        let summary = task_files_equivalent(TASKS, changed, TaskFileFormat::Zed).unwrap();
+        assert_eq!(
+            summary,
+            DiffSummary {
+                added: vec!["fmt".to_string()],
+                removed: vec!["lint".to_string()],
+                changed: vec!["build".to_string()],
+            }
+        );
+        assert!(!summary.is_equivalent());
+
+        let error =
+            task_files_equivalent(TASKS, r#"[{"label": 1}]"#, TaskFileFormat::Zed).unwrap_err();
+        assert_eq!(error.path.as_deref(), Some("tasks[0].label"));
+    }
+
+    #[test]
+    fn test_vscode_files() {
+        let old = r#"{"version": "2.0.0", "tasks": [{"label": "build", "type": "shell", "command": "make"}]}"#;
+        let new = r#"{
+            "version": "2.0.0",
+            "tasks": [
+                {"type": "shell", "command": "make", "label": "build"},
+                {"label": "clean", "type": "shell", "command": "make clean"}
+            ]
+        }"#;
+        let summary = task_files_equivalent(old, new, TaskFileFormat::VsCode).unwrap();
+        assert_eq!(summary.added, vec!["clean"]);
+        assert!(summary.removed.is_empty() && summary.changed.is_empty());
+        assert!(task_files_equivalent(old, "[]", TaskFileFormat::VsCode).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Env variables sourced from the output of a command, see [`crate::TaskTemplate::env_command`].
+//!
+//! Running the command has side effects, so it happens right before the spawn, in [`SpawnInTerminal::apply_env_command`],
+//! and never during the resolution, which only substitutes the variables in the command.
+
+use std::{
+    io::Read as _,
+    path::Path,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context as _};
+
+use crate::{env_file::parse_env_file, static_source::normalize_line_endings, SpawnInTerminal};
+
+/// The default time for the [`SpawnInTerminal::env_command`] to finish in, see [`SpawnInTerminal::apply_env_command`].
+pub const DEFAULT_ENV_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs the [`SpawnInTerminal::env_command`]s, see [`ShellEnvCommandRunner`].
+pub trait EnvCommandRunner {
+    /// Runs the shell command in the `cwd`, returning its stdout.
+    /// Fails if the command fails, or does not finish within the `timeout`.
+    fn run(&self, command: &str, cwd: Option<&Path>, timeout: Duration) -> anyhow::Result<String>;
+}
+
+/// Runs the commands with `sh -c` (`cmd /C` on Windows), killing the ones which do not finish in time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShellEnvCommandRunner;
+
+impl EnvCommandRunner for ShellEnvCommandRunner {
+    fn run(&self, command: &str, cwd: Option<&Path>, timeout: Duration) -> anyhow::Result<String> {
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+        shell
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        if let Some(cwd) = cwd {
+            shell.current_dir(cwd);
+        }
+        let mut child = shell
+            .spawn()
+            .with_context(|| format!("spawning env command `{command}`"))?;
+        let mut stdout = child.stdout.take().context("missing env command stdout")?;
+        // Read the output concurrently, so that the command does not block on a full pipe.
+        let reader = std::thread::spawn(move || {
+            let mut output = String::new();
+            stdout.read_to_string(&mut output).map(|_| output)
+        });
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                child.kill().ok();
+                child.wait().ok();
+                bail!("env command `{command}` did not finish in {timeout:?}");
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        let output = reader
+            .join()
+            .map_err(|_| anyhow!("reading the output of env command `{command}`"))?
+            .with_context(|| format!("reading the output of env command `{command}`"))?;
+        if !status.success() {
+            bail!("env command `{command}` failed with {status}");
+        }
+        Ok(output)
+    }
+}
+
+impl SpawnInTerminal {
+    /// Runs the [`SpawnInTerminal::env_command`], if any, in the task's cwd, and adds the variables it printed to the env.
+    /// The variables already in the env, from the template's `env` and `env_file` and the task context, are kept.
+    ///
+    /// The command may have any side effects, call this right before spawning the task.
+    /// Fails if the command fails, does not finish within the `timeout` (usually [`DEFAULT_ENV_COMMAND_TIMEOUT`]),
+    /// or prints anything but the env variable definitions; the env is left unchanged then.
+    pub fn apply_env_command(
+        &mut self,
+        runner: &dyn EnvCommandRunner,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        let Some(env_command) = self.env_command.as_deref() else {
+            return Ok(());
+        };
+        let output = runner.run(env_command, self.cwd.as_deref(), timeout)?;
+        let variables = parse_env_file(&normalize_line_endings(&output))
+            .with_context(|| format!("parsing the output of env command `{env_command}`"))?;
+        for (key, value) in variables {
+            self.env.entry(key).or_insert(value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, path::PathBuf};
+
+    use crate::{TaskContext, TaskTemplate, TaskVariables, VariableName};
+
+    use super::*;
+
+    struct MockRunner {
+        output: &'static str,
+        calls: RefCell<Vec<(String, Option<PathBuf>)>>,
+    }
+
+    impl EnvCommandRunner for MockRunner {
+        fn run(&self, command: &str, cwd: Option<&Path>, _: Duration) -> anyhow::Result<String> {
+            self.calls
+                .borrow_mut()
+                .push((command.to_string(), cwd.map(Path::to_path_buf)));
+            Ok(self.output.to_string())
+        }
+    }
+
+    fn resolve(template_json: &str) -> SpawnInTerminal {
+        let template: TaskTemplate = serde_json_lenient::from_str(template_json).unwrap();
+        template
+            .resolve_task(
+                "test",
+                TaskContext {
+                    cwd: Some(PathBuf::from("/project")),
+                    task_variables: TaskVariables::from_iter([(
+                        VariableName::Custom("PROFILE".into()),
+                        "dev".to_string(),
+                    )]),
+                },
+            )
+            .unwrap()
+            .resolved
+            .unwrap()
+    }
+
+    #[test]
+    fn test_env_command_output_is_merged() {
+        let mut spawn = resolve(
+            r#"{
+                "label": "deploy",
+                "command": "deploy",
+                "env": {"AWS_REGION": "eu-west-1"},
+                "env_command": "aws configure export-credentials --profile $ZED_CUSTOM_PROFILE --format env"
+            }"#,
+        );
+        let runner = MockRunner {
+            output: "export AWS_ACCESS_KEY_ID=AKIA\r\nexport AWS_SECRET_ACCESS_KEY=\"s3cr3t\"\n\n# comment\nAWS_REGION=us-east-1\n",
+            calls: RefCell::default(),
+        };
+        spawn
+            .apply_env_command(&runner, DEFAULT_ENV_COMMAND_TIMEOUT)
+            .unwrap();
+        assert_eq!(
+            runner.calls.into_inner(),
+            vec![(
+                "aws configure export-credentials --profile dev --format env".to_string(),
+                Some(PathBuf::from("/project"))
+            )]
+        );
+        assert_eq!(spawn.env["AWS_ACCESS_KEY_ID"], "AKIA");
+        assert_eq!(spawn.env["AWS_SECRET_ACCESS_KEY"], "s3cr3t");
+        assert_eq!(
+            spawn.env["AWS_REGION"], "eu-west-1",
+            "Inline env should win over the command output"
+        );
+
+        let invalid_runner = MockRunner {
+            output: "Unable to locate credentials\n",
+            calls: RefCell::default(),
+        };
+        let env_before = spawn.env.clone();
+        assert!(spawn
+            .apply_env_command(&invalid_runner, DEFAULT_ENV_COMMAND_TIMEOUT)
+            .is_err());
+        assert_eq!(spawn.env, env_before);
+
+        let mut spawn = resolve(r#"{"label": "deploy", "command": "deploy"}"#);
+        spawn
+            .apply_env_command(&invalid_runner, DEFAULT_ENV_COMMAND_TIMEOUT)
+            .unwrap();
+        assert_eq!(
+            invalid_runner.calls.borrow().len(),
+            1,
+            "Tasks without env commands should not run anything"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_env_command_timeout() {
+        let mut spawn = resolve(
+            r#"{"label": "deploy", "command": "deploy", "env_command": "sleep 30; echo TOKEN=late"}"#,
+        );
+        spawn.cwd = None;
+        let env_before = spawn.env.clone();
+        let started = Instant::now();
+        let error = spawn
+            .apply_env_command(&ShellEnvCommandRunner, Duration::from_millis(100))
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("did not finish"),
+            "Unexpected error: {error}"
+        );
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "The slow command should be killed at the deadline"
+        );
+        assert_eq!(spawn.env, env_before);
+
+        spawn.env_command = Some("sleep 0.1; echo TOKEN=early".to_string());
+        spawn
+            .apply_env_command(&ShellEnvCommandRunner, DEFAULT_ENV_COMMAND_TIMEOUT)
+            .unwrap();
+        assert_eq!(spawn.env["TOKEN"], "early");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shell_runner() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = ShellEnvCommandRunner
+            .run(
+                "echo TOKEN=$(basename \"$PWD\")",
+                Some(temp_dir.path()),
+                DEFAULT_ENV_COMMAND_TIMEOUT,
+            )
+            .unwrap();
+        let dir_name = temp_dir.path().file_name().unwrap().to_string_lossy();
+        assert_eq!(output, format!("TOKEN={dir_name}\n"));
+
+        let started = Instant::now();
+        let error = ShellEnvCommandRunner
+            .run("sleep 30", None, Duration::from_millis(100))
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("did not finish"),
+            "Unexpected error: {error}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(ShellEnvCommandRunner
+            .run("exit 3", None, DEFAULT_ENV_COMMAND_TIMEOUT)
+            .is_err());
+    }
+}
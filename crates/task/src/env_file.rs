@@ -0,0 +1,156 @@
+//! Parsing of the environment files, referenced by the task templates, see [`crate::TaskTemplate::env_file`].
+//!
+//! The file is read right before the spawn, in [`SpawnInTerminal::apply_env_file`], so that the resolved tasks
+//! (and their caches) never hold stale file contents, and the resolution does not touch the file system.
+
+use anyhow::{bail, Context};
+
+use crate::{static_source::normalize_line_endings, SpawnInTerminal};
+
+impl SpawnInTerminal {
+    /// Reads the [`SpawnInTerminal::env_file`], if any, and adds its variables to the env:
+    /// the variables already in the env, from the template's `env` and the task context, are kept.
+    ///
+    /// Call this right before spawning the task, before [`SpawnInTerminal::apply_env_command`].
+    /// Fails if the file cannot be read or parsed; the env is left unchanged then.
+    pub fn apply_env_file(&mut self) -> anyhow::Result<()> {
+        let Some(env_file) = &self.env_file else {
+            return Ok(());
+        };
+        let contents = std::fs::read_to_string(env_file)
+            .with_context(|| format!("reading env file {env_file:?}"))?;
+        let variables = parse_env_file(&normalize_line_endings(&contents))
+            .with_context(|| format!("parsing env file {env_file:?}"))?;
+        for (key, value) in variables {
+            self.env.entry(key).or_insert(value);
+        }
+        Ok(())
+    }
+}
+
+/// Parses the environment file contents into the variable definitions, in the order of their declaration.
+///
+/// The format is detected per line, so both formats may be mixed in one file:
+/// * dotenv — `KEY=VALUE`
+/// * shell exports, as used by `.envrc` files — `export KEY=VALUE`
+///
+/// Blank lines and lines starting with `#` are skipped.
+/// Values may be wrapped in single or double quotes; double-quoted values support `\"`, `\\` and `\n` escapes,
+/// everything else is taken literally. No expansion happens inside the file: `$VAR` and `${...}` are kept as is in the values.
+pub(crate) fn parse_env_file(contents: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut variables = Vec::new();
+    for (line_index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = parse_env_line(line)
+            .with_context(|| format!("parsing env file line {}", line_index + 1))?;
+        variables.push((key.to_string(), value));
+    }
+    Ok(variables)
+}
+
+fn parse_env_line(line: &str) -> anyhow::Result<(&str, String)> {
+    let definition = match line.strip_prefix("export") {
+        Some(exported) if exported.starts_with(char::is_whitespace) => exported.trim_start(),
+        _ => line,
+    };
+    let Some((key, value)) = definition.split_once('=') else {
+        bail!("expected a `KEY=VALUE` definition, got `{line}`");
+    };
+    let key = key.trim();
+    let is_valid_key = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_valid_key {
+        bail!("invalid variable name `{key}`");
+    }
+    Ok((key, parse_env_value(value.trim())?))
+}
+
+fn parse_env_value(value: &str) -> anyhow::Result<String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        let Some(end) = quoted.find('\'') else {
+            bail!("unterminated single-quoted value");
+        };
+        return Ok(quoted[..end].to_string());
+    }
+    if let Some(quoted) = value.strip_prefix('"') {
+        let mut parsed = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(parsed),
+                '\\' => match chars.next() {
+                    Some('n') => parsed.push('\n'),
+                    Some(escaped @ ('"' | '\\')) => parsed.push(escaped),
+                    Some(other) => {
+                        parsed.push('\\');
+                        parsed.push(other);
+                    }
+                    None => break,
+                },
+                c => parsed.push(c),
+            }
+        }
+        bail!("unterminated double-quoted value");
+    }
+    let value = match value.find(" #") {
+        Some(comment_start) => &value[..comment_start],
+        None => value,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_dotenv_and_export_lines() {
+        let contents = r#"
+            # Shared settings
+            RUST_LOG=debug
+            export RUST_BACKTRACE=1
+            export   DATABASE_URL="postgres://localhost/zed \"dev\""
+            GREETING='hello ${USER}' # single quotes are literal
+            PLAIN_REFERENCE=${HOME}/bin # no expansion for unquoted values either
+            exported_name=value
+            EMPTY=
+        "#;
+        assert_eq!(
+            parse_env_file(contents).unwrap(),
+            vec![
+                ("RUST_LOG".to_string(), "debug".to_string()),
+                ("RUST_BACKTRACE".to_string(), "1".to_string()),
+                (
+                    "DATABASE_URL".to_string(),
+                    r#"postgres://localhost/zed "dev""#.to_string()
+                ),
+                ("GREETING".to_string(), "hello ${USER}".to_string()),
+                ("PLAIN_REFERENCE".to_string(), "${HOME}/bin".to_string()),
+                ("exported_name".to_string(), "value".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_file_lines() {
+        for invalid_contents in [
+            "export",
+            "NO_VALUE",
+            "1STARTS_WITH_DIGIT=1",
+            "KEY=\"unterminated",
+            "OK=1\nexport WITH SPACE=1",
+        ] {
+            assert!(
+                parse_env_file(invalid_contents).is_err(),
+                "Expected `{invalid_contents}` to fail parsing"
+            );
+        }
+    }
+}
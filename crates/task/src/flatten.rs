@@ -0,0 +1,323 @@
+//! Expansion of the templates into the concrete ones a user could pick, see [`TaskTemplates::flatten`]:
+//! the [`TaskTemplate::matrix`] combinations and the [`TaskTemplate::platform_overrides`].
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{EnvValue, TaskArg, TaskTemplate, TaskTemplates};
+
+/// A prefix of the references to the matrix axis values, see [`TaskTemplate::matrix`].
+const MATRIX_REFERENCE_PREFIX: &str = "${matrix:";
+
+/// An operating system Zed runs on, to pick the [`TaskTemplate::platform_overrides`] for.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    /// Linux, and other Unix systems but macOS.
+    Linux,
+    /// macOS.
+    Macos,
+    /// Windows.
+    Windows,
+}
+
+impl Platform {
+    /// The platform Zed is built for.
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Self::Windows
+        } else if cfg!(target_os = "macos") {
+            Self::Macos
+        } else {
+            Self::Linux
+        }
+    }
+}
+
+/// Template fields to use on a particular [`Platform`] instead of the common ones, see [`TaskTemplate::platform_overrides`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PlatformOverride {
+    /// The command to run instead of the template's one.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// The arguments to use instead of the template's ones.
+    #[serde(default)]
+    pub args: Option<Vec<TaskArg>>,
+    /// Env variables to add to the template's ones, overriding the same variables there.
+    #[serde(default)]
+    pub env: BTreeMap<String, EnvValue>,
+    /// The working directory to use instead of the template's one.
+    #[serde(default)]
+    pub cwd: Option<String>,
+}
+
+impl PlatformOverride {
+    /// All fields that may contain variable references.
+    pub(crate) fn template_strings(&self) -> impl Iterator<Item = &str> {
+        self.command
+            .as_deref()
+            .into_iter()
+            .chain(self.args.iter().flatten().map(TaskArg::value))
+            .chain(
+                self.env
+                    .values()
+                    .flat_map(EnvValue::parts)
+                    .map(String::as_str),
+            )
+            .chain(self.cwd.as_deref())
+    }
+}
+
+impl TaskTemplate {
+    /// The template with the [`TaskTemplate::platform_overrides`] for the platform given applied, and no overrides left.
+    pub fn for_platform(mut self, platform: Platform) -> Self {
+        let mut overrides = std::mem::take(&mut self.platform_overrides);
+        if let Some(platform_override) = overrides.remove(&platform) {
+            if let Some(command) = platform_override.command {
+                self.command = command;
+            }
+            if let Some(args) = platform_override.args {
+                self.args = args;
+            }
+            self.env.extend(platform_override.env);
+            if platform_override.cwd.is_some() {
+                self.cwd = platform_override.cwd;
+            }
+        }
+        self
+    }
+
+    /// A template for every combination of the [`TaskTemplate::matrix`] axis values, with the `${matrix:<axis>}` references substituted,
+    /// and with no matrix left; a template without a matrix is returned as is.
+    /// Labels without the references get the combination appended, e.g. `test (os=linux, toolchain=stable)`, to tell the templates apart.
+    pub fn expand_matrix(mut self) -> Vec<Self> {
+        let matrix = std::mem::take(&mut self.matrix);
+        if matrix.is_empty() {
+            return vec![self];
+        }
+        let mut combinations = vec![Vec::new()];
+        for (axis, values) in &matrix {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination: Vec<(&str, &str)>| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((axis.as_str(), value.as_str()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations
+            .into_iter()
+            .map(|combination| {
+                let mut template = self.clone();
+                let label_has_references = template.label.contains(MATRIX_REFERENCE_PREFIX);
+                for string in template.matrix_strings_mut() {
+                    for (axis, value) in &combination {
+                        *string =
+                            string.replace(&format!("{MATRIX_REFERENCE_PREFIX}{axis}}}"), value);
+                    }
+                }
+                if !label_has_references {
+                    let combination = combination
+                        .iter()
+                        .map(|(axis, value)| format!("{axis}={value}"))
+                        .collect::<Vec<_>>();
+                    template.label = format!("{} ({})", template.label, combination.join(", "));
+                }
+                template
+            })
+            .collect()
+    }
+
+    fn matrix_strings_mut(&mut self) -> impl Iterator<Item = &mut String> {
+        [&mut self.label, &mut self.command]
+            .into_iter()
+            .chain(self.args.iter_mut().map(TaskArg::value_mut))
+            .chain(self.env.values_mut().flat_map(EnvValue::parts_mut))
+            .chain(self.cwd.iter_mut())
+            .chain(self.context_label.iter_mut())
+    }
+}
+
+impl TaskTemplates {
+    /// The templates as loaded for the platform given: with the platform overrides applied, then the inheritance flattened,
+    /// so that the children get the overridden fields of their parents, and the matrices expanded.
+    pub fn for_platform(&self, platform: Platform) -> TaskTemplates {
+        let overridden = Self(
+            self.0
+                .iter()
+                .map(|template| template.clone().for_platform(platform))
+                .collect(),
+        );
+        Self(
+            overridden
+                .resolve_inheritance()
+                .0
+                .into_iter()
+                .flat_map(TaskTemplate::expand_matrix)
+                .collect(),
+        )
+    }
+
+    /// The concrete templates a user could pick on the platform given, e.g. for a "run everything" command:
+    /// the [`TaskTemplates::for_platform`] ones, with the [`TaskTemplate::hidden`] templates and the templates that cannot run left out.
+    pub fn flatten(&self, platform: Platform) -> TaskTemplates {
+        Self(
+            self.for_platform(platform)
+                .0
+                .into_iter()
+                .filter(|template| !template.hidden && template.is_runnable())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten() {
+        let templates: TaskTemplates = serde_json_lenient::from_str(
+            r#"[
+                {
+                    "label": "cargo",
+                    "command": "cargo",
+                    "args": ["test", "--workspace"],
+                    "env": {"RUST_BACKTRACE": "1"},
+                    "hidden": true,
+                    "platform_overrides": {"windows": {"command": "cargo.exe"}}
+                },
+                {
+                    "label": "test ${matrix:target} (${matrix:toolchain})",
+                    "extends": "cargo",
+                    "args": ["+${matrix:toolchain}", "${inherit}", "--target=${matrix:target}"],
+                    "matrix": {
+                        "toolchain": ["stable", "nightly"],
+                        "target": ["x86_64", "aarch64", "wasm32"]
+                    },
+                    "platform_overrides": {
+                        "windows": {"env": {"CARGO_TARGET_DIR": "C:\\target"}},
+                        "macos": {"args": ["+${matrix:toolchain}", "test"]}
+                    }
+                },
+                {
+                    "label": "lint",
+                    "command": "cargo",
+                    "args": ["clippy"],
+                    "matrix": {"toolchain": []}
+                }
+            ]"#,
+        )
+        .unwrap();
+
+        let linux = templates.flatten(Platform::Linux);
+        assert_eq!(
+            linux.0.len(),
+            6,
+            "Hidden parent and empty matrix should produce nothing"
+        );
+        let labels = linux
+            .0
+            .iter()
+            .map(|template| template.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec![
+                "test x86_64 (stable)",
+                "test x86_64 (nightly)",
+                "test aarch64 (stable)",
+                "test aarch64 (nightly)",
+                "test wasm32 (stable)",
+                "test wasm32 (nightly)",
+            ]
+        );
+        let first = &linux.0[0];
+        assert_eq!(first.command, "cargo");
+        assert_eq!(
+            first.args,
+            ["+stable", "test", "--workspace", "--target=x86_64"].map(TaskArg::from)
+        );
+        assert_eq!(first.env["RUST_BACKTRACE"], EnvValue::from("1".to_string()));
+        assert!(linux.0.iter().all(|template| template.matrix.is_empty()
+            && template.platform_overrides.is_empty()
+            && template.extends.is_none()));
+
+        let windows = templates.flatten(Platform::Windows);
+        assert_eq!(windows.0.len(), 6);
+        assert!(windows
+            .0
+            .iter()
+            .all(|template| template.env.contains_key("CARGO_TARGET_DIR")));
+        assert!(
+            windows
+                .0
+                .iter()
+                .all(|template| template.command == "cargo.exe"),
+            "Overrides of the parent should be inherited"
+        );
+
+        let macos = templates.flatten(Platform::Macos);
+        assert_eq!(macos.0.len(), 6);
+        assert_eq!(macos.0[1].args, ["+nightly", "test"].map(TaskArg::from));
+    }
+
+    #[test]
+    fn test_resolving_with_platform_overrides() {
+        let template = TaskTemplate {
+            label: "build".to_string(),
+            command: "make".to_string(),
+            platform_overrides: BTreeMap::from_iter([(
+                Platform::current(),
+                PlatformOverride {
+                    command: Some("build.cmd".to_string()),
+                    ..PlatformOverride::default()
+                },
+            )]),
+            ..TaskTemplate::default()
+        };
+        let resolved = template
+            .resolve_task("test", crate::TaskContext::default())
+            .unwrap();
+        assert_eq!(resolved.resolved.unwrap().command, "build.cmd");
+        assert_eq!(resolved.original_task, template);
+    }
+
+    #[test]
+    fn test_matrix_labels() {
+        let template = TaskTemplate {
+            label: "build".to_string(),
+            command: "make".to_string(),
+            args: vec!["${matrix:profile}".into()],
+            matrix: BTreeMap::from_iter([(
+                "profile".to_string(),
+                vec!["debug".to_string(), "release".to_string()],
+            )]),
+            ..TaskTemplate::default()
+        };
+        let expanded = template.clone().expand_matrix();
+        assert_eq!(
+            expanded
+                .iter()
+                .map(|template| (template.label.as_str(), template.args.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("build (profile=debug)", vec!["debug".into()]),
+                ("build (profile=release)", vec!["release".into()]),
+            ]
+        );
+        let plain = TaskTemplate {
+            matrix: BTreeMap::default(),
+            ..template
+        };
+        assert_eq!(plain.clone().expand_matrix(), vec![plain]);
+    }
+}
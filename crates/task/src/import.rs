@@ -0,0 +1,96 @@
+//! Merging of the task templates, imported from multiple sources (e.g. task files of different formats), into a single list.
+
+use collections::HashMap;
+use serde::Serialize;
+
+use crate::TaskTemplates;
+
+/// A label collision found while merging the imported templates, see [`merge_imported_templates`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConflictReport {
+    /// The label shared by the templates.
+    pub label: String,
+    /// Name of the source, which template got into the merged list.
+    pub kept_source: String,
+    /// Name of the source, which template got dropped.
+    pub dropped_source: String,
+}
+
+/// Merges the templates of the named sources, given in the order of their priority, into a single list.
+///
+/// When a label is used by multiple templates, the first template with it wins, and every other one is dropped and reported as a conflict.
+/// Templates keep their relative order in the merged list.
+pub fn merge_imported_templates<'a>(
+    sources: impl IntoIterator<Item = (&'a str, TaskTemplates)>,
+) -> (TaskTemplates, Vec<ConflictReport>) {
+    let mut merged = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut sources_by_label = HashMap::<String, &str>::default();
+    for (source_name, templates) in sources {
+        for template in templates.0 {
+            if let Some(kept_source) = sources_by_label.get(&template.label) {
+                conflicts.push(ConflictReport {
+                    label: template.label,
+                    kept_source: kept_source.to_string(),
+                    dropped_source: source_name.to_string(),
+                });
+            } else {
+                sources_by_label.insert(template.label.clone(), source_name);
+                merged.push(template);
+            }
+        }
+    }
+    (TaskTemplates(merged), conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaskTemplate, VsCodeTaskFile};
+
+    use super::*;
+
+    #[test]
+    fn test_conflicts_of_mixed_format_merge() {
+        let native = TaskTemplates(
+            ["build", "lint"]
+                .into_iter()
+                .map(|label| TaskTemplate {
+                    label: label.to_string(),
+                    command: "make".to_string(),
+                    args: vec![label.into()],
+                    ..TaskTemplate::default()
+                })
+                .collect(),
+        );
+        let vscode = serde_json_lenient::from_str::<VsCodeTaskFile>(
+            r#"{
+                "version": "2.0.0",
+                "tasks": [
+                    { "label": "test", "type": "shell", "command": "npm", "args": ["test"] },
+                    { "label": "build", "type": "shell", "command": "npm", "args": ["run", "build"] },
+                    { "label": "lint", "type": "npm", "script": "lint" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let vscode = TaskTemplates::try_from(vscode).unwrap();
+
+        let (merged, conflicts) =
+            merge_imported_templates([("tasks.json", native), (".vscode/tasks.json", vscode)]);
+        let merged = merged
+            .0
+            .iter()
+            .map(|template| (template.label.as_str(), template.command.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            merged,
+            vec![("build", "make"), ("lint", "make"), ("test", "npm")]
+        );
+        let conflict = |label: &str| ConflictReport {
+            label: label.to_string(),
+            kept_source: "tasks.json".to_string(),
+            dropped_source: ".vscode/tasks.json".to_string(),
+        };
+        assert_eq!(conflicts, vec![conflict("build"), conflict("lint")]);
+    }
+}
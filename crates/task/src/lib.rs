@@ -1,27 +1,90 @@
 //! Baseline interface of Tasks in Zed: all tasks in Zed are intended to use those for implementing their own logic.
 #![deny(missing_docs)]
 
+mod affinity;
+mod args_file;
+mod bundle;
+pub mod channel_source;
+mod condition;
+mod diff;
+mod env_command;
+mod env_file;
+mod flatten;
+mod import;
+mod lsp_command;
+mod migration;
+mod quoting;
+mod ranking;
+mod resolution_cache;
+mod resolving_context;
+mod run_as;
+mod session;
 pub mod static_source;
+mod summary;
 mod task_template;
+mod template_builder;
+mod terminal_defaults;
+mod transform;
+mod validation;
 mod vscode_format;
 
+use anyhow::{bail, Context as _};
 use collections::HashMap;
 use gpui::ModelContext;
-use serde::Serialize;
+use serde::{Deserialize, Serialize, Serializer};
 use std::any::Any;
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-pub use task_template::{RevealStrategy, TaskTemplate, TaskTemplates};
+pub use affinity::{group_by_affinity, ReuseScope};
+pub use args_file::{DEFAULT_ARGS_FILE_THRESHOLD, DEFAULT_ENV_SIZE_LIMIT, MAX_COMMAND_LENGTH};
+pub use bundle::TaskBundle;
+pub use diff::{task_files_equivalent, DiffSummary, TaskFileFormat};
+pub use env_command::{EnvCommandRunner, ShellEnvCommandRunner, DEFAULT_ENV_COMMAND_TIMEOUT};
+pub use flatten::{Platform, PlatformOverride};
+pub use import::{merge_imported_templates, ConflictReport};
+pub use migration::{MigrationReport, TASKS_FORMAT_VERSION};
+pub use quoting::QuotingDialect;
+pub use ranking::{RankInfo, APPLICABLE_BOOST, CONTEXTUAL_BOOST};
+pub use resolution_cache::ResolutionCache;
+pub use resolving_context::{
+    ResolutionWarning, ResolvingContext, VariableProviders, DEFAULT_PROVIDER_TIMEOUT,
+};
+pub use session::TaskSession;
+pub use summary::ResolvedTaskSummary;
+pub use task_template::{
+    ArgSegment, EnvValue, FieldTrace, OutputMode, PathMode, ReadyProbe, ResolutionTrace,
+    ResolveOptions, RevealStrategy, RevealTarget, StopSignal, TagCounts, TaskArg, TaskGroup,
+    TaskInput, TaskTemplate, TaskTemplates, TemplateOrder, UnresolvedPlaceholder,
+    VariableSubstitution,
+};
+pub use template_builder::TaskTemplateBuilder;
+pub use terminal_defaults::TerminalDefaults;
+pub use transform::TemplateTransform;
+pub use validation::{
+    parse_task_file, validate_task_file, DiagnosticSeverity, ParseError, TaskDiagnostic,
+    ValidationReport,
+};
 pub use vscode_format::VsCodeTaskFile;
 
+/// Env variables that disable the output buffering of the common runtimes, see [`SpawnInTerminal::apply_line_buffering`].
+const LINE_BUFFERING_ENV: &[(&str, &str)] = &[("PYTHONUNBUFFERED", "1")];
+
 /// Task identifier, unique within the application.
 /// Based on it, task reruns and terminal tabs are managed.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TaskId(pub String);
 
 /// Contains all information needed by Zed to spawn a new terminal tab for the given task.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Deserializes with the defaults for the missing fields, so that the tasks persisted by the older versions still load.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SpawnInTerminal {
     /// Id of the task to use when determining task tab affinity.
     pub id: TaskId,
@@ -36,6 +99,7 @@ pub struct SpawnInTerminal {
     /// Current working directory to spawn the command into.
     pub cwd: Option<PathBuf>,
     /// Env overrides for the command, will be appended to the terminal's environment from the settings.
+    #[serde(serialize_with = "serialize_sorted_map")]
     pub env: HashMap<String, String>,
     /// Whether to use a new terminal tab or reuse the existing one to spawn the process.
     pub use_new_terminal: bool,
@@ -43,10 +107,282 @@ pub struct SpawnInTerminal {
     pub allow_concurrent_runs: bool,
     /// What to do with the terminal pane and tab, after the command was started.
     pub reveal: RevealStrategy,
+    /// Whether to print the command line, see [`SpawnInTerminal::display_command`], before running it.
+    pub echo_command: bool,
+    /// A message to ask the user to confirm before spawning the task, if any.
+    pub confirm: Option<String>,
+    /// A signal to stop the process with when the task is cancelled, if the default one should not be used.
+    pub stop_signal: Option<StopSignal>,
+    /// How long to wait for the process to exit after the `stop_signal` was sent, before killing it.
+    pub stop_timeout_ms: Option<u64>,
+    /// A user to run the process as, by name or uid. Unix only.
+    pub run_as_user: Option<String>,
+    /// A group to run the process as, by name or gid. Unix only.
+    pub run_as_group: Option<String>,
+    /// Where to show the output of the process.
+    pub output_mode: OutputMode,
+    /// Name of a group of tasks that should not run simultaneously with this one.
+    pub mutex_group: Option<String>,
+    /// A command to run after the process exits successfully.
+    pub on_success: Option<String>,
+    /// A command to run after the process exits with a failure.
+    pub on_failure: Option<String>,
+    /// Whether the process should not be killed when its terminal tab or the workspace is closed.
+    pub detached: bool,
+    /// Whether to create a missing working directory on spawn, see [`SpawnInTerminal::ensure_cwd`].
+    pub create_cwd: bool,
+    /// Whether to always pass the arguments in a response file, see [`SpawnInTerminal::use_args_file`].
+    pub args_file: bool,
+    /// Where to put the terminal tab of the task.
+    pub reveal_target: RevealTarget,
+    /// Whether to ask the process to flush its output line by line, see [`SpawnInTerminal::apply_line_buffering`].
+    pub force_line_buffered: bool,
+    /// A file to copy the process output into, if any.
+    pub log_file: Option<PathBuf>,
+    /// Whether to append the output to the `log_file`, instead of overwriting it.
+    pub log_append: bool,
+    /// A shell command printing the env variables to add to the `env`, see [`SpawnInTerminal::apply_env_command`].
+    pub env_command: Option<String>,
+    /// The encoding to decode the process output with, UTF-8 if not set.
+    pub output_encoding: Option<String>,
+    /// A name of the terminal profile to apply before the task's own settings, if any.
+    pub terminal_profile: Option<String>,
+    /// Whether to keep the directories Zed prepends to the `PATH` out of the task env, see [`task_path`].
+    pub strip_zed_path_additions: bool,
+    /// A check telling when the spawned task becomes ready to use, if any, see [`TaskTemplate::ready_when`].
+    pub ready_when: Option<ReadyProbe>,
+    /// How the `PATH` of the `env` combines with the inherited one, see [`SpawnInTerminal::merge_env_from`].
+    pub path_mode: PathMode,
+    /// Whether the `command` is a complete command line to run verbatim by the shell, with no `args`, see [`TaskTemplate::raw_command`].
+    pub raw_command: bool,
+    /// The shell to run the task in, see [`TaskTemplate::shell`]; the default one if not set, see [`SpawnInTerminal::apply_defaults`].
+    pub shell: Option<String>,
+    /// The maximum number of lines to keep in the terminal scrollback, see [`TaskTemplate::scrollback`]; the default one if not set.
+    pub scrollback: Option<usize>,
+    /// An absolute path to the file to add the env variables from, see [`SpawnInTerminal::apply_env_file`].
+    pub env_file: Option<PathBuf>,
+    /// Candidates for the `cwd` with the task variables substituted, see [`SpawnInTerminal::apply_cwd_candidates`].
+    pub cwd_candidates: Vec<String>,
+}
+
+impl SpawnInTerminal {
+    /// A human-readable form of the command line: the command, followed by its arguments,
+    /// with the ones containing whitespace or shell-specific characters quoted for POSIX shells.
+    /// See [`SpawnInTerminal::command_line`] for the other shells.
+    pub fn display_command(&self) -> String {
+        self.command_line(QuotingDialect::Posix)
+    }
+
+    /// The command line as a single list, with the command as the first element followed by the arguments, as `execvp`-like APIs expect.
+    /// The [`SpawnInTerminal::raw_command`] commands are run with `sh -c`, or `cmd /C` on Windows.
+    pub fn argv(&self) -> Vec<String> {
+        if self.raw_command {
+            let (shell, flag) = if cfg!(windows) {
+                ("cmd", "/C")
+            } else {
+                ("sh", "-c")
+            };
+            return vec![shell.to_owned(), flag.to_owned(), self.command.clone()];
+        }
+        std::iter::once(self.command.clone())
+            .chain(self.args.iter().cloned())
+            .collect()
+    }
+
+    /// The env to spawn the task with: the `inherited` env, e.g. the one of the terminal, overridden by the task env,
+    /// except for the `PATH`, that combines the task's and the inherited directories according to the [`SpawnInTerminal::path_mode`].
+    pub fn merge_env_from(&self, inherited: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut env = inherited.clone();
+        for (key, value) in &self.env {
+            let inherited_path = inherited
+                .iter()
+                .find(|(inherited_key, _)| is_path_key(key) && is_path_key(inherited_key))
+                .map(|(inherited_key, inherited_path)| (inherited_key.clone(), inherited_path));
+            let value = match (self.path_mode, inherited_path) {
+                (_, None) => value.clone(),
+                (PathMode::Replace, Some((inherited_key, _))) => {
+                    env.remove(&inherited_key);
+                    value.clone()
+                }
+                (path_mode, Some((inherited_key, inherited_path))) => {
+                    env.remove(&inherited_key);
+                    let task_paths = std::env::split_paths(value);
+                    let inherited_paths = std::env::split_paths(inherited_path);
+                    let paths = if path_mode == PathMode::Prepend {
+                        task_paths.chain(inherited_paths).collect::<Vec<_>>()
+                    } else {
+                        inherited_paths.chain(task_paths).collect()
+                    };
+                    match std::env::join_paths(paths) {
+                        Ok(path) => path.to_string_lossy().into_owned(),
+                        Err(_) => value.clone(),
+                    }
+                }
+            };
+            env.insert(key.clone(), value);
+        }
+        env
+    }
+
+    /// A text to print before running the command, if the task was configured to echo its command.
+    pub fn command_echo(&self) -> Option<String> {
+        self.echo_command.then(|| self.display_command())
+    }
+
+    /// The text the terminal shows before the process output, followed by a newline: the [`SpawnInTerminal::command_echo`],
+    /// with a note about the working directory if the task has one. `None` if the task does not echo its command.
+    pub fn output_banner(&self) -> Option<String> {
+        let mut banner = self.command_echo()?;
+        if let Some(cwd) = &self.cwd {
+            banner.push_str(&format!("\n(in {})", cwd.display()));
+        }
+        Some(banner)
+    }
+
+    /// Adds the env variables, that make the runtimes known to buffer it, such as Python, flush their output line by line, to the task env,
+    /// if [`SpawnInTerminal::force_line_buffered`] is set. Values set in the task env are kept.
+    ///
+    /// Only env variables are used, so this works the same way on all platforms, but only for the tools respecting them:
+    /// `stdbuf` is not used, as it is only available with GNU coreutils and has no effect on statically linked programs.
+    /// Processes spawned in a terminal are usually line buffered already, as their output is a PTY.
+    pub fn apply_line_buffering(&mut self) {
+        if !self.force_line_buffered {
+            return;
+        }
+        for (key, value) in LINE_BUFFERING_ENV {
+            self.env
+                .entry((*key).to_owned())
+                .or_insert_with(|| (*value).to_owned());
+        }
+    }
+
+    /// Moves the [`TaskTemplate::wrapper`] into the command line: the first wrapper element becomes the command,
+    /// followed by the rest of the wrapper and the [`SpawnInTerminal::argv`] of the task, e.g. `cargo build` under `time` becomes `time cargo build`.
+    /// Does nothing if the wrapper is empty.
+    pub(crate) fn wrap(&mut self, mut wrapper: Vec<String>) {
+        if wrapper.is_empty() {
+            return;
+        }
+        let mut args = wrapper.split_off(1);
+        args.extend(self.argv());
+        self.command = wrapper.remove(0);
+        self.args = args;
+        self.raw_command = false;
+    }
+
+    /// Replaces the `cwd` with the first of the [`SpawnInTerminal::cwd_candidates`] that is an existing directory, if any,
+    /// see [`TaskTemplate::cwd_candidates`]. Checks the file system and Zed's env, so call this right before the spawn.
+    pub fn apply_cwd_candidates(&mut self) {
+        let candidates = std::mem::take(&mut self.cwd_candidates);
+        if let Some(cwd) =
+            task_template::first_existing_cwd(&candidates, |name| std::env::var(name).ok())
+        {
+            self.cwd = Some(cwd);
+        }
+    }
+
+    /// Makes sure the working directory of the task exists before the spawn:
+    /// creates it with all its parents if [`SpawnInTerminal::create_cwd`] is set, fails if it is not set and the directory is missing.
+    pub fn ensure_cwd(&self) -> anyhow::Result<()> {
+        let Some(cwd) = &self.cwd else {
+            return Ok(());
+        };
+        if cwd.is_dir() {
+            return Ok(());
+        }
+        if self.create_cwd {
+            std::fs::create_dir_all(cwd)
+                .with_context(|| format!("creating task working directory {cwd:?}"))
+        } else {
+            bail!("Task working directory {cwd:?} does not exist")
+        }
+    }
+
+    /// Creates a command to spawn the task process directly, without a terminal, from the [`SpawnInTerminal::argv`].
+    ///
+    /// On Unix, the process is switched to [`SpawnInTerminal::run_as_user`] and [`SpawnInTerminal::run_as_group`] right before the exec;
+    /// on other platforms these are ignored with a warning.
+    pub fn to_std_command(&self) -> anyhow::Result<std::process::Command> {
+        let argv = self.argv();
+        let mut command = std::process::Command::new(&argv[0]);
+        command.args(&argv[1..]).envs(&self.env);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        if let Some(warning) = run_as::run_as_warning(self, cfg!(unix)) {
+            log::warn!("{warning}");
+        }
+        run_as::apply_run_as(
+            &mut command,
+            self.run_as_user.as_deref(),
+            self.run_as_group.as_deref(),
+        )?;
+        Ok(command)
+    }
+}
+
+/// Quotes the string for POSIX shells, if it contains any characters that the shell may interpret.
+pub fn posix_quote(arg: &str) -> Cow<'_, str> {
+    let is_safe_char = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe_char) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
+    }
+}
+
+/// The `PATH` to spawn the task with: Zed's `additions` for its integrations, followed by the `base_path` the task would get outside of Zed,
+/// or the `base_path` alone, if [`SpawnInTerminal::strip_zed_path_additions`] is set.
+/// `None` if there is nothing to put into the `PATH`, or its entries cannot be joined.
+pub fn task_path(
+    base_path: Option<&OsStr>,
+    additions: impl IntoIterator<Item = PathBuf>,
+    strip_zed_path_additions: bool,
+) -> Option<String> {
+    let base_paths = base_path.into_iter().flat_map(std::env::split_paths);
+    let paths = if strip_zed_path_additions {
+        base_paths.collect::<Vec<_>>()
+    } else {
+        additions.into_iter().chain(base_paths).collect()
+    };
+    if paths.is_empty() {
+        return None;
+    }
+    let path = std::env::join_paths(paths).ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
+/// Whether the env variable is the `PATH`, named case-insensitively on Windows.
+fn is_path_key(key: &str) -> bool {
+    if cfg!(windows) {
+        key.eq_ignore_ascii_case("PATH")
+    } else {
+        key == "PATH"
+    }
+}
+
+/// Serializes the map with string forms of its keys, sorted,
+/// so that the output does not depend on the map's iteration order.
+pub(crate) fn serialize_sorted_map<K, V, S>(
+    map: &HashMap<K, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    K: Display,
+    V: Serialize,
+    S: Serializer,
+{
+    let mut entries = map
+        .iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect::<Vec<_>>();
+    entries.sort_by(|(key_a, _), (key_b, _)| key_a.cmp(key_b));
+    serializer.collect_map(entries)
 }
 
 /// A final form of the [`TaskTemplate`], that got resolved with a particualar [`TaskContext`] and now is ready to spawn the actual task.
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// See [`ResolvedTaskSummary`] for a compact form to persist, without the `original_task`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ResolvedTask {
     /// A way to distinguish tasks produced by the same template, but different contexts.
     /// NOTE: Resolved tasks may have the same labels, commands and do the same things,
@@ -61,6 +397,101 @@ pub struct ResolvedTask {
     /// Further actions that need to take place after the resolved task is spawned,
     /// with all task variables resolved.
     pub resolved: Option<SpawnInTerminal>,
+    /// When the task was last run, if ever.
+    /// Not set during the resolution, embedders are expected to populate it from their task history.
+    pub last_run: Option<SystemTime>,
+    /// What the task does when scheduled.
+    pub kind: TaskKind,
+}
+
+/// What a [`ResolvedTask`] does when scheduled.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    /// Spawns a process in the terminal, described by [`ResolvedTask::resolved`].
+    #[default]
+    Spawn,
+    /// Runs an application action with the name given, see [`TaskTemplate::action`].
+    /// [`ResolvedTask::resolved`] is always `None` for such tasks, as there is nothing to spawn.
+    Action(String),
+}
+
+impl ResolvedTask {
+    /// Compares tasks for ordering by recency: the most recently run tasks go first,
+    /// tasks that were never run go last, and ties are broken by the resolved label.
+    pub fn cmp_by_recency(&self, other: &Self) -> Ordering {
+        other
+            .last_run
+            .cmp(&self.last_run)
+            .then_with(|| self.resolved_label.cmp(&other.resolved_label))
+    }
+
+    /// Whether the task is the same as the other one, e.g. restored from the task history, judging by its content rather than its [`ResolvedTask::id`]:
+    /// the original template label, the kind, and the resolved command, args and cwd are compared.
+    /// Unlike the equality, the ids may differ, so that the persisted tasks still match after the way the ids get derived changes.
+    pub fn matches_persisted(&self, other: &ResolvedTask) -> bool {
+        let same_spawn = match (&self.resolved, &other.resolved) {
+            (Some(spawn), Some(other_spawn)) => {
+                spawn.command == other_spawn.command
+                    && spawn.args == other_spawn.args
+                    && spawn.cwd == other_spawn.cwd
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        self.original_task.label == other.original_task.label
+            && self.kind == other.kind
+            && same_spawn
+    }
+
+    /// A message to show when the task is run, if its template is [`TaskTemplate::deprecated`].
+    pub fn deprecation_message(&self) -> Option<String> {
+        let message = self.original_task.deprecated.as_deref()?;
+        Some(format!(
+            "Task `{}` is deprecated: {message}",
+            self.resolved_label
+        ))
+    }
+
+    /// A summary of the finished task run to notify with, e.g. `cargo build succeeded in 2.5s`.
+    /// Uses [`TaskTemplate::success_message`] or [`TaskTemplate::failure_message`] if set, depending on
+    /// whether [`TaskTemplate::is_success`] for the exit code, with their placeholders filled.
+    pub fn completion_message(&self, exit_code: i32, duration: Duration) -> String {
+        let (custom_message, default_message) = if self.original_task.is_success(exit_code) {
+            (
+                self.original_task.success_message.as_deref(),
+                "{label} succeeded in {duration}",
+            )
+        } else {
+            (
+                self.original_task.failure_message.as_deref(),
+                "{label} failed with exit code {exit_code} after {duration}",
+            )
+        };
+        custom_message
+            .unwrap_or(default_message)
+            .replace("{label}", &self.resolved_label)
+            .replace("{exit_code}", &exit_code.to_string())
+            .replace("{duration}", &format_duration(duration))
+    }
+}
+
+/// Formats the duration for humans, e.g. `350ms`, `2.5s` or `3m 5s`.
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        format!("{millis}ms")
+    } else if millis < 60_000 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        let seconds = duration.as_secs();
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    }
+}
+
+/// Sorts the tasks so that the most recently run ones go first, see [`ResolvedTask::cmp_by_recency`].
+pub fn sort_by_recency(tasks: &mut [ResolvedTask]) {
+    tasks.sort_by(ResolvedTask::cmp_by_recency);
 }
 
 /// Variables, available for use in [`TaskContext`] when a Zed's [`TaskTemplate`] gets resolved into a [`ResolvedTask`].
@@ -68,6 +499,10 @@ pub struct ResolvedTask {
 pub enum VariableName {
     /// An absolute path of the currently opened file.
     File,
+    /// A `file://` URI of the currently opened file, derived from the [`VariableName::File`] during the resolution, see [`file_uri`].
+    FileUri,
+    /// A path of the currently opened file, relative to the worktree root, see [`relative_file_path`].
+    RelativeFile,
     /// An absolute path of the currently opened worktree, that contains the file.
     WorktreeRoot,
     /// A symbol text, that contains latest cursor/selection position.
@@ -76,14 +511,104 @@ pub enum VariableName {
     Row,
     /// A column with the latest cursor/selection position.
     Column,
+    /// A visual column with the latest cursor/selection position, with the tabs before it expanded to the tab width, see [`visual_column`].
+    /// Use it for the tools that count columns as displayed, rather than as characters.
+    VisualColumn,
     /// Text from the latest selection.
     SelectedText,
+    /// Indentation width configured in the editor settings for the current file.
+    /// Resolves to an empty string, if not provided in the context.
+    TabWidth,
+    /// Message of the diagnostic under the cursor.
+    /// Resolves to an empty string, if there is no diagnostic.
+    DiagnosticMessage,
+    /// Code of the diagnostic under the cursor, as reported by the language server.
+    /// Resolves to an empty string, if there is no diagnostic or it has no code.
+    DiagnosticCode,
+    /// Exit code of the previous task in a sequence of chained tasks, supplied by the scheduler.
+    /// Resolves to an empty string for tasks run standalone.
+    PrevExitCode,
+    /// Trimmed stdout of the previous task in a sequence of chained tasks, supplied by the scheduler, see [`prev_output_value`].
+    /// Resolves to an empty string for tasks run standalone.
+    PrevOutput,
+    /// Id of the language of the currently opened file, as detected by the editor, e.g. `rust` or `python`.
+    /// Resolves to an empty string, if the language is not known.
+    Language,
+    /// Upstream of the current git branch, e.g. `origin/main`, see [`GitInfo`].
+    /// Resolves to an empty string, if the branch has no upstream or the worktree is not a git repository.
+    GitUpstream,
+    /// URL of the `origin` git remote, see [`GitInfo`].
+    /// Resolves to an empty string, if there is no such remote or the worktree is not a git repository.
+    GitRemoteUrl,
+    /// Name of the test, benchmark or other runnable at the cursor, as identified by the language context provider,
+    /// e.g. a fully qualified test path `tests::parsing::test_empty`, that may differ from the [`VariableName::Symbol`] text.
+    /// Resolves to an empty string, if there is no runnable at the cursor.
+    RunnableSymbol,
     /// Custom variable, provided by the plugin or other external source.
     /// Will be printed with `ZED_` prefix to avoid potential conflicts with other variables.
     Custom(Cow<'static, str>),
 }
 
 impl VariableName {
+    /// A friendly English name of the variable for the UI, e.g. `Current file` for the `ZED_FILE` in the variable insertion menu,
+    /// as opposed to its [`Display`] spelling used in the templates and the env. All custom variables share the same label.
+    pub fn human_label(&self) -> &'static str {
+        match self {
+            Self::File => "Current file",
+            Self::FileUri => "Current file URI",
+            Self::RelativeFile => "Current file, relative to the worktree",
+            Self::WorktreeRoot => "Worktree root",
+            Self::Symbol => "Symbol at cursor",
+            Self::Row => "Cursor row",
+            Self::Column => "Cursor column",
+            Self::VisualColumn => "Cursor visual column",
+            Self::SelectedText => "Selected text",
+            Self::TabWidth => "Tab width",
+            Self::DiagnosticMessage => "Diagnostic message",
+            Self::DiagnosticCode => "Diagnostic code",
+            Self::PrevExitCode => "Previous task exit code",
+            Self::PrevOutput => "Previous task output",
+            Self::Language => "Language",
+            Self::GitUpstream => "Git upstream branch",
+            Self::GitRemoteUrl => "Git remote URL",
+            Self::RunnableSymbol => "Runnable at cursor",
+            Self::Custom(_) => "Custom variable",
+        }
+    }
+
+    /// The variable spelled as given in the env or the templates, e.g. `ZED_FILE` or `ZED_CUSTOM_PACKAGE`, with integer offsets ignored, e.g. `ZED_ROW+1`.
+    pub(crate) fn from_env_name(name: &str) -> Option<Self> {
+        let parse = |name: &str| {
+            BUILTIN_VARIABLES
+                .iter()
+                .find(|variable| variable.to_string() == name)
+                .cloned()
+                .or_else(|| {
+                    name.strip_prefix(ZED_VARIABLE_NAME_PREFIX)?
+                        .strip_prefix("CUSTOM_")
+                        .filter(|name| !name.is_empty())
+                        .map(|name| Self::Custom(Cow::Owned(name.to_owned())))
+                })
+        };
+        parse(name).or_else(|| parse(&name[..name.rfind(['+', '-'])?]))
+    }
+
+    /// A [`VariableName::Custom`] variable, failing if the name is empty or has characters other than ASCII letters, digits and `_`,
+    /// that would not survive the substitution or make a valid env variable name: e.g. `RUST_PACKAGE` is accepted, `my package` is not.
+    pub fn custom(name: impl Into<Cow<'static, str>>) -> anyhow::Result<Self> {
+        let name = name.into();
+        if name.is_empty() {
+            bail!("custom variable name is empty");
+        }
+        if let Some(c) = name
+            .chars()
+            .find(|&c| !c.is_ascii_alphanumeric() && c != '_')
+        {
+            bail!("custom variable name `{name}` contains an invalid character {c:?}");
+        }
+        Ok(Self::Custom(name))
+    }
+
     /// Generates a `$VARIABLE`-like string value to be used in templates.
     /// Custom variables are wrapped in `${}` to avoid substitution issues with whitespaces.
     pub fn template_value(&self) -> String {
@@ -95,6 +620,48 @@ impl VariableName {
     }
 }
 
+/// All variables except the [`VariableName::Custom`] ones, that templates may reference.
+pub(crate) const BUILTIN_VARIABLES: &[VariableName] = &[
+    VariableName::File,
+    VariableName::FileUri,
+    VariableName::RelativeFile,
+    VariableName::WorktreeRoot,
+    VariableName::Symbol,
+    VariableName::Row,
+    VariableName::Column,
+    VariableName::VisualColumn,
+    VariableName::SelectedText,
+    VariableName::TabWidth,
+    VariableName::DiagnosticMessage,
+    VariableName::DiagnosticCode,
+    VariableName::PrevExitCode,
+    VariableName::PrevOutput,
+    VariableName::Language,
+    VariableName::GitUpstream,
+    VariableName::GitRemoteUrl,
+    VariableName::RunnableSymbol,
+];
+
+/// Variables that are substituted with an empty string when missing in the [`TaskContext`], instead of failing the resolution.
+pub(crate) const OPTIONAL_VARIABLES: &[VariableName] = &[
+    VariableName::TabWidth,
+    VariableName::DiagnosticMessage,
+    VariableName::DiagnosticCode,
+    VariableName::PrevExitCode,
+    VariableName::PrevOutput,
+    VariableName::Language,
+    VariableName::GitUpstream,
+    VariableName::GitRemoteUrl,
+    VariableName::RunnableSymbol,
+];
+
+/// Variables with integer values, that may be referenced with an offset, e.g. `${ZED_ROW+1}`.
+pub(crate) const NUMERIC_VARIABLES: &[VariableName] = &[
+    VariableName::Row,
+    VariableName::Column,
+    VariableName::VisualColumn,
+];
+
 /// A prefix that all [`VariableName`] variants are prefixed with when used in environment variables and similar template contexts.
 pub const ZED_VARIABLE_NAME_PREFIX: &str = "ZED_";
 
@@ -102,29 +669,153 @@ impl std::fmt::Display for VariableName {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::File => write!(f, "{ZED_VARIABLE_NAME_PREFIX}FILE"),
+            Self::FileUri => write!(f, "{ZED_VARIABLE_NAME_PREFIX}FILE_URI"),
             Self::WorktreeRoot => write!(f, "{ZED_VARIABLE_NAME_PREFIX}WORKTREE_ROOT"),
+            Self::RelativeFile => write!(f, "{ZED_VARIABLE_NAME_PREFIX}RELATIVE_FILE"),
             Self::Symbol => write!(f, "{ZED_VARIABLE_NAME_PREFIX}SYMBOL"),
             Self::Row => write!(f, "{ZED_VARIABLE_NAME_PREFIX}ROW"),
             Self::Column => write!(f, "{ZED_VARIABLE_NAME_PREFIX}COLUMN"),
+            Self::VisualColumn => write!(f, "{ZED_VARIABLE_NAME_PREFIX}VISUAL_COLUMN"),
             Self::SelectedText => write!(f, "{ZED_VARIABLE_NAME_PREFIX}SELECTED_TEXT"),
+            Self::TabWidth => write!(f, "{ZED_VARIABLE_NAME_PREFIX}TAB_WIDTH"),
+            Self::DiagnosticMessage => write!(f, "{ZED_VARIABLE_NAME_PREFIX}DIAGNOSTIC_MESSAGE"),
+            Self::DiagnosticCode => write!(f, "{ZED_VARIABLE_NAME_PREFIX}DIAGNOSTIC_CODE"),
+            Self::PrevExitCode => write!(f, "{ZED_VARIABLE_NAME_PREFIX}PREV_EXIT_CODE"),
+            Self::PrevOutput => write!(f, "{ZED_VARIABLE_NAME_PREFIX}PREV_OUTPUT"),
+            Self::Language => write!(f, "{ZED_VARIABLE_NAME_PREFIX}LANGUAGE"),
+            Self::GitUpstream => write!(f, "{ZED_VARIABLE_NAME_PREFIX}GIT_UPSTREAM"),
+            Self::GitRemoteUrl => write!(f, "{ZED_VARIABLE_NAME_PREFIX}GIT_REMOTE_URL"),
+            Self::RunnableSymbol => write!(f, "{ZED_VARIABLE_NAME_PREFIX}RUNNABLE_SYMBOL"),
             Self::Custom(s) => write!(f, "{ZED_VARIABLE_NAME_PREFIX}CUSTOM_{s}"),
         }
     }
 }
 
+/// Computes the [`VariableName::RelativeFile`] value: the path of the file relative to the worktree root.
+///
+/// The paths are compared as given first, without canonicalizing either of them, so that the result matches the paths shown in the UI.
+/// If the file is not under the root this way, e.g. when the root is a symlink and the file path is resolved (or vice versa),
+/// both paths are canonicalized and compared again: canonicalizing only one of them would never match in such cases.
+/// Returns `None` if the file is outside of the worktree either way, or the paths cannot be canonicalized.
+pub fn relative_file_path(file: &Path, worktree_root: &Path) -> Option<PathBuf> {
+    if let Ok(relative_path) = file.strip_prefix(worktree_root) {
+        return Some(relative_path.to_path_buf());
+    }
+    let file = file.canonicalize().ok()?;
+    let worktree_root = worktree_root.canonicalize().ok()?;
+    file.strip_prefix(worktree_root).ok().map(Path::to_path_buf)
+}
+
+/// Computes the [`VariableName::FileUri`] value: a `file://` URI of the absolute path, with the bytes other than
+/// ASCII letters, digits and `-._~` percent-encoded in each path segment, e.g. `file:///home/me/my%20file.rs`.
+/// On Windows, drive paths become `file:///C:/dir/file.rs`, and UNC paths `\\server\share` become `file://server/share`.
+/// Returns `None` for the relative paths and the paths that are not valid Unicode.
+pub fn file_uri(path: &Path) -> Option<String> {
+    file_uri_for_platform(path.to_str()?, cfg!(windows))
+}
+
+fn file_uri_for_platform(path: &str, is_windows: bool) -> Option<String> {
+    let encode = |segments: &str| {
+        let mut encoded = String::with_capacity(segments.len());
+        for byte in segments.bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        encoded
+    };
+    if !is_windows {
+        return path
+            .starts_with('/')
+            .then(|| format!("file://{}", encode(path)));
+    }
+    let path = path.replace('\\', "/");
+    if let Some(unc_path) = path.strip_prefix("//") {
+        let (host, share_path) = unc_path.split_once('/').unwrap_or((unc_path, ""));
+        if host.is_empty() {
+            return None;
+        }
+        return Some(format!("file://{}/{}", encode(host), encode(share_path)));
+    }
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = path.strip_prefix(drive)?.strip_prefix(':')?;
+    if !rest.starts_with('/') {
+        return None;
+    }
+    Some(format!("file:///{drive}:{}", encode(rest)))
+}
+
+/// Computes the [`VariableName::VisualColumn`] value: the 1-based column of the position after the `line_prefix` text,
+/// as displayed with the tabs expanded to the next multiple of `tab_width`. Other characters take one column each.
+pub fn visual_column(line_prefix: &str, tab_width: u32) -> u32 {
+    let tab_width = tab_width.max(1);
+    let width = line_prefix.chars().fold(0, |width, c| {
+        if c == '\t' {
+            (width / tab_width + 1) * tab_width
+        } else {
+            width + 1
+        }
+    });
+    width + 1
+}
+
+/// Git state of the worktree, gathered by the embedder, so that the task variables based on it
+/// are available without this crate running `git`, see [`TaskVariables::insert_git_info`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GitInfo {
+    /// Upstream of the current branch, e.g. `origin/main`.
+    pub upstream: Option<String>,
+    /// URL of the `origin` remote.
+    pub remote_url: Option<String>,
+}
+
+/// Maximum length of the [`VariableName::PrevOutput`] value, in bytes.
+pub const MAX_PREV_OUTPUT_LENGTH: usize = 64 * 1024;
+
+/// Converts the stdout of a finished task into a [`VariableName::PrevOutput`] value for the next task in the chain:
+/// the output is trimmed, and only its first [`MAX_PREV_OUTPUT_LENGTH`] bytes (cut at a char boundary) are kept.
+pub fn prev_output_value(stdout: &str) -> String {
+    let stdout = stdout.trim();
+    let mut end = stdout.len().min(MAX_PREV_OUTPUT_LENGTH);
+    while !stdout.is_char_boundary(end) {
+        end -= 1;
+    }
+    stdout[..end].to_string()
+}
+
 /// Container for predefined environment variables that describe state of Zed at the time the task was spawned.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
-pub struct TaskVariables(HashMap<VariableName, String>);
+pub struct TaskVariables(
+    #[serde(serialize_with = "serialize_sorted_map")] HashMap<VariableName, String>,
+);
 
 impl TaskVariables {
-    /// Converts the container into a map of environment variables and their values.
-    fn into_env_variables(self) -> HashMap<String, String> {
+    /// Converts the container into a map of environment variables and their values,
+    /// with the [`VariableName::FileUri`] derived from the [`VariableName::File`], unless provided.
+    fn into_env_variables(mut self) -> HashMap<String, String> {
+        if !self.0.contains_key(&VariableName::FileUri) {
+            if let Some(file_uri) = self
+                .0
+                .get(&VariableName::File)
+                .and_then(|file| file_uri(Path::new(file)))
+            {
+                self.0.insert(VariableName::FileUri, file_uri);
+            }
+        }
         self.0
             .into_iter()
             .map(|(name, value)| (name.to_string(), value))
             .collect()
     }
 
+    /// The value of the variable, if the container has it.
+    pub fn get(&self, variable: &VariableName) -> Option<&str> {
+        self.0.get(variable).map(String::as_str)
+    }
+
     /// Inserts another variable into the container, overwriting the existing one if it already exists — in this case, the old value is returned.
     pub fn insert(&mut self, variable: VariableName, value: String) -> Option<String> {
         self.0.insert(variable, value)
@@ -134,6 +825,82 @@ impl TaskVariables {
     pub fn extend(&mut self, other: Self) {
         self.0.extend(other.0);
     }
+
+    /// The variables which are missing in the `base` or have a different value there, e.g. to send only the changed part of a context.
+    /// Variables present in the `base` only are not reported.
+    pub fn delta(&self, base: &TaskVariables) -> TaskVariables {
+        self.0
+            .iter()
+            .filter(|(variable, value)| base.0.get(*variable) != Some(*value))
+            .map(|(variable, value)| (variable.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Inserts the [`VariableName::GitUpstream`] and [`VariableName::GitRemoteUrl`] variables, for the values known.
+    pub fn insert_git_info(&mut self, git_info: &GitInfo) {
+        for (variable, value) in [
+            (VariableName::GitUpstream, &git_info.upstream),
+            (VariableName::GitRemoteUrl, &git_info.remote_url),
+        ] {
+            if let Some(value) = value {
+                self.insert(variable, value.clone());
+            }
+        }
+    }
+
+    /// Imports the environment variables of the current process, which names start with the `prefix`, as [`VariableName::Custom`] ones
+    /// named without the prefix: e.g. `MYAPP_FOO` becomes `ZED_CUSTOM_FOO` with the `MYAPP_` prefix, letting CI-set variables flow into templates.
+    /// Variables with non-Unicode values, and the ones which names without the prefix are not valid [`VariableName::custom`] ones
+    /// (e.g. the one named exactly the `prefix`, or `MYAPP_FOO-BAR`, which templates could not reference), are skipped.
+    pub fn from_process_env_filtered(prefix: &str) -> Self {
+        Self::from_env_filtered(std::env::vars_os(), prefix)
+    }
+
+    fn from_env_filtered(
+        vars: impl IntoIterator<Item = (std::ffi::OsString, std::ffi::OsString)>,
+        prefix: &str,
+    ) -> Self {
+        vars.into_iter()
+            .filter_map(|(name, value)| {
+                let name = name.into_string().ok()?;
+                let variable = VariableName::custom(name.strip_prefix(prefix)?.to_owned()).ok()?;
+                Some((variable, value.into_string().ok()?))
+            })
+            .collect()
+    }
+
+    /// Formats the variables as a sorted `NAME = value` listing, one variable per line, with the `=` signs aligned, e.g. for task previews.
+    /// Values are redacted the same way as in [`TaskContext::redacted_debug`].
+    pub fn display_table(&self) -> String {
+        let mut variables = self.redacted_variables();
+        variables.sort();
+        let name_width = variables
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        variables
+            .into_iter()
+            .map(|(name, value)| format!("{name:<name_width$} = {value}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Variable names and values, with secret-looking custom variables replaced with `***` and home directory prefixes shortened to `~`.
+    fn redacted_variables(&self) -> Vec<(String, String)> {
+        self.0
+            .iter()
+            .map(|(name, value)| {
+                let value = match name {
+                    VariableName::Custom(custom_name) if is_secret_like(custom_name) => {
+                        REDACTED_VALUE.to_string()
+                    }
+                    _ => shorten_home_prefix(value),
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
 }
 
 impl FromIterator<(VariableName, String)> for TaskVariables {
@@ -142,6 +909,41 @@ impl FromIterator<(VariableName, String)> for TaskVariables {
     }
 }
 
+/// A builder of the [`TaskVariables`] from user input, validating the custom variable names as they are added, see [`TaskVariables::builder`].
+#[derive(Clone, Debug, Default)]
+pub struct TaskVariablesBuilder {
+    variables: TaskVariables,
+}
+
+impl TaskVariables {
+    /// Starts building the variables, see [`TaskVariablesBuilder`].
+    pub fn builder() -> TaskVariablesBuilder {
+        TaskVariablesBuilder::default()
+    }
+}
+
+impl TaskVariablesBuilder {
+    /// Adds a variable, overriding the previous value of the same variable.
+    pub fn variable(mut self, variable: VariableName, value: impl Into<String>) -> Self {
+        self.variables.insert(variable, value.into());
+        self
+    }
+
+    /// Adds a custom variable, failing if the name is not valid, see [`VariableName::custom`].
+    pub fn custom(
+        self,
+        name: impl Into<Cow<'static, str>>,
+        value: impl Into<String>,
+    ) -> anyhow::Result<Self> {
+        Ok(self.variable(VariableName::custom(name)?, value))
+    }
+
+    /// Finishes the variables.
+    pub fn build(self) -> TaskVariables {
+        self.variables
+    }
+}
+
 /// Keeps track of the file associated with a task and context of tasks execution (i.e. current file or current function).
 /// Keeps all Zed-related state inside, used to produce a resolved task out of its template.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
@@ -152,13 +954,774 @@ pub struct TaskContext {
     pub task_variables: TaskVariables,
 }
 
+/// A replacement for values that should not be shown, see [`TaskContext::redacted_debug`].
+const REDACTED_VALUE: &str = "***";
+
+/// Parts of custom variable names, hinting that the variable holds some secret value.
+const SECRET_NAME_PARTS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "APIKEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
+
+fn is_secret_like(variable_name: &str) -> bool {
+    let variable_name = variable_name.to_uppercase();
+    SECRET_NAME_PARTS
+        .iter()
+        .any(|secret_part| variable_name.contains(secret_part))
+}
+
+fn shorten_home_prefix(value: &str) -> String {
+    let home = util::paths::HOME.to_string_lossy();
+    match value.strip_prefix(home.as_ref()) {
+        Some(rest)
+            if !home.is_empty()
+                && (rest.is_empty() || rest.starts_with(std::path::is_separator)) =>
+        {
+            format!("~{rest}")
+        }
+        _ => value.to_string(),
+    }
+}
+
+impl TaskContext {
+    /// A hash of the context, that does not depend on the order the variables were inserted in.
+    /// Equal contexts have equal hashes, see [`ResolutionCache`].
+    pub fn content_hash(&self) -> u64 {
+        let mut variables = self
+            .task_variables
+            .0
+            .iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect::<Vec<_>>();
+        variables.sort();
+        let mut hasher = DefaultHasher::new();
+        (&self.cwd, variables).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Formats the context in a way that is safe to share, e.g. in bug reports:
+    /// custom variables with secret-looking names get their values replaced with `***`,
+    /// and home directory prefixes in paths and values are shortened to `~`.
+    pub fn redacted_debug(&self) -> String {
+        let mut variables = self.task_variables.redacted_variables();
+        variables.sort();
+        let variables = variables
+            .into_iter()
+            .map(|(name, value)| format!("{name}: {value:?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let cwd = self
+            .cwd
+            .as_ref()
+            .map(|cwd| shorten_home_prefix(&cwd.to_string_lossy()));
+        format!("TaskContext {{ cwd: {cwd:?}, task_variables: {{{variables}}} }}")
+    }
+}
+
 /// [`Source`] produces tasks that can be scheduled.
 ///
 /// Implementations of this trait could be e.g. [`StaticSource`] that parses tasks from a .json files and provides process templates to be spawned;
 /// another one could be a language server providing lenses with tests or build server listing all targets for a given project.
+///
+/// Sources are not required to be [`Send`]: they live in gpui models, which are owned and accessed on the main thread only,
+/// and may hold main-thread-only state, such as gpui subscriptions.
+/// Sources that fetch their tasks in the background should compute the [`TaskTemplates`] (that are [`Send`] and [`Sync`]) off the main thread
+/// and pass them back, e.g. with a [`channel_source::ChannelSource`].
 pub trait TaskSource: Any {
     /// A way to erase the type of the source, processing and storing them generically.
     fn as_any(&mut self) -> &mut dyn Any;
     /// Collects all tasks available for scheduling.
     fn tasks_to_schedule(&mut self, cx: &mut ModelContext<Box<dyn TaskSource>>) -> TaskTemplates;
+    /// Called once the source is registered to provide tasks, e.g. to lazily start the work or acquire resources tied to the source lifetime.
+    fn on_register(&mut self, _cx: &mut ModelContext<Box<dyn TaskSource>>) {}
+    /// Called once the source is removed and will not be queried for tasks anymore, e.g. to release the resources acquired.
+    fn on_unregister(&mut self, _cx: &mut ModelContext<Box<dyn TaskSource>>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, time::Duration};
+
+    use super::*;
+
+    fn resolved_task(label: &str, last_run: Option<SystemTime>) -> ResolvedTask {
+        ResolvedTask {
+            id: TaskId(label.to_string()),
+            original_task: TaskTemplate::default(),
+            resolved_label: label.to_string(),
+            resolved: None,
+            last_run,
+            kind: TaskKind::Spawn,
+        }
+    }
+
+    #[test]
+    fn test_variables_display_table() {
+        let variables = TaskVariables::from_iter([
+            (VariableName::Row, "12".to_string()),
+            (VariableName::File, "main.rs".to_string()),
+            (
+                VariableName::Custom(Cow::Borrowed("GITHUB_TOKEN")),
+                "ghp_very_secret".to_string(),
+            ),
+            (
+                VariableName::Custom(Cow::Borrowed("PACKAGE")),
+                "task".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            variables.display_table(),
+            [
+                "ZED_CUSTOM_GITHUB_TOKEN = ***",
+                "ZED_CUSTOM_PACKAGE      = task",
+                "ZED_FILE                = main.rs",
+                "ZED_ROW                 = 12",
+            ]
+            .join("\n")
+        );
+        assert_eq!(TaskVariables::default().display_table(), "");
+    }
+
+    #[test]
+    fn test_relative_file_path() {
+        assert_eq!(
+            relative_file_path(Path::new("/dir/src/main.rs"), Path::new("/dir")),
+            Some(PathBuf::from("src/main.rs"))
+        );
+        assert_eq!(
+            relative_file_path(Path::new("/elsewhere/main.rs"), Path::new("/dir")),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relative_file_path_with_symlinked_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let real_root = temp_dir.path().join("real");
+        std::fs::create_dir_all(real_root.join("src")).unwrap();
+        std::fs::write(real_root.join("src").join("main.rs"), "").unwrap();
+        let linked_root = temp_dir.path().join("linked");
+        std::os::unix::fs::symlink(&real_root, &linked_root).unwrap();
+
+        let expected = Some(PathBuf::from("src/main.rs"));
+        assert_eq!(
+            relative_file_path(&linked_root.join("src").join("main.rs"), &linked_root),
+            expected
+        );
+        let resolved_file = linked_root
+            .join("src")
+            .join("main.rs")
+            .canonicalize()
+            .unwrap();
+        assert_eq!(
+            relative_file_path(&resolved_file, &linked_root),
+            expected,
+            "Resolved file path should still be relative to the symlinked root"
+        );
+        assert_eq!(
+            relative_file_path(&linked_root.join("src").join("main.rs"), &real_root),
+            expected,
+            "File path through the symlink should be relative to the resolved root"
+        );
+    }
+
+    #[test]
+    fn test_completion_message() {
+        let mut task = resolved_task("cargo build", None);
+        assert_eq!(
+            task.completion_message(0, Duration::from_millis(2500)),
+            "cargo build succeeded in 2.5s"
+        );
+        assert_eq!(
+            task.completion_message(101, Duration::from_secs(185)),
+            "cargo build failed with exit code 101 after 3m 5s"
+        );
+
+        task.original_task = TaskTemplate {
+            success_exit_codes: vec![0, 1],
+            success_message: Some("Built {label} in {duration}".to_string()),
+            failure_message: Some("{label}: exit {exit_code}".to_string()),
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            task.completion_message(1, Duration::from_millis(350)),
+            "Built cargo build in 350ms"
+        );
+        assert_eq!(
+            task.completion_message(2, Duration::from_millis(350)),
+            "cargo build: exit 2"
+        );
+    }
+
+    #[test]
+    fn test_variables_from_env_filtered() {
+        let variables = TaskVariables::from_env_filtered(
+            [
+                ("MYAPP_FOO", "bar"),
+                ("MYAPP_", "empty name"),
+                ("MYAPP_FOO-BAR", "invalid name"),
+                ("MYAPP_FOO BAR", "invalid name"),
+                ("OTHER_FOO", "baz"),
+                ("myapp_FOO", "case"),
+            ]
+            .map(|(name, value)| (name.into(), value.into())),
+            "MYAPP_",
+        );
+        assert_eq!(
+            variables,
+            TaskVariables::from_iter([(
+                VariableName::Custom(Cow::Borrowed("FOO")),
+                "bar".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_line_buffering() {
+        let template: TaskTemplate =
+            serde_json_lenient::from_str(r#"{"label": "serve", "command": "python3"}"#).unwrap();
+        assert!(!template.force_line_buffered);
+        let mut spawn = template
+            .resolve_task("test", TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        spawn.apply_line_buffering();
+        assert!(spawn.env.is_empty());
+
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "serve", "command": "python3", "force_line_buffered": true, "env": {"PYTHONUNBUFFERED": ""}}"#,
+        )
+        .unwrap();
+        let mut spawn = template
+            .resolve_task("test", TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert!(spawn.force_line_buffered);
+        spawn.apply_line_buffering();
+        assert_eq!(
+            spawn.env.get("PYTHONUNBUFFERED").map(String::as_str),
+            Some(""),
+            "Values from the task env should be kept"
+        );
+        spawn.env.clear();
+        spawn.apply_line_buffering();
+        assert_eq!(
+            spawn.env.get("PYTHONUNBUFFERED").map(String::as_str),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn test_variables_delta() {
+        let base = TaskVariables::from_iter([
+            (VariableName::File, "/project/a.rs".to_string()),
+            (VariableName::Row, "1".to_string()),
+            (VariableName::Symbol, "main".to_string()),
+        ]);
+        let current = TaskVariables::from_iter([
+            (VariableName::File, "/project/a.rs".to_string()),
+            (VariableName::Row, "42".to_string()),
+            (VariableName::Column, "5".to_string()),
+        ]);
+        assert_eq!(
+            current.delta(&base),
+            TaskVariables::from_iter([
+                (VariableName::Row, "42".to_string()),
+                (VariableName::Column, "5".to_string()),
+            ])
+        );
+        assert_eq!(current.delta(&current), TaskVariables::default());
+        assert_eq!(current.delta(&TaskVariables::default()), current);
+    }
+
+    #[test]
+    fn test_argv() {
+        let spawn = TaskTemplate {
+            label: "test".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["test".into(), "--".into(), "my test".into()],
+            ..TaskTemplate::default()
+        }
+        .resolve_task("test", TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert_eq!(spawn.argv(), vec!["cargo", "test", "--", "my test"]);
+
+        let no_args = TaskTemplate {
+            label: "ls".to_string(),
+            command: "ls".to_string(),
+            ..TaskTemplate::default()
+        }
+        .resolve_task("test", TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert_eq!(no_args.argv(), vec!["ls"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_std_command_runs_raw_commands_in_shell() {
+        let resolve = |template: TaskTemplate| {
+            template
+                .resolve_task("test", TaskContext::default())
+                .unwrap()
+                .resolved
+                .unwrap()
+        };
+        let spawn = resolve(TaskTemplate {
+            label: "shout".to_string(),
+            command: r#"echo "bar baz" | tr a-z A-Z"#.to_string(),
+            raw_command: true,
+            ..TaskTemplate::default()
+        });
+        let output = spawn.to_std_command().unwrap().output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "BAR BAZ\n");
+
+        let spawn = resolve(TaskTemplate {
+            label: "echo".to_string(),
+            command: "echo".to_string(),
+            args: vec!["bar baz".into()],
+            ..TaskTemplate::default()
+        });
+        let command = spawn.to_std_command().unwrap();
+        assert_eq!(command.get_program(), "echo");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), vec!["bar baz"]);
+    }
+
+    #[test]
+    fn test_file_uri() {
+        assert_eq!(
+            file_uri_for_platform("/home/me/my project/main.rs", false).as_deref(),
+            Some("file:///home/me/my%20project/main.rs")
+        );
+        assert_eq!(
+            file_uri_for_platform("/home/me/проект/ü#1.rs", false).as_deref(),
+            Some("file:///home/me/%D0%BF%D1%80%D0%BE%D0%B5%D0%BA%D1%82/%C3%BC%231.rs")
+        );
+        assert_eq!(file_uri_for_platform("src/main.rs", false), None);
+
+        assert_eq!(
+            file_uri_for_platform("C:\\Users\\me\\My Project\\main.rs", true).as_deref(),
+            Some("file:///C:/Users/me/My%20Project/main.rs")
+        );
+        assert_eq!(
+            file_uri_for_platform("d:\\données\\a.rs", true).as_deref(),
+            Some("file:///d:/donn%C3%A9es/a.rs")
+        );
+        assert_eq!(
+            file_uri_for_platform("\\\\server\\share\\a b.rs", true).as_deref(),
+            Some("file://server/share/a%20b.rs")
+        );
+        assert_eq!(file_uri_for_platform("src\\main.rs", true), None);
+        assert_eq!(file_uri_for_platform("C:main.rs", true), None);
+
+        let template = TaskTemplate {
+            label: "open".to_string(),
+            command: "open".to_string(),
+            args: vec![VariableName::FileUri.template_value().into()],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(VariableName::FileUri.template_value(), "$ZED_FILE_URI");
+        let file = if cfg!(windows) {
+            "C:\\dir\\a b.html"
+        } else {
+            "/dir/a b.html"
+        };
+        let resolved = template
+            .resolve_task(
+                "test",
+                TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([(
+                        VariableName::File,
+                        file.to_string(),
+                    )]),
+                },
+            )
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(resolved.args, vec![file_uri(Path::new(file)).unwrap()]);
+        assert!(resolved.args[0].ends_with("/dir/a%20b.html"));
+        assert_eq!(resolved.env["ZED_FILE_URI"], resolved.args[0]);
+        assert!(
+            template
+                .resolve_task("test", TaskContext::default())
+                .is_none(),
+            "No file URI should be available without a file"
+        );
+    }
+
+    #[test]
+    fn test_task_path() {
+        let base_path = std::env::join_paths(["/usr/local/bin", "/usr/bin", "/bin"]).unwrap();
+        let venv_bin = PathBuf::from("/project/.venv/bin");
+        let split =
+            |path: Option<String>| std::env::split_paths(&path.unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(
+            split(task_path(Some(&base_path), [venv_bin.clone()], false)),
+            vec![
+                venv_bin.clone(),
+                PathBuf::from("/usr/local/bin"),
+                PathBuf::from("/usr/bin"),
+                PathBuf::from("/bin"),
+            ]
+        );
+        assert_eq!(
+            split(task_path(Some(&base_path), [venv_bin.clone()], true)),
+            vec![
+                PathBuf::from("/usr/local/bin"),
+                PathBuf::from("/usr/bin"),
+                PathBuf::from("/bin"),
+            ],
+            "Zed's additions should be stripped, keeping the base PATH"
+        );
+        assert_eq!(
+            split(task_path(None, [venv_bin.clone()], false)),
+            vec![venv_bin.clone()]
+        );
+        assert_eq!(task_path(None, [venv_bin], true), None);
+
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "test", "command": "pytest", "strip_zed_path_additions": true}"#,
+        )
+        .unwrap();
+        assert!(template.strip_zed_path_additions);
+        let spawn = template
+            .resolve_task("test", TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert!(spawn.strip_zed_path_additions);
+    }
+
+    #[test]
+    fn test_matches_persisted() {
+        let template = TaskTemplate {
+            label: "test $ZED_SYMBOL".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["test".into(), "$ZED_SYMBOL".into()],
+            cwd: Some("/project".to_string()),
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(
+                VariableName::Symbol,
+                "my_test".to_string(),
+            )]),
+        };
+        let task = template
+            .resolve_task("new_id_base", context.clone())
+            .unwrap();
+        let mut persisted = template.resolve_task("old_id_base", context).unwrap();
+        persisted.id = TaskId("old_id_scheme_1".to_string());
+        persisted.last_run = Some(SystemTime::UNIX_EPOCH);
+        assert_ne!(task, persisted);
+        assert!(task.matches_persisted(&persisted));
+        assert!(persisted.matches_persisted(&task));
+
+        let other_symbol = template
+            .resolve_task(
+                "new_id_base",
+                TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([(
+                        VariableName::Symbol,
+                        "other_test".to_string(),
+                    )]),
+                },
+            )
+            .unwrap();
+        assert!(!task.matches_persisted(&other_symbol));
+
+        let mut other_cwd = persisted.clone();
+        other_cwd.resolved.as_mut().unwrap().cwd = Some(PathBuf::from("/other"));
+        assert!(!task.matches_persisted(&other_cwd));
+    }
+
+    #[test]
+    fn test_path_modes() {
+        let join = |paths: &[&str]| {
+            std::env::join_paths(paths)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        };
+        let inherited = HashMap::from_iter([
+            ("PATH".to_string(), join(&["/usr/bin", "/bin"])),
+            ("HOME".to_string(), "/home/me".to_string()),
+            ("EDITOR".to_string(), "vi".to_string()),
+        ]);
+        let spawn = |path_mode: PathMode| {
+            let template: TaskTemplate = serde_json_lenient::from_str(&format!(
+                r#"{{"label": "build", "command": "make", "path_mode": {}, "env": {{"PATH": {}, "EDITOR": "zed"}}}}"#,
+                serde_json_lenient::to_string(&path_mode).unwrap(),
+                serde_json_lenient::to_string(&join(&["/opt/tools/bin", "/opt/sdk/bin"])).unwrap(),
+            ))
+            .unwrap();
+            template
+                .resolve_task("test", TaskContext::default())
+                .unwrap()
+                .resolved
+                .unwrap()
+        };
+
+        let env = spawn(PathMode::Prepend).merge_env_from(&inherited);
+        assert_eq!(
+            env["PATH"],
+            join(&["/opt/tools/bin", "/opt/sdk/bin", "/usr/bin", "/bin"])
+        );
+        assert_eq!(env["EDITOR"], "zed");
+        assert_eq!(env["HOME"], "/home/me");
+        assert_eq!(
+            spawn(PathMode::Append).merge_env_from(&inherited)["PATH"],
+            join(&["/usr/bin", "/bin", "/opt/tools/bin", "/opt/sdk/bin"])
+        );
+        assert_eq!(
+            spawn(PathMode::Replace).merge_env_from(&inherited)["PATH"],
+            join(&["/opt/tools/bin", "/opt/sdk/bin"])
+        );
+        // Windows env keys are case-insensitive, and its `PATH` is usually inherited as `Path`.
+        let inherited_key = if cfg!(windows) { "Path" } else { "PATH" };
+        let inherited_with_key =
+            HashMap::from_iter([(inherited_key.to_string(), join(&["/usr/bin", "/bin"]))]);
+        for path_mode in [PathMode::Prepend, PathMode::Append, PathMode::Replace] {
+            let env = spawn(path_mode).merge_env_from(&inherited_with_key);
+            assert_eq!(
+                env.keys().filter(|key| is_path_key(key)).count(),
+                1,
+                "{path_mode:?} should leave a single PATH, got {env:?}"
+            );
+        }
+        assert_eq!(
+            spawn(PathMode::Prepend).merge_env_from(&HashMap::default())["PATH"],
+            join(&["/opt/tools/bin", "/opt/sdk/bin"]),
+            "Without an inherited PATH, the task one should be used as is"
+        );
+        assert_eq!(
+            serde_json_lenient::from_str::<TaskTemplate>(
+                r#"{"label": "build", "command": "make"}"#
+            )
+            .unwrap()
+            .path_mode,
+            PathMode::Prepend
+        );
+    }
+
+    #[test]
+    fn test_task_variables_builder() {
+        let variables = TaskVariables::builder()
+            .variable(VariableName::File, "/project/main.rs")
+            .custom("RUST_PACKAGE", "task")
+            .unwrap()
+            .build();
+        assert_eq!(variables.get(&VariableName::File), Some("/project/main.rs"));
+        assert_eq!(
+            variables.get(&VariableName::Custom("RUST_PACKAGE".into())),
+            Some("task")
+        );
+
+        for invalid_name in ["", "my package", "${ZED_FILE}", "a-b"] {
+            assert!(
+                TaskVariables::builder()
+                    .custom(invalid_name, "value")
+                    .is_err(),
+                "Custom name {invalid_name:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_human_labels() {
+        let mut labels = BTreeSet::new();
+        for variable in BUILTIN_VARIABLES {
+            let label = variable.human_label();
+            assert!(!label.is_empty(), "{variable} should have a label");
+            assert_ne!(label, variable.to_string());
+            assert!(labels.insert(label), "Label {label:?} is not unique");
+        }
+        assert_eq!(
+            VariableName::Custom("PACKAGE".into()).human_label(),
+            "Custom variable"
+        );
+    }
+
+    #[test]
+    fn test_redacted_debug() {
+        let home = util::paths::HOME.clone();
+        let context = TaskContext {
+            cwd: Some(home.join("project")),
+            task_variables: TaskVariables::from_iter([
+                (
+                    VariableName::Custom(Cow::Borrowed("API_TOKEN")),
+                    "very secret value".to_string(),
+                ),
+                (
+                    VariableName::Custom(Cow::Borrowed("RUST_PACKAGE")),
+                    "task".to_string(),
+                ),
+                (
+                    VariableName::File,
+                    home.join("project").join("main.rs").display().to_string(),
+                ),
+                (
+                    VariableName::Custom(Cow::Borrowed("SIBLING")),
+                    format!("{}ice/x", home.display()),
+                ),
+            ]),
+        };
+
+        let redacted = context.redacted_debug();
+        assert!(
+            !redacted.contains("very secret value"),
+            "Secret-looking custom variables should be redacted, but got: {redacted}"
+        );
+        assert!(redacted.contains(r#"ZED_CUSTOM_API_TOKEN: "***""#));
+        assert!(
+            redacted.contains(r#"ZED_CUSTOM_RUST_PACKAGE: "task""#),
+            "Regular custom variables should be shown as is, but got: {redacted}"
+        );
+        let project_dir = home.join("project").display().to_string();
+        assert!(
+            !redacted.contains(&project_dir),
+            "Home directory should be shortened, but got: {redacted}"
+        );
+        assert!(redacted.contains(r#"cwd: Some("~"#));
+        assert!(
+            redacted.contains(&format!(r#"ZED_CUSTOM_SIBLING: "{}ice/x""#, home.display())),
+            "Paths only sharing a string prefix with the home directory should be kept, but got: {redacted}"
+        );
+    }
+
+    #[test]
+    fn test_sort_by_recency() {
+        let now = SystemTime::now();
+        let hour_ago = now - Duration::from_secs(60 * 60);
+        let mut tasks = vec![
+            resolved_task("never run", None),
+            resolved_task("old", Some(hour_ago)),
+            resolved_task("b recent", Some(now)),
+            resolved_task("a never run", None),
+            resolved_task("a recent", Some(now)),
+        ];
+
+        sort_by_recency(&mut tasks);
+        let labels = tasks
+            .iter()
+            .map(|task| task.resolved_label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec!["a recent", "b recent", "old", "a never run", "never run"],
+            "Most recent tasks should go first, ties should be broken by label"
+        );
+    }
+
+    #[test]
+    fn test_display_command_and_echo() {
+        let mut spawn_in_terminal = SpawnInTerminal {
+            id: TaskId("test".to_string()),
+            full_label: "test".to_string(),
+            label: "test".to_string(),
+            command: "cargo".to_string(),
+            args: vec![
+                "test".to_string(),
+                "my test".to_string(),
+                "it's".to_string(),
+                "--".to_string(),
+                "".to_string(),
+            ],
+            ..SpawnInTerminal::default()
+        };
+        assert_eq!(
+            spawn_in_terminal.display_command(),
+            r#"cargo test 'my test' 'it'\''s' -- ''"#
+        );
+        assert_eq!(spawn_in_terminal.command_echo(), None);
+
+        assert_eq!(spawn_in_terminal.output_banner(), None);
+
+        spawn_in_terminal.echo_command = true;
+        assert_eq!(
+            spawn_in_terminal.command_echo(),
+            Some(spawn_in_terminal.display_command())
+        );
+        assert_eq!(
+            spawn_in_terminal.output_banner(),
+            spawn_in_terminal.command_echo()
+        );
+
+        spawn_in_terminal.cwd = Some(PathBuf::from("/project/crates/task"));
+        assert_eq!(
+            spawn_in_terminal.output_banner().unwrap(),
+            format!(
+                "{}\n(in /project/crates/task)",
+                spawn_in_terminal.display_command()
+            )
+        );
+    }
+
+    #[test]
+    fn test_serialized_maps_are_sorted() {
+        let variables = [
+            (VariableName::Row, "1".to_string()),
+            (
+                VariableName::Custom(Cow::Borrowed("PACKAGE")),
+                "task".to_string(),
+            ),
+            (VariableName::File, "main.rs".to_string()),
+            (VariableName::Column, "2".to_string()),
+        ];
+        let serialized_variables =
+            serde_json_lenient::to_string(&TaskVariables::from_iter(variables.clone())).unwrap();
+        assert_eq!(
+            serialized_variables,
+            r#"{"ZED_COLUMN":"2","ZED_CUSTOM_PACKAGE":"task","ZED_FILE":"main.rs","ZED_ROW":"1"}"#
+        );
+        let reversed_variables =
+            TaskVariables::from_iter(variables.into_iter().rev().collect::<Vec<_>>());
+        assert_eq!(
+            serde_json_lenient::to_string(&reversed_variables).unwrap(),
+            serialized_variables
+        );
+
+        let spawn_in_terminal = SpawnInTerminal {
+            id: TaskId("test".to_string()),
+            full_label: "test".to_string(),
+            label: "test".to_string(),
+            command: "cargo".to_string(),
+            env: HashMap::from_iter(
+                ["RUST_LOG", "CARGO_TERM_COLOR", "ZED_ROW", "A"]
+                    .into_iter()
+                    .map(|key| (key.to_string(), String::new())),
+            ),
+            ..SpawnInTerminal::default()
+        };
+        let serialized = serde_json_lenient::to_value(&spawn_in_terminal).unwrap();
+        let env_keys = serialized["env"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            env_keys,
+            vec!["A", "CARGO_TERM_COLOR", "RUST_LOG", "ZED_ROW"]
+        );
+    }
 }
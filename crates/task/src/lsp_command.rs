@@ -0,0 +1,130 @@
+//! Tasks from the commands language servers attach to their code lenses, e.g. "Run test", see [`ResolvedTask::from_lsp_command`].
+
+use std::path::PathBuf;
+
+use serde_json_lenient::Value;
+
+use crate::{ResolvedTask, SpawnInTerminal, TaskArg, TaskId, TaskKind, TaskTemplate};
+
+impl ResolvedTask {
+    /// Adapts a language server `Command` into a task ready to spawn, with a synthetic template describing it.
+    ///
+    /// The command and its arguments are taken literally, no task variables are substituted in them.
+    /// String arguments are passed as is, nested lists of arguments are flattened into the list,
+    /// other JSON values, such as numbers or objects, are passed as their JSON text.
+    pub fn from_lsp_command(
+        id: impl Into<String>,
+        title: impl Into<String>,
+        command: impl Into<String>,
+        arguments: impl IntoIterator<Item = Value>,
+        cwd: Option<PathBuf>,
+    ) -> Self {
+        let title = title.into();
+        let command = command.into();
+        let mut args = Vec::new();
+        for argument in arguments {
+            push_lsp_argument(&mut args, argument);
+        }
+        let original_task = TaskTemplate {
+            label: title.clone(),
+            command: command.clone(),
+            args: args.iter().cloned().map(TaskArg::from).collect(),
+            cwd: cwd.as_ref().map(|cwd| cwd.to_string_lossy().into_owned()),
+            ..TaskTemplate::default()
+        };
+        let id = TaskId(id.into());
+        Self {
+            id: id.clone(),
+            original_task,
+            resolved_label: title.clone(),
+            resolved: Some(SpawnInTerminal {
+                id,
+                full_label: title.clone(),
+                label: title,
+                command,
+                args,
+                cwd,
+                ..SpawnInTerminal::default()
+            }),
+            last_run: None,
+            kind: TaskKind::Spawn,
+        }
+    }
+}
+
+fn push_lsp_argument(args: &mut Vec<String>, argument: Value) {
+    match argument {
+        Value::String(argument) => args.push(argument),
+        Value::Array(arguments) => {
+            for argument in arguments {
+                push_lsp_argument(args, argument);
+            }
+        }
+        argument => args.push(argument.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_lenient::json;
+
+    use super::*;
+
+    #[test]
+    fn test_lsp_command_conversion() {
+        let task = ResolvedTask::from_lsp_command(
+            "lsp_run_test_1",
+            "Run test $ZED_SYMBOL",
+            "cargo",
+            [
+                json!("test"),
+                json!(["--package", "task"]),
+                json!("--"),
+                json!("tests::my_test"),
+                json!({"exact": true}),
+                json!(2),
+            ],
+            Some(PathBuf::from("/project")),
+        );
+        assert_eq!(task.id, TaskId("lsp_run_test_1".to_string()));
+        assert_eq!(task.resolved_label, "Run test $ZED_SYMBOL");
+        assert_eq!(task.kind, TaskKind::Spawn);
+        let spawn = task.resolved.as_ref().unwrap();
+        assert_eq!(spawn.command, "cargo");
+        assert_eq!(
+            spawn.args,
+            vec![
+                "test",
+                "--package",
+                "task",
+                "--",
+                "tests::my_test",
+                "{\"exact\":true}",
+                "2"
+            ]
+        );
+        assert_eq!(spawn.cwd, Some(PathBuf::from("/project")));
+        assert_eq!(
+            spawn.label, "Run test $ZED_SYMBOL",
+            "No variables should be substituted"
+        );
+        assert_eq!(
+            spawn.display_command(),
+            "cargo test --package task -- tests::my_test '{\"exact\":true}' 2"
+        );
+        assert!(spawn.env.is_empty());
+
+        assert_eq!(task.original_task.command, "cargo");
+        assert_eq!(
+            task.original_task.args,
+            spawn
+                .args
+                .iter()
+                .cloned()
+                .map(TaskArg::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(task.original_task.cwd.as_deref(), Some("/project"));
+        assert!(task.original_task.is_runnable());
+    }
+}
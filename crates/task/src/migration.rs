@@ -0,0 +1,223 @@
+//! Upgrades of the older tasks file documents to the current shape, applied before the deserialization.
+
+use anyhow::bail;
+use serde::Serialize;
+use serde_json_lenient::Value;
+
+use crate::{
+    validation::{deserialize_templates, DEPRECATED_TASK_FIELDS},
+    TaskTemplates,
+};
+
+/// The current version of the tasks file format.
+///
+/// Files may declare their version as `{"version": 1, "tasks": [...]}`, plain lists of tasks are treated as version `0`;
+/// either way, the document gets migrated into a plain list of tasks, see [`TaskTemplates::migrate`].
+pub const TASKS_FORMAT_VERSION: u64 = 1;
+
+/// A single upgrade of the task list from one format version to the next one.
+struct Migration {
+    from_version: u64,
+    description: &'static str,
+    apply: fn(&mut [Value]) -> bool,
+}
+
+/// All migrations, ordered by the version they upgrade from.
+const MIGRATIONS: &[Migration] = &[Migration {
+    from_version: 0,
+    description: "renamed deprecated task fields",
+    apply: rename_deprecated_fields,
+}];
+
+fn rename_deprecated_fields(tasks: &mut [Value]) -> bool {
+    let mut changed = false;
+    for task in tasks.iter_mut().filter_map(Value::as_object_mut) {
+        for (deprecated_field, new_field) in DEPRECATED_TASK_FIELDS {
+            if task.contains_key(*new_field) {
+                continue;
+            }
+            if let Some(value) = task.remove(*deprecated_field) {
+                task.insert(new_field.to_string(), value);
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// The `version` the document declares, if any; documents without one are of version `0`.
+fn declared_version(document: &Value) -> Option<&Value> {
+    document.as_object()?.get("version")
+}
+
+/// What [`TaskTemplates::migrate`] did to the document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MigrationReport {
+    /// The format version the document declared, `0` for the documents without a version.
+    pub from_version: u64,
+    /// Descriptions of the migrations that changed the document, in the order of application.
+    pub applied: Vec<String>,
+}
+
+impl TaskTemplates {
+    /// Checks the format version the tasks file document declares against the [`TASKS_FORMAT_VERSION`] supported,
+    /// returning the declared version, or `0` if the document declares none, same as [`TaskTemplates::migrate`] assumes.
+    /// Fails for the newer versions, as their fields may mean something this version cannot interpret.
+    pub fn validate_against_schema_version(document: &Value) -> anyhow::Result<u64> {
+        let Some(version) = declared_version(document) else {
+            return Ok(0);
+        };
+        let Some(version) = version.as_u64() else {
+            bail!("Invalid tasks file version {version}, expected a non-negative integer");
+        };
+        if version > TASKS_FORMAT_VERSION {
+            bail!(
+                "This task file requires a newer Zed: its format version {version} is above the supported version {TASKS_FORMAT_VERSION}"
+            );
+        }
+        Ok(version)
+    }
+
+    /// Upgrades a tasks file document of any older format version into the current shape, a plain list of tasks, in place.
+    /// Documents of unknown (newer) versions are only unwrapped into the list of tasks, check them with [`TaskTemplates::validate_against_schema_version`] first.
+    pub fn migrate(document: &mut Value) -> MigrationReport {
+        let from_version = declared_version(document)
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        let mut tasks = match document.take() {
+            Value::Object(mut versioned) if versioned.contains_key("tasks") => {
+                versioned.remove("tasks").unwrap_or_default()
+            }
+            other => other,
+        };
+        let mut applied = Vec::new();
+        if let Value::Array(tasks) = &mut tasks {
+            for migration in MIGRATIONS
+                .iter()
+                .filter(|migration| migration.from_version >= from_version)
+            {
+                if (migration.apply)(tasks) {
+                    applied.push(migration.description.to_string());
+                }
+            }
+        }
+        *document = tasks;
+        MigrationReport {
+            from_version,
+            applied,
+        }
+    }
+}
+
+/// Rejects the documents of newer format versions and migrates the older ones to the current format version first, see [`TaskTemplates::migrate`].
+impl TryFrom<Value> for TaskTemplates {
+    type Error = anyhow::Error;
+
+    fn try_from(mut document: Value) -> Result<Self, Self::Error> {
+        Self::validate_against_schema_version(&document)?;
+        let report = Self::migrate(&mut document);
+        if !report.applied.is_empty() {
+            log::info!(
+                "Migrated tasks file from version {}: {}",
+                report.from_version,
+                report.applied.join(", ")
+            );
+        }
+        Ok(deserialize_templates(document)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_lenient::json;
+
+    use super::*;
+
+    #[test]
+    fn test_migrating_old_documents() {
+        let mut document = json!([
+            {"name": "build", "command": "cargo", "args": ["build"]},
+            {"label": "test", "command": "cargo", "args": ["test"]},
+        ]);
+        let report = TaskTemplates::migrate(&mut document);
+        assert_eq!(
+            report,
+            MigrationReport {
+                from_version: 0,
+                applied: vec!["renamed deprecated task fields".to_string()],
+            }
+        );
+        assert_eq!(document[0]["label"], "build");
+        assert!(document[0].get("name").is_none());
+        let templates = TaskTemplates::try_from(document).unwrap();
+        assert_eq!(
+            templates
+                .0
+                .iter()
+                .map(|template| template.label.as_str())
+                .collect::<Vec<_>>(),
+            vec!["build", "test"]
+        );
+
+        let mut versioned = json!({
+            "version": 0,
+            "tasks": [{"name": "build", "command": "cargo"}],
+        });
+        TaskTemplates::migrate(&mut versioned);
+        assert_eq!(versioned, json!([{"label": "build", "command": "cargo"}]));
+    }
+
+    #[test]
+    fn test_current_documents_are_kept() {
+        let tasks = json!([{"label": "build", "command": "cargo"}]);
+        let mut document = json!({"version": TASKS_FORMAT_VERSION, "tasks": tasks.clone()});
+        let report = TaskTemplates::migrate(&mut document);
+        assert_eq!(report.from_version, TASKS_FORMAT_VERSION);
+        assert!(report.applied.is_empty());
+        assert_eq!(document, tasks);
+
+        let mut document = tasks.clone();
+        assert!(TaskTemplates::migrate(&mut document).applied.is_empty());
+        assert_eq!(document, tasks);
+    }
+
+    #[test]
+    fn test_schema_version_validation() {
+        let future = json!({"version": TASKS_FORMAT_VERSION + 1, "tasks": []});
+        let error = TaskTemplates::validate_against_schema_version(&future).unwrap_err();
+        assert!(
+            error.to_string().contains("requires a newer Zed"),
+            "Unexpected error: {error}"
+        );
+        assert!(
+            TaskTemplates::try_from(future).is_err(),
+            "Newer documents should not be partially parsed"
+        );
+        assert!(TaskTemplates::validate_against_schema_version(
+            &json!({"version": "2.0.0", "tasks": []})
+        )
+        .is_err());
+
+        let current = json!({"version": TASKS_FORMAT_VERSION, "tasks": [{"label": "build", "command": "cargo"}]});
+        assert_eq!(
+            TaskTemplates::validate_against_schema_version(&current).unwrap(),
+            TASKS_FORMAT_VERSION
+        );
+        assert_eq!(TaskTemplates::try_from(current).unwrap().0.len(), 1);
+
+        for mut versionless in [
+            json!({"tasks": [{"name": "build", "command": "cargo"}]}),
+            json!([{"name": "build", "command": "cargo"}]),
+        ] {
+            assert_eq!(
+                TaskTemplates::validate_against_schema_version(&versionless).unwrap(),
+                0,
+                "Missing version should mean the oldest one"
+            );
+            let report = TaskTemplates::migrate(&mut versionless);
+            assert_eq!(report.from_version, 0);
+            assert_eq!(report.applied, vec!["renamed deprecated task fields"]);
+            assert_eq!(versionless, json!([{"label": "build", "command": "cargo"}]));
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! Quoting of the command lines for the different shells, so that the commands displayed or exported match the shell they are run in.
+
+use std::{borrow::Cow, fmt::Write as _};
+
+use crate::{posix_quote, SpawnInTerminal};
+
+/// The quoting rules of a shell, see [`QuotingDialect::for_shell`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum QuotingDialect {
+    /// `sh`, `bash` and `zsh`: single quotes, with the single quotes inside closed, escaped and reopened (`'it'\''s'`).
+    #[default]
+    Posix,
+    /// `fish`: single quotes, with the single quotes and the backslashes inside escaped with a backslash (`'it\'s'`),
+    /// as fish interprets these two escapes even in the single quotes.
+    Fish,
+    /// PowerShell: single quotes, with the single quotes inside doubled (`'it''s'`), so that neither `$` nor backticks get interpreted.
+    PowerShell,
+    /// `cmd.exe` batch files: double quotes, with the `"` inside and the backslashes before them escaped
+    /// the way programs parse their command line (`"say \"hi\""`), and `%` doubled.
+    Cmd,
+}
+
+impl QuotingDialect {
+    /// The dialect of the shell program given as a name or a path, e.g. `/usr/bin/fish` or `pwsh.exe`, [`QuotingDialect::Posix`] for unknown shells.
+    pub fn for_shell(program: &str) -> Self {
+        let name = program
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(program)
+            .to_ascii_lowercase();
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "fish" => Self::Fish,
+            "pwsh" | "powershell" => Self::PowerShell,
+            "cmd" => Self::Cmd,
+            _ => Self::Posix,
+        }
+    }
+
+    /// Quotes the argument, if it contains any characters that the shell may interpret.
+    pub fn quote(self, arg: &str) -> Cow<'_, str> {
+        match self {
+            Self::Posix => posix_quote(arg),
+            Self::Fish => {
+                let is_safe_char = |c: char| c.is_ascii_alphanumeric() || "_-./:=@+,".contains(c);
+                if !arg.is_empty() && arg.chars().all(is_safe_char) {
+                    return Cow::Borrowed(arg);
+                }
+                let mut quoted = String::with_capacity(arg.len() + 2);
+                quoted.push('\'');
+                for c in arg.chars() {
+                    if matches!(c, '\'' | '\\') {
+                        quoted.push('\\');
+                    }
+                    quoted.push(c);
+                }
+                quoted.push('\'');
+                Cow::Owned(quoted)
+            }
+            Self::PowerShell => {
+                let is_safe_char = |c: char| c.is_ascii_alphanumeric() || "_-./:=+".contains(c);
+                if !arg.is_empty() && arg.chars().all(is_safe_char) {
+                    return Cow::Borrowed(arg);
+                }
+                let mut quoted = String::with_capacity(arg.len() + 2);
+                quoted.push('\'');
+                for c in arg.chars() {
+                    // PowerShell treats the typographic single quotes as the ASCII ones.
+                    if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+                        quoted.push(c);
+                    }
+                    quoted.push(c);
+                }
+                quoted.push('\'');
+                Cow::Owned(quoted)
+            }
+            Self::Cmd => {
+                let is_safe_char = |c: char| c.is_ascii_alphanumeric() || "_-./:\\@+".contains(c);
+                if !arg.is_empty() && arg.chars().all(is_safe_char) {
+                    return Cow::Borrowed(arg);
+                }
+                let mut quoted = String::with_capacity(arg.len() + 2);
+                quoted.push('"');
+                let mut backslashes = 0;
+                for c in arg.chars() {
+                    match c {
+                        '\\' => backslashes += 1,
+                        '"' => {
+                            quoted.extend(std::iter::repeat('\\').take(backslashes * 2 + 1));
+                            backslashes = 0;
+                        }
+                        _ => {
+                            quoted.extend(std::iter::repeat('\\').take(backslashes));
+                            backslashes = 0;
+                        }
+                    }
+                    if c == '%' {
+                        quoted.push('%');
+                    }
+                    if c != '\\' {
+                        quoted.push(c);
+                    }
+                }
+                quoted.extend(std::iter::repeat('\\').take(backslashes * 2));
+                quoted.push('"');
+                Cow::Owned(quoted)
+            }
+        }
+    }
+
+    /// The command, followed by its arguments, all quoted if needed.
+    /// PowerShell command lines start with the `&` call operator if the command has to be quoted, as quoted strings are not run otherwise.
+    pub fn command_line(self, command: &str, args: &[String]) -> String {
+        let quoted_command = self.quote(command);
+        let mut command_line = match (self, &quoted_command) {
+            (Self::PowerShell, Cow::Owned(_)) => format!("& {quoted_command}"),
+            _ => quoted_command.into_owned(),
+        };
+        for arg in args {
+            command_line.push(' ');
+            command_line.push_str(&self.quote(arg));
+        }
+        command_line
+    }
+}
+
+impl SpawnInTerminal {
+    /// The command line to run the task with in the shell of the dialect given, the [`SpawnInTerminal::raw_command`] commands as is.
+    pub fn command_line(&self, dialect: QuotingDialect) -> String {
+        if self.raw_command {
+            return self.command.clone();
+        }
+        dialect.command_line(&self.command, &self.args)
+    }
+
+    /// A script running the task in the shell of the dialect given, e.g. to reproduce the task outside of Zed:
+    /// it changes into the task's cwd, sets the task env variables, sorted, then runs the [`SpawnInTerminal::command_line`].
+    pub fn to_shell_script(&self, dialect: QuotingDialect) -> String {
+        let mut env = self.env.iter().collect::<Vec<_>>();
+        env.sort();
+        let mut script = String::new();
+        match dialect {
+            QuotingDialect::Posix => {
+                script.push_str("#!/bin/sh\n");
+                if let Some(cwd) = &self.cwd {
+                    let cwd = cwd.to_string_lossy();
+                    writeln!(script, "cd {} || exit 1", dialect.quote(&cwd)).ok();
+                }
+                for (key, value) in env {
+                    writeln!(script, "export {key}={}", dialect.quote(value)).ok();
+                }
+            }
+            QuotingDialect::Fish => {
+                script.push_str("#!/usr/bin/env fish\n");
+                if let Some(cwd) = &self.cwd {
+                    let cwd = cwd.to_string_lossy();
+                    writeln!(script, "cd {}; or exit 1", dialect.quote(&cwd)).ok();
+                }
+                for (key, value) in env {
+                    writeln!(script, "set -gx {key} {}", dialect.quote(value)).ok();
+                }
+            }
+            QuotingDialect::PowerShell => {
+                if let Some(cwd) = &self.cwd {
+                    let cwd = cwd.to_string_lossy();
+                    writeln!(script, "Set-Location -LiteralPath {}", dialect.quote(&cwd)).ok();
+                }
+                for (key, value) in env {
+                    writeln!(script, "$env:{key} = {}", dialect.quote(value)).ok();
+                }
+            }
+            QuotingDialect::Cmd => {
+                script.push_str("@echo off\n");
+                if let Some(cwd) = &self.cwd {
+                    let cwd = cwd.to_string_lossy();
+                    writeln!(script, "cd /d {}", dialect.quote(&cwd)).ok();
+                }
+                for (key, value) in env {
+                    writeln!(script, "set \"{key}={}\"", value.replace('%', "%%")).ok();
+                }
+            }
+        }
+        script.push_str(&self.command_line(dialect));
+        script.push('\n');
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use collections::HashMap;
+
+    use crate::{TaskContext, TaskTemplate};
+
+    use super::*;
+
+    const ARGS: [&str; 6] = [
+        "plain",
+        "two words",
+        "say \"hi\"",
+        "it's $HOME",
+        "`date`",
+        "C:\\dir with space\\",
+    ];
+
+    fn quoted(dialect: QuotingDialect) -> Vec<String> {
+        ARGS.iter()
+            .map(|arg| dialect.quote(arg).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_posix_quoting() {
+        assert_eq!(
+            quoted(QuotingDialect::Posix),
+            vec![
+                "plain",
+                "'two words'",
+                "'say \"hi\"'",
+                "'it'\\''s $HOME'",
+                "'`date`'",
+                "'C:\\dir with space\\'",
+            ]
+        );
+        assert_eq!(QuotingDialect::Posix.quote(""), "''");
+    }
+
+    #[test]
+    fn test_fish_quoting() {
+        assert_eq!(
+            quoted(QuotingDialect::Fish),
+            vec![
+                "plain",
+                "'two words'",
+                "'say \"hi\"'",
+                "'it\\'s $HOME'",
+                "'`date`'",
+                "'C:\\\\dir with space\\\\'",
+            ]
+        );
+        assert_eq!(QuotingDialect::Fish.quote(""), "''");
+        assert_eq!(QuotingDialect::Fish.quote("100%"), "'100%'");
+    }
+
+    #[test]
+    fn test_powershell_quoting() {
+        assert_eq!(
+            quoted(QuotingDialect::PowerShell),
+            vec![
+                "plain",
+                "'two words'",
+                "'say \"hi\"'",
+                "'it''s $HOME'",
+                "'`date`'",
+                "'C:\\dir with space\\'",
+            ]
+        );
+        assert_eq!(
+            QuotingDialect::PowerShell.quote("it\u{2019}s"),
+            "'it\u{2019}\u{2019}s'"
+        );
+        assert_eq!(
+            QuotingDialect::PowerShell
+                .command_line("C:\\Program Files\\tool.exe", &["--flag".to_string()]),
+            "& 'C:\\Program Files\\tool.exe' --flag"
+        );
+    }
+
+    #[test]
+    fn test_cmd_quoting() {
+        assert_eq!(
+            quoted(QuotingDialect::Cmd),
+            vec![
+                "plain",
+                "\"two words\"",
+                "\"say \\\"hi\\\"\"",
+                "\"it's $HOME\"",
+                "\"`date`\"",
+                "\"C:\\dir with space\\\\\"",
+            ]
+        );
+        assert_eq!(QuotingDialect::Cmd.quote("C:\\dir\\file"), "C:\\dir\\file");
+        assert_eq!(QuotingDialect::Cmd.quote("a\\\"b"), "\"a\\\\\\\"b\"");
+        assert_eq!(QuotingDialect::Cmd.quote("100%"), "\"100%%\"");
+    }
+
+    #[test]
+    fn test_dialect_for_shell() {
+        assert_eq!(
+            QuotingDialect::for_shell("/usr/bin/fish"),
+            QuotingDialect::Fish
+        );
+        assert_eq!(QuotingDialect::for_shell("bash"), QuotingDialect::Posix);
+        assert_eq!(
+            QuotingDialect::for_shell("pwsh"),
+            QuotingDialect::PowerShell
+        );
+        assert_eq!(
+            QuotingDialect::for_shell(
+                "C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\powershell.exe"
+            ),
+            QuotingDialect::PowerShell
+        );
+        assert_eq!(QuotingDialect::for_shell("CMD.EXE"), QuotingDialect::Cmd);
+    }
+
+    #[test]
+    fn test_shell_scripts() {
+        let spawn = TaskTemplate {
+            label: "greet".to_string(),
+            command: "echo".to_string(),
+            args: vec!["hello world".into()],
+            env: HashMap::from_iter([("GREETING".to_string(), "it's 100%".into())]),
+            cwd: Some("/project".to_string()),
+            ..TaskTemplate::default()
+        }
+        .resolve_task("test", TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert_eq!(spawn.cwd, Some(PathBuf::from("/project")));
+        assert_eq!(
+            spawn.display_command(),
+            spawn.command_line(QuotingDialect::Posix)
+        );
+        assert_eq!(
+            spawn.to_shell_script(QuotingDialect::Posix),
+            "#!/bin/sh\ncd /project || exit 1\nexport GREETING='it'\\''s 100%'\necho 'hello world'\n"
+        );
+        assert_eq!(
+            spawn.to_shell_script(QuotingDialect::Fish),
+            "#!/usr/bin/env fish\ncd /project; or exit 1\nset -gx GREETING 'it\\'s 100%'\necho 'hello world'\n"
+        );
+        assert_eq!(
+            spawn.to_shell_script(QuotingDialect::PowerShell),
+            "Set-Location -LiteralPath /project\n$env:GREETING = 'it''s 100%'\necho 'hello world'\n"
+        );
+        assert_eq!(
+            spawn.to_shell_script(QuotingDialect::Cmd),
+            "@echo off\ncd /d /project\nset \"GREETING=it's 100%%\"\necho \"hello world\"\n"
+        );
+    }
+
+    #[test]
+    fn test_raw_commands() {
+        let spawn = TaskTemplate {
+            label: "count".to_string(),
+            command: "grep -r \"fn main\" src | wc -l".to_string(),
+            raw_command: true,
+            ..TaskTemplate::default()
+        }
+        .resolve_task("test", TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert!(spawn.raw_command);
+        for dialect in [
+            QuotingDialect::Posix,
+            QuotingDialect::Fish,
+            QuotingDialect::PowerShell,
+            QuotingDialect::Cmd,
+        ] {
+            assert_eq!(
+                spawn.command_line(dialect),
+                "grep -r \"fn main\" src | wc -l",
+                "Raw commands should not be quoted"
+            );
+        }
+        assert_eq!(
+            spawn.to_shell_script(QuotingDialect::Posix),
+            "#!/bin/sh\ngrep -r \"fn main\" src | wc -l\n"
+        );
+        assert_eq!(spawn.argv().last(), Some(&spawn.command));
+    }
+}
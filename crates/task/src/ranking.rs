@@ -0,0 +1,175 @@
+//! Ranking of the task templates for pickers and palettes, combining the fuzzy match of the label with the template applicability.
+
+use collections::HashSet;
+
+use crate::{TaskContext, TaskTemplate, TaskTemplates, OPTIONAL_VARIABLES};
+
+/// A boost for the templates that can be resolved within the context, i.e. all variables they reference are present (or optional).
+pub const APPLICABLE_BOOST: f64 = 1.0;
+/// An additional boost for the applicable templates that reference the context variables, e.g. the current file.
+pub const CONTEXTUAL_BOOST: f64 = 0.5;
+
+/// Components of the template rank, see [`TaskTemplates::rank`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RankInfo {
+    /// How well the label matches the query, in `(0.0, 1.0]`; `1.0` for an empty query.
+    pub match_score: f64,
+    /// [`APPLICABLE_BOOST`] and [`CONTEXTUAL_BOOST`] earned by the template, if any.
+    pub applicability_boost: f64,
+    /// The resulting score, a sum of the ones above.
+    pub total: f64,
+}
+
+impl TaskTemplates {
+    /// Ranks the templates which labels fuzzy match the query, returning their indices with the rank components, best ones first.
+    ///
+    /// Since the match score never exceeds `1.0`, applicable templates always outrank not applicable ones,
+    /// and contextual templates outrank the other applicable ones, unless their labels match the query considerably worse.
+    /// Templates with equal scores keep their relative order.
+    pub fn rank(&self, query: &str, context: &TaskContext) -> Vec<(usize, RankInfo)> {
+        let context_variables = context
+            .task_variables
+            .0
+            .keys()
+            .map(|name| name.to_string())
+            .collect::<HashSet<_>>();
+        let mut ranked = self
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, template)| {
+                let match_score = match_score(&template.label, query)?;
+                let applicability_boost = applicability_boost(template, &context_variables);
+                Some((
+                    index,
+                    RankInfo {
+                        match_score,
+                        applicability_boost,
+                        total: match_score + applicability_boost,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(_, a), (_, b)| b.total.total_cmp(&a.total));
+        ranked
+    }
+}
+
+/// Scores a case-insensitive subsequence match of the query in the label:
+/// every matched character gets a point, and another one for each of being consecutive to the previous match and starting a word.
+fn match_score(label: &str, query: &str) -> Option<f64> {
+    let query = query.trim().to_lowercase().chars().collect::<Vec<_>>();
+    if query.is_empty() {
+        return Some(1.0);
+    }
+    let mut points = 0;
+    let mut query_chars = query.iter().peekable();
+    let mut previous_label_char = None;
+    let mut previous_matched = false;
+    for label_char in label.chars().flat_map(char::to_lowercase) {
+        let Some(&&query_char) = query_chars.peek() else {
+            break;
+        };
+        let matched = label_char == query_char;
+        if matched {
+            query_chars.next();
+            points += 1;
+            if previous_matched {
+                points += 1;
+            }
+            if previous_label_char.map_or(true, |c: char| !c.is_alphanumeric()) {
+                points += 1;
+            }
+        }
+        previous_matched = matched;
+        previous_label_char = Some(label_char);
+    }
+    if query_chars.peek().is_some() {
+        return None;
+    }
+    Some(points as f64 / (query.len() * 3) as f64)
+}
+
+fn applicability_boost(template: &TaskTemplate, context_variables: &HashSet<String>) -> f64 {
+    let mut references_context = false;
+    for reference in template.variable_references() {
+        if context_variables.contains(&reference) {
+            references_context = true;
+        } else if !OPTIONAL_VARIABLES
+            .iter()
+            .any(|optional| optional.to_string() == reference)
+        {
+            return 0.0;
+        }
+    }
+    if references_context {
+        APPLICABLE_BOOST + CONTEXTUAL_BOOST
+    } else {
+        APPLICABLE_BOOST
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaskVariables, VariableName};
+
+    use super::*;
+
+    #[test]
+    fn test_applicable_matches_outrank_better_fuzzy_matches() {
+        let template = |label: &str, args: &[&str]| TaskTemplate {
+            label: label.to_string(),
+            command: "cargo".to_string(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+            ..TaskTemplate::default()
+        };
+        let templates = TaskTemplates(vec![
+            template("test symbol", &["test", "$ZED_SYMBOL"]),
+            template("test file", &["test", "${ZED_FILE:unix}"]),
+            template("cargo build", &["build"]),
+            template("test workspace", &["test", "--workspace"]),
+        ]);
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(
+                VariableName::File,
+                "src/main.rs".to_string(),
+            )]),
+        };
+
+        let ranked = templates.rank("test", &context);
+        let indices = ranked.iter().map(|(index, _)| *index).collect::<Vec<_>>();
+        assert_eq!(
+            indices,
+            vec![1, 3, 0],
+            "Contextual applicable template should go first, then a generic one, then the one referencing a missing variable"
+        );
+        let (_, file_rank) = ranked[0];
+        let (_, symbol_rank) = ranked[2];
+        assert_eq!(file_rank.match_score, symbol_rank.match_score);
+        assert_eq!(
+            file_rank.applicability_boost,
+            APPLICABLE_BOOST + CONTEXTUAL_BOOST
+        );
+        assert_eq!(symbol_rank.applicability_boost, 0.0);
+
+        let ranked = templates.rank("tsw", &context);
+        assert_eq!(ranked[0].0, 3);
+        let ranked = templates.rank("tsy", &context);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0, 0);
+        let weak_applicable = ranked[0].1;
+
+        let exact_not_applicable = templates.rank("test symbol", &context)[0].1;
+        let weak_applicable_file = templates.rank("tf", &context)[0].1;
+        assert!(exact_not_applicable.match_score > weak_applicable_file.match_score);
+        assert!(
+            weak_applicable_file.total > exact_not_applicable.total,
+            "An applicable fuzzy match should outrank a better, but not applicable one"
+        );
+        assert!(weak_applicable.total < weak_applicable_file.total);
+
+        assert_eq!(templates.rank("", &context).len(), templates.0.len());
+        assert!(templates.rank("xyz", &context).is_empty());
+    }
+}
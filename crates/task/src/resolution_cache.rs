@@ -0,0 +1,242 @@
+//! A cache of the resolved tasks, to avoid resolving the same templates within the same context repeatedly.
+
+use collections::HashMap;
+
+use crate::{ResolvedTask, TaskContext, TaskTemplate, TaskTemplates};
+
+/// Keeps the tasks resolved by [`TaskTemplate::resolve_task`] with the same `id_base`,
+/// keyed by the [`TaskTemplate::content_hash`] and [`TaskContext::content_hash`] of the resolution inputs.
+///
+/// Any change in a template or a context produces a new key, so no stale task is ever returned;
+/// entries of the templates that are gone can be dropped with [`ResolutionCache::retain_templates`].
+#[derive(Debug, Default)]
+pub struct ResolutionCache {
+    id_base: String,
+    resolved: HashMap<(u64, u64), ResolvedTask>,
+    resolutions_made: usize,
+}
+
+impl ResolutionCache {
+    /// Creates an empty cache for the tasks resolved with the `id_base` given.
+    pub fn new(id_base: impl Into<String>) -> Self {
+        Self {
+            id_base: id_base.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the task resolved from the template within the context given, resolving and caching it if was not cached before.
+    /// Templates that fail to resolve are not cached.
+    pub fn resolve(&mut self, template: &TaskTemplate, cx: &TaskContext) -> Option<ResolvedTask> {
+        let key = (template.content_hash(), cx.content_hash());
+        if let Some(resolved) = self.resolved.get(&key) {
+            return Some(resolved.clone());
+        }
+        self.resolutions_made += 1;
+        let resolved = template.resolve_task(&self.id_base, cx.clone())?;
+        self.resolved.insert(key, resolved.clone());
+        Some(resolved)
+    }
+
+    /// Resolves the templates lazily, as the iterator gets consumed, the same way [`TaskTemplates::iter_resolved`] does,
+    /// but serving the tasks resolved before from the cache.
+    pub fn iter_resolved<'a>(
+        &'a mut self,
+        templates: &'a TaskTemplates,
+        cx: &'a TaskContext,
+    ) -> impl Iterator<Item = ResolvedTask> + 'a {
+        templates
+            .0
+            .iter()
+            .filter_map(move |template| self.resolve(template, cx))
+    }
+
+    /// Drops the cached tasks of all templates that are not among the `templates` given, e.g. after the task file got changed.
+    pub fn retain_templates(&mut self, templates: &TaskTemplates) {
+        let template_hashes = templates
+            .0
+            .iter()
+            .map(TaskTemplate::content_hash)
+            .collect::<Vec<_>>();
+        self.resolved
+            .retain(|(template_hash, _), _| template_hashes.contains(template_hash));
+    }
+
+    /// Drops all cached tasks.
+    pub fn clear(&mut self) {
+        self.resolved.clear();
+    }
+
+    /// Number of the tasks cached.
+    pub fn len(&self) -> usize {
+        self.resolved.len()
+    }
+
+    /// Whether no tasks are cached.
+    pub fn is_empty(&self) -> bool {
+        self.resolved.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::{ResolvingContext, TaskVariables, VariableName};
+
+    use super::*;
+
+    #[test]
+    fn test_cache_hits_skip_resolution() {
+        let template = TaskTemplate {
+            label: "echo ${ZED_FILE}".to_string(),
+            command: "echo".to_string(),
+            args: vec!["$ZED_FILE".into()],
+            ..TaskTemplate::default()
+        };
+        let context = |file: &str| TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::File, file.to_string()),
+                (
+                    VariableName::Custom(Cow::Borrowed("PACKAGE")),
+                    "task".to_string(),
+                ),
+            ]),
+        };
+        let mut cache = ResolutionCache::new("test_base");
+
+        let resolved = cache.resolve(&template, &context("main.rs")).unwrap();
+        assert_eq!(
+            Some(resolved.clone()),
+            template.resolve_task("test_base", context("main.rs"))
+        );
+        assert_eq!(cache.resolutions_made, 1);
+        assert_eq!(
+            cache.resolve(&template.clone(), &context("main.rs")),
+            Some(resolved.clone())
+        );
+        assert_eq!(
+            cache.resolutions_made, 1,
+            "Equal template and context should be served from the cache"
+        );
+
+        let other_file = cache.resolve(&template, &context("lib.rs")).unwrap();
+        assert_ne!(other_file, resolved);
+        assert_eq!(cache.resolutions_made, 2);
+        let changed_template = TaskTemplate {
+            command: "cat".to_string(),
+            ..template.clone()
+        };
+        let changed = cache
+            .resolve(&changed_template, &context("main.rs"))
+            .unwrap();
+        assert_eq!(changed.resolved.unwrap().command, "cat");
+        assert_eq!(cache.resolutions_made, 3);
+        assert_eq!(cache.len(), 3);
+
+        cache.retain_templates(&TaskTemplates(vec![changed_template]));
+        assert_eq!(cache.len(), 1);
+        cache.resolve(&template, &context("main.rs")).unwrap();
+        assert_eq!(
+            cache.resolutions_made, 4,
+            "Dropped entries should be resolved again"
+        );
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_content_hashes_ignore_insertion_order() {
+        let first = (VariableName::File, "main.rs".to_string());
+        let second = (VariableName::Row, "1".to_string());
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([first.clone(), second.clone()]),
+        };
+        let reordered = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([second, first]),
+        };
+        assert_eq!(context.content_hash(), reordered.content_hash());
+        assert_ne!(
+            context.content_hash(),
+            TaskContext::default().content_hash()
+        );
+
+        let template = TaskTemplate {
+            label: "test".to_string(),
+            command: "test".to_string(),
+            env: HashMap::from_iter([("A".to_string(), "1".into()), ("B".to_string(), "2".into())]),
+            ..TaskTemplate::default()
+        };
+        let reordered = TaskTemplate {
+            env: HashMap::from_iter([("B".to_string(), "2".into()), ("A".to_string(), "1".into())]),
+            ..template.clone()
+        };
+        assert_eq!(template.content_hash(), reordered.content_hash());
+        assert_ne!(
+            template.content_hash(),
+            TaskTemplate::default().content_hash()
+        );
+    }
+
+    #[test]
+    fn test_lazy_resolution() {
+        let template = |label: &str| TaskTemplate {
+            label: label.to_string(),
+            command: "cargo".to_string(),
+            args: vec![label.into(), "$ZED_FILE".into()],
+            ..TaskTemplate::default()
+        };
+        let templates = TaskTemplates(vec![
+            template("build"),
+            TaskTemplate {
+                command: "$ZED_SYMBOL".to_string(),
+                ..template("unresolvable")
+            },
+            template("test"),
+            template("bench"),
+        ]);
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(VariableName::File, "main.rs".to_string())]),
+        };
+
+        let resolved = templates
+            .iter_resolved("test_base", &context)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            resolved,
+            ResolvingContext::new(context.clone())
+                .resolve_all("test_base", &templates)
+                .into_iter()
+                .map(|(_, task)| task)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(resolved.len(), 3);
+
+        let mut cache = ResolutionCache::new("test_base");
+        let first_two = cache
+            .iter_resolved(&templates, &context)
+            .take(2)
+            .map(|task| task.resolved_label)
+            .collect::<Vec<_>>();
+        assert_eq!(first_two, vec!["build", "test"]);
+        assert_eq!(
+            cache.resolutions_made, 3,
+            "Only the consumed prefix should be resolved"
+        );
+        assert_eq!(
+            cache
+                .iter_resolved(&templates, &context)
+                .collect::<Vec<_>>(),
+            resolved
+        );
+        assert_eq!(
+            cache.resolutions_made, 5,
+            "Cached tasks should not be resolved again, unlike the ones that failed"
+        );
+    }
+}
@@ -0,0 +1,685 @@
+//! A [`TaskContext`] wrapper, computing expensive variables lazily, at most once per resolution batch, and a registry of custom variable providers for it.
+
+use std::{
+    borrow::Cow,
+    future::Future,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+use collections::HashMap;
+use futures::{
+    future::{BoxFuture, LocalBoxFuture},
+    FutureExt as _,
+};
+use gpui::BackgroundExecutor;
+
+use crate::{ResolvedTask, TaskContext, TaskTemplate, TaskTemplates, VariableName};
+
+type VariableProvider<'a> = Box<dyn FnMut(&VariableName, &TaskContext) -> Option<String> + 'a>;
+
+type BackgroundVariableProvider = (
+    BackgroundExecutor,
+    Box<dyn Fn() -> BoxFuture<'static, Option<String>>>,
+);
+
+type PendingVariable = (BackgroundExecutor, LocalBoxFuture<'static, Option<String>>);
+
+/// How long the resolutions without an explicit deadline wait for the background providers,
+/// see [`ResolvingContext::with_background_provider`].
+pub const DEFAULT_PROVIDER_TIMEOUT: Duration = Duration::from_secs(2);
+
+type ContextVariableProvider = Box<dyn Fn(&TaskContext) -> Option<String>>;
+
+/// A registry of custom variables, contributed by e.g. extensions, with the providers computing their values from the [`TaskContext`].
+/// Attach it to a [`ResolvingContext`] with [`ResolvingContext::with_providers`].
+#[derive(Default)]
+pub struct VariableProviders {
+    providers: HashMap<VariableName, ContextVariableProvider>,
+}
+
+impl VariableProviders {
+    /// Registers a provider of the [`VariableName::Custom`] variable with the name given.
+    /// Fails if the variable already has a provider registered, keeping the existing one.
+    pub fn register(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        provider: impl Fn(&TaskContext) -> Option<String> + 'static,
+    ) -> anyhow::Result<()> {
+        let variable = VariableName::Custom(name.into());
+        if self.providers.contains_key(&variable) {
+            bail!("Variable {variable} already has a provider registered");
+        }
+        self.providers.insert(variable, Box::new(provider));
+        Ok(())
+    }
+
+    /// Whether the variable has a provider registered.
+    pub fn contains(&self, variable: &VariableName) -> bool {
+        self.providers.contains_key(variable)
+    }
+}
+
+/// A problem that did not prevent the task from being resolved, but may have affected its fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolutionWarning {
+    /// The background provider of the variable did not return a value before the deadline, so the variable was resolved as empty,
+    /// see [`ResolvingContext::resolve_with_deadline`].
+    ProviderTimedOut {
+        /// The variable that was not provided in time.
+        name: VariableName,
+    },
+    /// The task would run outside of the worktree it was resolved for, e.g. because of a misconfigured `../../` in its `cwd`.
+    /// Some tasks run elsewhere intentionally, so the task is still resolved.
+    CwdOutsideWorktree {
+        /// The resolved working directory of the task.
+        cwd: PathBuf,
+        /// The [`VariableName::WorktreeRoot`] of the context.
+        worktree_root: PathBuf,
+    },
+}
+
+/// A [`TaskContext`] with additional variables, provided on demand: each provider gets called
+/// only when a template referencing its variable gets resolved, and only once per the [`ResolvingContext`] lifetime.
+///
+/// Create a new one for each batch of resolutions, so that the values do not get stale.
+pub struct ResolvingContext<'a> {
+    context: TaskContext,
+    providers: HashMap<VariableName, VariableProvider<'a>>,
+    background_providers: HashMap<VariableName, BackgroundVariableProvider>,
+    pending: HashMap<VariableName, PendingVariable>,
+    provided: HashMap<VariableName, Option<String>>,
+}
+
+impl<'a> ResolvingContext<'a> {
+    /// Wraps the context with the variables already known.
+    pub fn new(context: TaskContext) -> Self {
+        Self {
+            context,
+            providers: HashMap::default(),
+            background_providers: HashMap::default(),
+            pending: HashMap::default(),
+            provided: HashMap::default(),
+        }
+    }
+
+    /// Registers a provider of the variable value, used if the wrapped context has no such variable.
+    /// Providers returning `None` leave the variable unset.
+    /// See [`ResolvingContext::with_context_provider`] for the providers computing the value from the other variables.
+    pub fn with_provider(
+        self,
+        variable: VariableName,
+        mut provider: impl FnMut() -> Option<String> + 'a,
+    ) -> Self {
+        self.with_context_provider(variable, move |_, _| provider())
+    }
+
+    /// Registers a provider of the variable value, same as [`ResolvingContext::with_provider`] does,
+    /// that gets the name of the variable requested and the context to compute the value from, e.g. a path relative to the worktree root:
+    /// the wrapped context, with the variables provided so far for the template being resolved.
+    pub fn with_context_provider(
+        mut self,
+        variable: VariableName,
+        provider: impl FnMut(&VariableName, &TaskContext) -> Option<String> + 'a,
+    ) -> Self {
+        self.providers.insert(variable, Box::new(provider));
+        self
+    }
+
+    /// Registers a provider of the variable value that runs on the background executor, e.g. for the slow ones calling external tools.
+    /// The background providers of a template run concurrently, and the resolutions stop waiting for them at the deadline:
+    /// the one given to [`ResolvingContext::resolve_with_deadline`], or [`DEFAULT_PROVIDER_TIMEOUT`] from now for the rest.
+    /// The providers registered with [`ResolvingContext::with_provider`] always run to completion.
+    pub fn with_background_provider<F>(
+        mut self,
+        executor: &BackgroundExecutor,
+        variable: VariableName,
+        provider: impl Fn() -> F + 'static,
+    ) -> Self
+    where
+        F: Future<Output = Option<String>> + Send + 'static,
+    {
+        self.background_providers.insert(
+            variable,
+            (executor.clone(), Box::new(move || provider().boxed())),
+        );
+        self
+    }
+
+    /// Registers all providers from the registry, same as [`ResolvingContext::with_context_provider`] does.
+    pub fn with_providers(mut self, providers: &'a VariableProviders) -> Self {
+        for (variable, provider) in &providers.providers {
+            self.providers.insert(
+                variable.clone(),
+                Box::new(move |_, context| provider(context)),
+            );
+        }
+        self
+    }
+
+    /// Produces the context to resolve the template with: the wrapped context, with all provided variables the template references.
+    pub fn context_for(&mut self, template: &TaskTemplate) -> TaskContext {
+        self.context_until(
+            template,
+            Instant::now() + DEFAULT_PROVIDER_TIMEOUT,
+            &mut Vec::new(),
+        )
+    }
+
+    fn context_until(
+        &mut self,
+        template: &TaskTemplate,
+        deadline: Instant,
+        warnings: &mut Vec<ResolutionWarning>,
+    ) -> TaskContext {
+        let mut context = self.context.clone();
+        let references = template.variable_references();
+        // Start all background providers first, so that they run while the rest are computed.
+        let mut awaited = Vec::new();
+        for reference in &references {
+            let Some((variable, (executor, provider))) = self
+                .background_providers
+                .iter()
+                .find(|(variable, _)| variable.to_string() == *reference)
+            else {
+                continue;
+            };
+            if context.task_variables.0.contains_key(variable) {
+                continue;
+            }
+            if !self.provided.contains_key(variable) && !self.pending.contains_key(variable) {
+                let task = executor.spawn(provider());
+                self.pending
+                    .insert(variable.clone(), (executor.clone(), task.boxed_local()));
+            }
+            awaited.push(variable.clone());
+        }
+        for reference in references {
+            let Some((variable, provider)) = self
+                .providers
+                .iter_mut()
+                .find(|(variable, _)| variable.to_string() == reference)
+            else {
+                continue;
+            };
+            if context.task_variables.0.contains_key(variable) {
+                continue;
+            }
+            let value = self
+                .provided
+                .entry(variable.clone())
+                .or_insert_with(|| provider(variable, &context))
+                .clone();
+            if let Some(value) = value {
+                context.task_variables.insert(variable.clone(), value);
+            }
+        }
+        for variable in awaited {
+            if let Some((executor, task)) = self.pending.remove(&variable) {
+                match executor
+                    .block_with_timeout(deadline.saturating_duration_since(Instant::now()), task)
+                {
+                    Ok(value) => {
+                        self.provided.insert(variable.clone(), value);
+                    }
+                    Err(task) => {
+                        // Keep waiting for the value in the background, for the later resolutions.
+                        self.pending
+                            .insert(variable.clone(), (executor, task.boxed_local()));
+                        context
+                            .task_variables
+                            .insert(variable.clone(), String::new());
+                        warnings.push(ResolutionWarning::ProviderTimedOut { name: variable });
+                        continue;
+                    }
+                }
+            }
+            if let Some(Some(value)) = self.provided.get(&variable) {
+                context.task_variables.insert(variable, value.clone());
+            }
+        }
+        context
+    }
+
+    /// Resolves the template, see [`TaskTemplate::resolve_task`].
+    /// Each provided variable is computed at most once and its value is reused for all occurrences in the template fields.
+    pub fn resolve(&mut self, id_base: &str, template: &TaskTemplate) -> Option<ResolvedTask> {
+        let context = self.context_for(template);
+        template.resolve_task(id_base, context)
+    }
+
+    /// Resolves the template, same as [`ResolvingContext::resolve`] does, but waits for the background providers only until the `deadline`:
+    /// the variables not provided by then are resolved as empty, with a [`ResolutionWarning::ProviderTimedOut`] for each,
+    /// and get their values in the later resolutions, once their providers finish.
+    pub fn resolve_with_deadline(
+        &mut self,
+        id_base: &str,
+        template: &TaskTemplate,
+        deadline: Instant,
+    ) -> Option<(ResolvedTask, Vec<ResolutionWarning>)> {
+        self.resolve_leniently(id_base, template, deadline)
+    }
+
+    /// Resolves the template, same as [`ResolvingContext::resolve`] does, also reporting the problems worth double-checking,
+    /// such as a [`ResolutionWarning::CwdOutsideWorktree`].
+    pub fn resolve_with_warnings(
+        &mut self,
+        id_base: &str,
+        template: &TaskTemplate,
+    ) -> Option<(ResolvedTask, Vec<ResolutionWarning>)> {
+        self.resolve_leniently(id_base, template, Instant::now() + DEFAULT_PROVIDER_TIMEOUT)
+    }
+
+    fn resolve_leniently(
+        &mut self,
+        id_base: &str,
+        template: &TaskTemplate,
+        deadline: Instant,
+    ) -> Option<(ResolvedTask, Vec<ResolutionWarning>)> {
+        let mut warnings = Vec::new();
+        let context = self.context_until(template, deadline, &mut warnings);
+        let worktree_root = context
+            .task_variables
+            .0
+            .get(&VariableName::WorktreeRoot)
+            .map(PathBuf::from);
+        let resolved = template.resolve_task(id_base, context)?;
+        let cwd = resolved
+            .resolved
+            .as_ref()
+            .and_then(|spawn| spawn.cwd.as_ref());
+        if let Some((cwd, worktree_root)) = cwd.zip(worktree_root) {
+            if !is_within(cwd, &worktree_root) {
+                warnings.push(ResolutionWarning::CwdOutsideWorktree {
+                    cwd: cwd.clone(),
+                    worktree_root,
+                });
+            }
+        }
+        Some((resolved, warnings))
+    }
+
+    /// Resolves all templates, returning the ones resolved successfully with their indices.
+    pub fn resolve_all(
+        &mut self,
+        id_base: &str,
+        templates: &TaskTemplates,
+    ) -> Vec<(usize, ResolvedTask)> {
+        templates
+            .0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, template)| Some((index, self.resolve(id_base, template)?)))
+            .collect()
+    }
+}
+
+/// Whether the path is the `root` or is inside it, with the `..` components resolved lexically, without touching the file system.
+/// Relative paths are taken relative to the `root`.
+fn is_within(path: &Path, root: &Path) -> bool {
+    let normalize = |path: &Path| {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    };
+    normalize(&root.join(path)).starts_with(normalize(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, cell::Cell};
+
+    use futures::channel::oneshot;
+    use gpui::TestAppContext;
+
+    use crate::{TaskArg, TaskVariables};
+
+    use super::*;
+
+    #[test]
+    fn test_providers_are_invoked_once_per_batch() {
+        let branch = VariableName::Custom(Cow::Borrowed("GIT_BRANCH"));
+        let template = |label: &str, args: Vec<String>| TaskTemplate {
+            label: label.to_string(),
+            command: "git".to_string(),
+            args: args.into_iter().map(TaskArg::from).collect(),
+            ..TaskTemplate::default()
+        };
+        let templates = TaskTemplates(vec![
+            template("push", vec!["push".to_string(), branch.template_value()]),
+            template("status", vec!["status".to_string()]),
+            template(
+                "log ${ZED_CUSTOM_GIT_BRANCH}",
+                vec!["log".to_string(), branch.template_value()],
+            ),
+        ]);
+        let branch_calls = Cell::new(0);
+        let unused_calls = Cell::new(0);
+
+        let mut context = ResolvingContext::new(TaskContext::default())
+            .with_provider(branch.clone(), || {
+                branch_calls.set(branch_calls.get() + 1);
+                Some("main".to_string())
+            })
+            .with_provider(VariableName::Symbol, || {
+                unused_calls.set(unused_calls.get() + 1);
+                Some("unused".to_string())
+            });
+        let resolved = context.resolve_all("test_base", &templates);
+        assert_eq!(resolved.len(), 3);
+        assert_eq!(
+            resolved[0].1.resolved.as_ref().unwrap().args,
+            vec!["push", "main"]
+        );
+        assert_eq!(resolved[2].1.resolved_label, "log main");
+        assert_eq!(
+            branch_calls.get(),
+            1,
+            "Provider should be called once for all templates referencing its variable"
+        );
+        assert_eq!(
+            unused_calls.get(),
+            0,
+            "Providers of variables not referenced should not be called"
+        );
+
+        context.resolve_all("test_base", &templates);
+        assert_eq!(branch_calls.get(), 1);
+    }
+
+    #[test]
+    fn test_known_variables_take_precedence_over_providers() {
+        let template = TaskTemplate {
+            label: "open".to_string(),
+            command: "open".to_string(),
+            args: vec![VariableName::File.template_value().into()],
+            ..TaskTemplate::default()
+        };
+        let provider_calls = Cell::new(0);
+        let mut context = ResolvingContext::new(TaskContext {
+            cwd: None,
+            task_variables: [(VariableName::File, "main.rs".to_string())]
+                .into_iter()
+                .collect(),
+        })
+        .with_provider(VariableName::File, || {
+            provider_calls.set(provider_calls.get() + 1);
+            Some("lib.rs".to_string())
+        });
+        let resolved = context.resolve("test_base", &template).unwrap();
+        assert_eq!(resolved.resolved.unwrap().args, vec!["main.rs"]);
+        assert_eq!(provider_calls.get(), 0);
+    }
+
+    #[test]
+    fn test_variable_providers_registry() {
+        let mut providers = VariableProviders::default();
+        providers
+            .register("GIT_BRANCH", |_| Some("main".to_string()))
+            .unwrap();
+        providers
+            .register("FILE_UPPERCASE", |context| {
+                Some(
+                    context
+                        .task_variables
+                        .0
+                        .get(&VariableName::File)?
+                        .to_uppercase(),
+                )
+            })
+            .unwrap();
+        let conflict = providers.register("GIT_BRANCH", |_| Some("dev".to_string()));
+        assert!(
+            conflict.is_err(),
+            "Providers claiming a registered variable should be rejected"
+        );
+        assert!(conflict
+            .unwrap_err()
+            .to_string()
+            .contains("ZED_CUSTOM_GIT_BRANCH"));
+        assert!(providers.contains(&VariableName::Custom(Cow::Borrowed("GIT_BRANCH"))));
+
+        let templates = TaskTemplates(vec![
+            TaskTemplate {
+                label: "push".to_string(),
+                command: "git".to_string(),
+                args: vec!["push".into(), "$ZED_CUSTOM_GIT_BRANCH".into()],
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "shout".to_string(),
+                command: "echo".to_string(),
+                args: vec!["$ZED_CUSTOM_FILE_UPPERCASE".into()],
+                ..TaskTemplate::default()
+            },
+        ]);
+        let mut context = ResolvingContext::new(TaskContext {
+            cwd: None,
+            task_variables: [(VariableName::File, "main.rs".to_string())]
+                .into_iter()
+                .collect(),
+        })
+        .with_providers(&providers);
+        let resolved = context.resolve_all("test_base", &templates);
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(
+            resolved[0].1.resolved.as_ref().unwrap().args,
+            vec!["push", "main"],
+            "The original provider should be kept after a conflicting registration"
+        );
+        assert_eq!(
+            resolved[1].1.resolved.as_ref().unwrap().args,
+            vec!["MAIN.RS"]
+        );
+    }
+
+    #[test]
+    fn test_provider_is_invoked_once_for_all_occurrences() {
+        let root = VariableName::Custom(Cow::Borrowed("PROJECT_ROOT"));
+        let template = TaskTemplate {
+            label: "build".to_string(),
+            command: format!("{}/build.sh", root.template_value()),
+            args: vec![
+                root.template_value().into(),
+                format!("--out={}/out", root.template_value()).into(),
+                format!("--cache=${{{root}}}/cache").into(),
+            ],
+            env: HashMap::from_iter([
+                ("ROOT".to_string(), root.template_value().into()),
+                (
+                    "PATH".to_string(),
+                    format!("{}/bin:$PATH", root.template_value()).into(),
+                ),
+            ]),
+            ..TaskTemplate::default()
+        };
+        let provider_calls = Cell::new(0);
+        let mut context =
+            ResolvingContext::new(TaskContext::default()).with_provider(root.clone(), || {
+                provider_calls.set(provider_calls.get() + 1);
+                Some("/project".to_string())
+            });
+
+        let resolved = context
+            .resolve("test_base", &template)
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(resolved.command, "/project/build.sh");
+        assert_eq!(
+            resolved.args,
+            vec!["/project", "--out=/project/out", "--cache=/project/cache"]
+        );
+        assert_eq!(
+            resolved.env.get("ROOT").map(String::as_str),
+            Some("/project")
+        );
+        assert!(resolved.env["PATH"].starts_with("/project/bin:"));
+        assert_eq!(
+            provider_calls.get(),
+            1,
+            "Provider should be called once for all occurrences of its variable"
+        );
+    }
+
+    #[gpui::test]
+    fn test_resolution_deadline(cx: &mut TestAppContext) {
+        let branch = VariableName::Custom(Cow::Borrowed("GIT_BRANCH"));
+        let remote = VariableName::Custom(Cow::Borrowed("GIT_REMOTE"));
+        let user = VariableName::Custom(Cow::Borrowed("USER"));
+        let template = TaskTemplate {
+            label: "push".to_string(),
+            command: "git".to_string(),
+            args: vec![
+                "push".into(),
+                remote.template_value().into(),
+                branch.template_value().into(),
+                format!("--push-option=user={}", user.template_value()).into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let executor = cx.executor();
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+        let release_rx = release_rx.shared();
+        let mut context = ResolvingContext::new(TaskContext::default())
+            .with_background_provider(&executor, branch.clone(), move || {
+                let release_rx = release_rx.clone();
+                async move {
+                    release_rx.await.ok()?;
+                    Some("main".to_string())
+                }
+            })
+            .with_background_provider(&executor, remote.clone(), || async {
+                Some("origin".to_string())
+            })
+            .with_provider(user.clone(), || Some("me".to_string()));
+
+        let remote_only = TaskTemplate {
+            label: "fetch".to_string(),
+            command: "git".to_string(),
+            args: vec!["fetch".into(), remote.template_value().into()],
+            ..TaskTemplate::default()
+        };
+        executor.set_block_on_ticks(usize::MAX..=usize::MAX);
+        let resolved = context.resolve("test_base", &remote_only).unwrap();
+        assert_eq!(resolved.resolved.unwrap().args, vec!["fetch", "origin"]);
+
+        // Give up on the pending providers at once, instead of waiting for the timers.
+        executor.set_block_on_ticks(0..=0);
+        let deadline = Instant::now() + std::time::Duration::from_millis(200);
+        let (resolved, warnings) = context
+            .resolve_with_deadline("test_base", &template, deadline)
+            .unwrap();
+        assert!(
+            Instant::now() < deadline + std::time::Duration::from_secs(5),
+            "Resolution should not wait for the slow provider past the deadline"
+        );
+        assert_eq!(
+            resolved.resolved.unwrap().args,
+            vec!["push", "origin", "", "--push-option=user=me"]
+        );
+        assert_eq!(
+            warnings,
+            vec![ResolutionWarning::ProviderTimedOut {
+                name: branch.clone()
+            }]
+        );
+
+        release_tx.send(()).unwrap();
+        executor.set_block_on_ticks(usize::MAX..=usize::MAX);
+        let (resolved, warnings) = context
+            .resolve_with_deadline(
+                "test_base",
+                &template,
+                Instant::now() + std::time::Duration::from_secs(5),
+            )
+            .unwrap();
+        assert_eq!(
+            resolved.resolved.unwrap().args,
+            vec!["push", "origin", "main", "--push-option=user=me"],
+            "Late values should be used by the later resolutions"
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_cwd_outside_worktree_warning() {
+        let resolve = |cwd: &str| {
+            let template = TaskTemplate {
+                label: "build".to_string(),
+                command: "make".to_string(),
+                cwd: Some(cwd.to_string()),
+                ..TaskTemplate::default()
+            };
+            let mut context = ResolvingContext::new(TaskContext {
+                cwd: None,
+                task_variables: TaskVariables::from_iter([(
+                    VariableName::WorktreeRoot,
+                    "/home/me/project".to_string(),
+                )]),
+            });
+            let (resolved, warnings) = context
+                .resolve_with_warnings("test_base", &template)
+                .unwrap();
+            assert!(resolved.resolved.is_some());
+            warnings
+        };
+
+        assert!(resolve("$ZED_WORKTREE_ROOT").is_empty());
+        assert!(resolve("$ZED_WORKTREE_ROOT/crates/../docs").is_empty());
+        assert!(resolve("docs/./site").is_empty());
+        assert_eq!(
+            resolve("$ZED_WORKTREE_ROOT/../../other"),
+            vec![ResolutionWarning::CwdOutsideWorktree {
+                cwd: PathBuf::from("/home/me/project/../../other"),
+                worktree_root: PathBuf::from("/home/me/project"),
+            }]
+        );
+        assert_eq!(
+            resolve("/home/me/project-other"),
+            vec![ResolutionWarning::CwdOutsideWorktree {
+                cwd: PathBuf::from("/home/me/project-other"),
+                worktree_root: PathBuf::from("/home/me/project"),
+            }],
+            "Sibling directories with the same prefix should be outside"
+        );
+    }
+
+    #[test]
+    fn test_context_provider() {
+        let relative_file = VariableName::Custom(Cow::Borrowed("RELATIVE_TO_ROOT"));
+        let template = TaskTemplate {
+            label: "lint".to_string(),
+            command: "lint".to_string(),
+            args: vec![relative_file.template_value().into()],
+            ..TaskTemplate::default()
+        };
+        let requested = Cell::new(None);
+        let mut context = ResolvingContext::new(TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::WorktreeRoot, "/project".to_string()),
+                (VariableName::File, "/project/src/main.rs".to_string()),
+            ]),
+        })
+        .with_context_provider(relative_file.clone(), |variable, context| {
+            requested.set(Some(variable.clone()));
+            let root = context.task_variables.get(&VariableName::WorktreeRoot)?;
+            let file = context.task_variables.get(&VariableName::File)?;
+            Some(file.strip_prefix(root)?.trim_start_matches('/').to_string())
+        });
+
+        let resolved = context.resolve("test_base", &template).unwrap();
+        assert_eq!(resolved.resolved.unwrap().args, vec!["src/main.rs"]);
+        assert_eq!(requested.take(), Some(relative_file));
+    }
+}
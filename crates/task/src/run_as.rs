@@ -0,0 +1,263 @@
+//! Running the task processes as a different user or group, see [`crate::TaskTemplate::run_as_user`].
+
+use std::process::Command;
+
+use crate::SpawnInTerminal;
+
+/// A warning about the [`SpawnInTerminal::run_as_user`] and [`SpawnInTerminal::run_as_group`] being ignored on platforms not supporting them.
+pub(crate) fn run_as_warning(spawn_in_terminal: &SpawnInTerminal, is_unix: bool) -> Option<String> {
+    if is_unix
+        || (spawn_in_terminal.run_as_user.is_none() && spawn_in_terminal.run_as_group.is_none())
+    {
+        return None;
+    }
+    Some(format!(
+        "Task `{}` is configured to run as a different user or group, which is only supported on Unix, running as the current user",
+        spawn_in_terminal.full_label
+    ))
+}
+
+/// Makes the command switch to the user and group given, both accepted as names or numeric ids.
+/// For users given by name, their primary group is used, if no group is given.
+/// When started by root, the process also drops root's supplementary groups, getting the ones of the user given by name instead,
+/// or only the group switched to otherwise.
+#[cfg(unix)]
+pub(crate) fn apply_run_as(
+    command: &mut Command,
+    user: Option<&str>,
+    group: Option<&str>,
+) -> anyhow::Result<()> {
+    use std::{io, os::unix::process::CommandExt};
+
+    if user.is_none() && group.is_none() {
+        return Ok(());
+    }
+    let mut uid = None;
+    let mut gid = None;
+    let mut user_name = None;
+    if let Some(user) = user {
+        match user.parse::<u32>() {
+            Ok(numeric_uid) => uid = Some(numeric_uid),
+            Err(_) => {
+                let (named_uid, primary_gid) = unix::lookup_user(user)?;
+                uid = Some(named_uid);
+                gid = Some(primary_gid);
+                user_name = Some(user);
+            }
+        }
+    }
+    if let Some(group) = group {
+        gid = Some(match group.parse::<u32>() {
+            Ok(gid) => gid,
+            Err(_) => unix::lookup_group(group)?,
+        });
+    }
+    // Looking the groups up is not async-signal-safe, so it happens before the fork.
+    let groups = match (user_name, gid) {
+        (Some(user_name), Some(gid)) => unix::supplementary_groups(user_name, gid)?,
+        (_, gid) => gid.into_iter().collect(),
+    };
+    // `Command::uid` switches the user before any `pre_exec` closure runs, after which the groups can no longer be changed,
+    // so the whole switch happens here instead, in the order that keeps the privileges needed for every step.
+    let switch = move || {
+        let is_root = unsafe { libc::geteuid() } == 0;
+        if is_root && unsafe { libc::setgroups(groups.len() as _, groups.as_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if let Some(gid) = gid {
+            if unsafe { libc::setgid(gid) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if let Some(uid) = uid {
+            if unsafe { libc::setuid(uid) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    };
+    unsafe { command.pre_exec(switch) };
+    Ok(())
+}
+
+/// Ignores the user and group on the platforms not supporting them, see [`run_as_warning`].
+#[cfg(not(unix))]
+pub(crate) fn apply_run_as(
+    _command: &mut Command,
+    _user: Option<&str>,
+    _group: Option<&str>,
+) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{ffi::CString, io, mem, ptr};
+
+    use anyhow::{bail, Context};
+
+    const LOOKUP_BUFFER_SIZE: usize = 16 * 1024;
+
+    pub(super) fn lookup_user(name: &str) -> anyhow::Result<(u32, u32)> {
+        let c_name = CString::new(name).context("invalid user name")?;
+        let mut passwd: libc::passwd = unsafe { mem::zeroed() };
+        let mut buffer = vec![0 as libc::c_char; LOOKUP_BUFFER_SIZE];
+        let mut result = ptr::null_mut();
+        let code = unsafe {
+            libc::getpwnam_r(
+                c_name.as_ptr(),
+                &mut passwd,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            )
+        };
+        if code != 0 {
+            return Err(io::Error::from_raw_os_error(code))
+                .with_context(|| format!("looking up user {name:?}"));
+        }
+        if result.is_null() {
+            bail!("Unknown user {name:?}");
+        }
+        Ok((passwd.pw_uid, passwd.pw_gid))
+    }
+
+    pub(super) fn lookup_group(name: &str) -> anyhow::Result<u32> {
+        let c_name = CString::new(name).context("invalid group name")?;
+        let mut group: libc::group = unsafe { mem::zeroed() };
+        let mut buffer = vec![0 as libc::c_char; LOOKUP_BUFFER_SIZE];
+        let mut result = ptr::null_mut();
+        let code = unsafe {
+            libc::getgrnam_r(
+                c_name.as_ptr(),
+                &mut group,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            )
+        };
+        if code != 0 {
+            return Err(io::Error::from_raw_os_error(code))
+                .with_context(|| format!("looking up group {name:?}"));
+        }
+        if result.is_null() {
+            bail!("Unknown group {name:?}");
+        }
+        Ok(group.gr_gid)
+    }
+
+    /// All groups of the user, including the `gid` given.
+    pub(super) fn supplementary_groups(name: &str, gid: u32) -> anyhow::Result<Vec<libc::gid_t>> {
+        let c_name = CString::new(name).context("invalid user name")?;
+        let mut groups = vec![0 as libc::gid_t; 64];
+        loop {
+            let mut count = groups.len() as libc::c_int;
+            let code = unsafe {
+                libc::getgrouplist(
+                    c_name.as_ptr(),
+                    gid as _,
+                    groups.as_mut_ptr() as *mut _,
+                    &mut count,
+                )
+            };
+            if code >= 0 {
+                groups.truncate(count as usize);
+                return Ok(groups);
+            }
+            if groups.len() >= LOOKUP_BUFFER_SIZE {
+                bail!("Too many groups of user {name:?}");
+            }
+            groups.resize((count as usize).max(groups.len() * 2), 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaskContext, TaskTemplate};
+
+    use super::*;
+
+    fn resolve_spawn(template: &TaskTemplate) -> SpawnInTerminal {
+        template
+            .resolve_task("test_base", TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap()
+    }
+
+    #[test]
+    fn test_run_as_fields_serde() {
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "serve", "command": "serve", "run_as_user": "www-data", "run_as_group": "1000"}"#,
+        )
+        .unwrap();
+        assert_eq!(template.run_as_user.as_deref(), Some("www-data"));
+        assert_eq!(template.run_as_group.as_deref(), Some("1000"));
+        let spawn_in_terminal = resolve_spawn(&template);
+        assert_eq!(spawn_in_terminal.run_as_user.as_deref(), Some("www-data"));
+        assert_eq!(spawn_in_terminal.run_as_group.as_deref(), Some("1000"));
+
+        let default_template: TaskTemplate =
+            serde_json_lenient::from_str(r#"{"label": "serve", "command": "serve"}"#).unwrap();
+        assert_eq!(default_template.run_as_user, None);
+        assert_eq!(default_template.run_as_group, None);
+    }
+
+    #[test]
+    fn test_run_as_ignored_with_warning_on_non_unix() {
+        let template = TaskTemplate {
+            label: "serve".to_string(),
+            command: "serve".to_string(),
+            run_as_user: Some("1000".to_string()),
+            ..TaskTemplate::default()
+        };
+        let spawn_in_terminal = resolve_spawn(&template);
+        let warning = run_as_warning(&spawn_in_terminal, false).unwrap();
+        assert!(warning.contains("only supported on Unix"), "{warning}");
+        assert_eq!(run_as_warning(&spawn_in_terminal, true), None);
+        let regular_task = resolve_spawn(&TaskTemplate {
+            run_as_user: None,
+            ..template
+        });
+        assert_eq!(run_as_warning(&regular_task, false), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_as_users_and_groups_lookup() {
+        apply_run_as(&mut Command::new("true"), Some("0"), Some("0")).unwrap();
+        apply_run_as(&mut Command::new("true"), Some("root"), None).unwrap();
+        assert!(apply_run_as(
+            &mut Command::new("true"),
+            Some("no-such-user-for-zed-tasks"),
+            None
+        )
+        .is_err());
+        assert!(apply_run_as(
+            &mut Command::new("true"),
+            None,
+            Some("no-such-group-for-zed-tasks")
+        )
+        .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_as_drops_root_groups() {
+        // Switching users needs root privileges.
+        if unsafe { libc::geteuid() } != 0 {
+            return;
+        }
+        let mut command = Command::new("id");
+        command.arg("-G");
+        apply_run_as(&mut command, Some("nobody"), None).unwrap();
+        let output = command.output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+        let groups = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            !groups.split_whitespace().any(|group| group == "0"),
+            "Root's groups should be dropped, got {groups:?}"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Undo of the ephemeral task edits, e.g. "run with overrides" followed by "rerun original", see [`TaskSession`].
+
+use crate::{ResolvedTask, TaskContext, TaskTemplate};
+
+/// A template and a context to resolve it in, with the previous pairs kept on a stack to restore them later.
+///
+/// Push the edited template or context with [`TaskSession::push_override`] before running it,
+/// and [`TaskSession::restore`] the previous pair to get back to the original one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskSession {
+    current: (TaskTemplate, TaskContext),
+    snapshots: Vec<(TaskTemplate, TaskContext)>,
+}
+
+impl TaskSession {
+    /// Starts a session with the original template and context, and no snapshots.
+    pub fn new(template: TaskTemplate, context: TaskContext) -> Self {
+        Self {
+            current: (template, context),
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// The current template.
+    pub fn template(&self) -> &TaskTemplate {
+        &self.current.0
+    }
+
+    /// The current context.
+    pub fn context(&self) -> &TaskContext {
+        &self.current.1
+    }
+
+    /// Makes the pair given current, keeping a snapshot of the previous one to [`TaskSession::restore`].
+    pub fn push_override(&mut self, template: TaskTemplate, context: TaskContext) {
+        let previous = std::mem::replace(&mut self.current, (template, context));
+        self.snapshots.push(previous);
+    }
+
+    /// Makes the last snapshot current again, returning the pair it replaced, or `None` if there are no snapshots left.
+    pub fn restore(&mut self) -> Option<(TaskTemplate, TaskContext)> {
+        let snapshot = self.snapshots.pop()?;
+        Some(std::mem::replace(&mut self.current, snapshot))
+    }
+
+    /// Drops all snapshots, making the original pair current again.
+    pub fn restore_original(&mut self) {
+        if !self.snapshots.is_empty() {
+            self.current = self.snapshots.swap_remove(0);
+            self.snapshots.clear();
+        }
+    }
+
+    /// The number of snapshots to restore.
+    pub fn depth(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Resolves the current template in the current context, see [`TaskTemplate::resolve_task`].
+    pub fn resolve(&self, id_base: &str) -> Option<ResolvedTask> {
+        let (template, context) = &self.current;
+        template.resolve_task(id_base, context.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaskVariables, VariableName};
+
+    use super::*;
+
+    #[test]
+    fn test_override_and_restore() {
+        let template = TaskTemplate {
+            label: "test $ZED_SYMBOL".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["test".into(), "$ZED_SYMBOL".into()],
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(
+                VariableName::Symbol,
+                "test_parsing".to_string(),
+            )]),
+        };
+        let mut session = TaskSession::new(template.clone(), context.clone());
+        let original = session.resolve("test").unwrap();
+        assert_eq!(session.restore(), None);
+
+        session.push_override(
+            TaskTemplate {
+                args: vec!["test".into(), "$ZED_SYMBOL".into(), "--release".into()],
+                ..template.clone()
+            },
+            context.clone(),
+        );
+        let mut overridden_context = context.clone();
+        overridden_context
+            .task_variables
+            .insert(VariableName::Symbol, "test_empty".to_string());
+        session.push_override(session.template().clone(), overridden_context);
+        assert_eq!(session.depth(), 2);
+        let overridden = session.resolve("test").unwrap();
+        assert_eq!(
+            overridden.resolved.unwrap().args,
+            vec!["test", "test_empty", "--release"]
+        );
+
+        session.restore().unwrap();
+        assert_eq!(
+            session.resolve("test").unwrap().resolved.unwrap().args,
+            vec!["test", "test_parsing", "--release"]
+        );
+        session.restore().unwrap();
+        assert_eq!(session.resolve("test"), Some(original.clone()));
+        assert_eq!(session.template(), &template);
+        assert_eq!(session.context(), &context);
+
+        session.push_override(TaskTemplate::default(), TaskContext::default());
+        session.push_override(TaskTemplate::default(), TaskContext::default());
+        session.restore_original();
+        assert_eq!(session.depth(), 0);
+        assert_eq!(session.resolve("test"), Some(original));
+    }
+}
@@ -1,16 +1,25 @@
 //! A source of tasks, based on a static configuration, deserialized from the tasks config file, and related infrastructure for tracking changes to the file.
 
+use std::{borrow::Cow, time::SystemTime};
+
+use anyhow::{bail, Context as _};
 use futures::StreamExt;
 use gpui::{AppContext, Context, Model, ModelContext, Subscription};
 use serde::Deserialize;
+use serde_json_lenient::Value;
 use util::ResultExt;
 
-use crate::{TaskSource, TaskTemplates};
+use crate::{validation::deserialize_templates, Platform, TaskArg, TaskSource, TaskTemplates};
 use futures::channel::mpsc::UnboundedReceiver;
 
+/// The name of the file next to a tasks file, with the machine-specific overrides of its tasks, e.g. absolute paths or local ports.
+/// Meant to be git-ignored, see [`TrackedFile::new_tasks_file_with_overlay`].
+pub const TASKS_OVERLAY_FILE_NAME: &str = "tasks.local.json";
+
 /// The source of tasks defined in a tasks config file.
 pub struct StaticSource {
     tasks: TaskTemplates,
+    last_load: LoadStatus,
     _templates: Model<TrackedFile<TaskTemplates>>,
     _subscription: Subscription,
 }
@@ -20,6 +29,35 @@ pub struct StaticSource {
 /// notified.
 pub struct TrackedFile<T> {
     parsed_contents: T,
+    last_load: Option<LoadAttempt>,
+}
+
+/// An outcome of the latest attempt to load the [`TrackedFile`] contents.
+struct LoadAttempt {
+    at: SystemTime,
+    error: Option<String>,
+}
+
+/// A status of the latest (re)load of the tasks file, see [`StaticSource::last_load_result`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum LoadStatus {
+    /// The file was not loaded yet.
+    #[default]
+    NotLoaded,
+    /// The file got loaded successfully.
+    Ok {
+        /// When the file was loaded.
+        at: SystemTime,
+        /// Number of the tasks loaded.
+        count: usize,
+    },
+    /// The file failed to load, the tasks from the last successful load are still used.
+    Failed {
+        /// When the load was attempted.
+        at: SystemTime,
+        /// A human readable reason of the failure.
+        error: String,
+    },
 }
 
 impl<T: PartialEq + 'static> TrackedFile<T> {
@@ -34,16 +72,12 @@ impl<T: PartialEq + 'static> TrackedFile<T> {
                     if !new_contents.trim().is_empty() {
                         // String -> T (ZedTaskFormat)
                         // String -> U (VsCodeFormat) -> Into::into T
-                        let Some(new_contents) =
-                            serde_json_lenient::from_str(&new_contents).log_err()
-                        else {
-                            continue;
-                        };
+                        let new_contents = normalize_line_endings(&new_contents);
+                        let new_contents = serde_json_lenient::from_str(&new_contents)
+                            .context("parsing the file contents");
                         tracked_file.update(&mut cx, |tracked_file: &mut TrackedFile<T>, cx| {
-                            if tracked_file.parsed_contents != new_contents {
-                                tracked_file.parsed_contents = new_contents;
-                                cx.notify();
-                            };
+                            tracked_file.record_load(new_contents);
+                            cx.notify();
                         })?;
                     }
                 }
@@ -52,6 +86,7 @@ impl<T: PartialEq + 'static> TrackedFile<T> {
             .detach_and_log_err(cx);
             Self {
                 parsed_contents: Default::default(),
+                last_load: None,
             }
         })
     }
@@ -68,19 +103,13 @@ impl<T: PartialEq + 'static> TrackedFile<T> {
             cx.spawn(|tracked_file, mut cx| async move {
                 while let Some(new_contents) = tracker.next().await {
                     if !new_contents.trim().is_empty() {
-                        let Some(new_contents) =
-                            serde_json_lenient::from_str::<U>(&new_contents).log_err()
-                        else {
-                            continue;
-                        };
-                        let Some(new_contents) = new_contents.try_into().log_err() else {
-                            continue;
-                        };
+                        let new_contents = normalize_line_endings(&new_contents);
+                        let new_contents = serde_json_lenient::from_str::<U>(&new_contents)
+                            .context("parsing the file contents")
+                            .and_then(|new_contents| new_contents.try_into());
                         tracked_file.update(&mut cx, |tracked_file: &mut TrackedFile<T>, cx| {
-                            if tracked_file.parsed_contents != new_contents {
-                                tracked_file.parsed_contents = new_contents;
-                                cx.notify();
-                            };
+                            tracked_file.record_load(new_contents);
+                            cx.notify();
                         })?;
                     }
                 }
@@ -89,6 +118,7 @@ impl<T: PartialEq + 'static> TrackedFile<T> {
             .detach_and_log_err(cx);
             Self {
                 parsed_contents: Default::default(),
+                last_load: None,
             }
         })
     }
@@ -96,6 +126,176 @@ impl<T: PartialEq + 'static> TrackedFile<T> {
     fn get(&self) -> &T {
         &self.parsed_contents
     }
+
+    /// Stores the outcome of a load attempt, keeping the last successfully loaded contents on failure.
+    fn record_load(&mut self, new_contents: anyhow::Result<T>) {
+        let at = SystemTime::now();
+        match new_contents {
+            Ok(new_contents) => {
+                self.parsed_contents = new_contents;
+                self.last_load = Some(LoadAttempt { at, error: None });
+            }
+            Err(e) => {
+                self.last_load = Some(LoadAttempt {
+                    at,
+                    error: Some(format!("{e:#}")),
+                });
+                Err::<(), _>(e).log_err();
+            }
+        }
+    }
+}
+
+impl TrackedFile<TaskTemplates> {
+    /// Initializes new [`TrackedFile`] with the tasks file contents,
+    /// migrated to the current format version before the deserialization, see [`TaskTemplates::migrate`].
+    pub fn new_tasks_file(tracker: UnboundedReceiver<String>, cx: &mut AppContext) -> Model<Self> {
+        Self::new_convertible::<serde_json_lenient::Value>(tracker, cx)
+    }
+
+    /// Initializes new [`TrackedFile`] with the tasks file contents, same as [`TrackedFile::new_tasks_file`] does,
+    /// with the overrides from the overlay file (see [`TASKS_OVERLAY_FILE_NAME`]) applied, see [`TaskTemplates::merge_overlay`].
+    /// Changes of either file refresh the merged tasks; a missing or empty overlay file leaves the tasks file as is.
+    pub fn new_tasks_file_with_overlay(
+        tracker: UnboundedReceiver<String>,
+        overlay_tracker: UnboundedReceiver<String>,
+        cx: &mut AppContext,
+    ) -> Model<Self> {
+        enum Change {
+            Tasks(String),
+            Overlay(String),
+        }
+
+        cx.new_model(move |cx| {
+            cx.spawn(|tracked_file, mut cx| async move {
+                let mut changes = futures::stream::select(
+                    tracker.map(Change::Tasks),
+                    overlay_tracker.map(Change::Overlay),
+                );
+                let mut tasks_contents = None;
+                let mut overlay_contents = None;
+                while let Some(change) = changes.next().await {
+                    match change {
+                        Change::Tasks(contents) => {
+                            if contents.trim().is_empty() {
+                                continue;
+                            }
+                            tasks_contents = Some(contents);
+                        }
+                        Change::Overlay(contents) => {
+                            overlay_contents = Some(contents).filter(|c| !c.trim().is_empty());
+                        }
+                    }
+                    let Some(tasks_contents) = tasks_contents.as_deref() else {
+                        continue;
+                    };
+                    let new_contents = parse_document(tasks_contents)
+                        .context("parsing the file contents")
+                        .and_then(|tasks| {
+                            let overlay = overlay_contents
+                                .as_deref()
+                                .map(parse_document)
+                                .transpose()
+                                .context("parsing the overlay file contents")?;
+                            TaskTemplates::merge_overlay(tasks, overlay)
+                        });
+                    tracked_file.update(&mut cx, |tracked_file: &mut TrackedFile<_>, cx| {
+                        tracked_file.record_load(new_contents);
+                        cx.notify();
+                    })?;
+                }
+                anyhow::Ok(())
+            })
+            .detach_and_log_err(cx);
+            Self {
+                parsed_contents: Default::default(),
+                last_load: None,
+            }
+        })
+    }
+
+    fn load_status(&self) -> LoadStatus {
+        match &self.last_load {
+            None => LoadStatus::NotLoaded,
+            Some(LoadAttempt { at, error: None }) => LoadStatus::Ok {
+                at: *at,
+                count: self.parsed_contents.0.len(),
+            },
+            Some(LoadAttempt {
+                at,
+                error: Some(error),
+            }) => LoadStatus::Failed {
+                at: *at,
+                error: error.clone(),
+            },
+        }
+    }
+}
+
+/// Converts Windows line endings into Unix ones, so that multiline string values
+/// in files authored on Windows do not end up with stray `\r` characters.
+/// Escaped `\r` sequences in JSON strings are not affected.
+pub(crate) fn normalize_line_endings(contents: &str) -> Cow<'_, str> {
+    if contents.contains('\r') {
+        Cow::Owned(contents.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(contents)
+    }
+}
+
+/// Strips the `\r` characters trailing the commands and the arguments, e.g. written as an escaped `\r`
+/// by the tools converting the files authored on Windows, that would end up in the spawned command line otherwise.
+pub(crate) fn trim_trailing_carriage_returns(templates: &mut TaskTemplates) {
+    for template in &mut templates.0 {
+        let fields = std::iter::once(&mut template.command)
+            .chain(template.args.iter_mut().map(TaskArg::value_mut));
+        for field in fields {
+            let trimmed_len = field.trim_end_matches('\r').len();
+            field.truncate(trimmed_len);
+        }
+    }
+}
+
+fn parse_document(contents: &str) -> anyhow::Result<Value> {
+    Ok(serde_json_lenient::from_str(&normalize_line_endings(
+        contents,
+    ))?)
+}
+
+impl TaskTemplates {
+    /// Merges the tasks file `document` with the overlay one, both migrated to the current format version first, see [`TaskTemplates::migrate`].
+    ///
+    /// Overlay tasks override the tasks file ones with the same label field by field: the fields present in the overlay task replace the tasks file ones as a whole,
+    /// the other fields are kept from the tasks file. Overlay tasks with the labels not in the tasks file are added after its tasks.
+    pub fn merge_overlay(mut document: Value, overlay: Option<Value>) -> anyhow::Result<Self> {
+        Self::validate_against_schema_version(&document)?;
+        Self::migrate(&mut document);
+        if let Some(mut overlay) = overlay {
+            Self::validate_against_schema_version(&overlay).context("checking the overlay")?;
+            Self::migrate(&mut overlay);
+            let (Value::Array(tasks), Value::Array(overlay_tasks)) = (&mut document, overlay)
+            else {
+                bail!("Both the tasks file and its overlay should contain lists of tasks");
+            };
+            for overlay_task in overlay_tasks {
+                let Value::Object(overlay_task) = overlay_task else {
+                    bail!("Overlay tasks should be objects, got {overlay_task}");
+                };
+                let Some(label) = overlay_task.get("label").and_then(Value::as_str) else {
+                    bail!("Overlay tasks should have labels to match the tasks with");
+                };
+                let task = tasks.iter_mut().find_map(|task| {
+                    task.as_object_mut()
+                        .filter(|task| task.get("label").and_then(Value::as_str) == Some(label))
+                });
+                match task {
+                    Some(task) => task.extend(overlay_task),
+                    None => tasks.push(Value::Object(overlay_task)),
+                }
+            }
+        }
+        Ok(deserialize_templates(document)?)
+    }
 }
 
 impl StaticSource {
@@ -109,18 +309,26 @@ impl StaticSource {
                 &templates,
                 move |source: &mut Box<(dyn TaskSource + 'static)>, new_templates, cx| {
                     if let Some(static_source) = source.as_any().downcast_mut::<Self>() {
-                        static_source.tasks = new_templates.read(cx).get().clone();
+                        let new_templates = new_templates.read(cx);
+                        static_source.tasks = new_templates.get().for_platform(Platform::current());
+                        static_source.last_load = new_templates.load_status();
                         cx.notify();
                     }
                 },
             );
             Box::new(Self {
                 tasks: TaskTemplates::default(),
+                last_load: LoadStatus::default(),
                 _templates: templates,
                 _subscription,
             })
         })
     }
+
+    /// Whether the latest (re)load of the tasks file succeeded, and when it happened.
+    pub fn last_load_result(&self) -> LoadStatus {
+        self.last_load.clone()
+    }
 }
 
 impl TaskSource for StaticSource {
@@ -132,3 +340,128 @@ impl TaskSource for StaticSource {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use collections::HashMap;
+
+    use crate::TaskTemplate;
+
+    use super::*;
+
+    #[test]
+    fn test_crlf_files_parse_as_lf_ones() {
+        let parse =
+            |contents: &str| TaskTemplates::try_from(parse_document(contents).unwrap()).unwrap();
+        let lf_templates = parse(
+            r#"[
+                {
+                    "label": "crlf task",
+                    "command": "echo",
+                    "args": ["a\r\nb", "c"],
+                },
+            ]"#,
+        );
+        let crlf_templates = parse(
+            &r#"[
+                {
+                    "label": "crlf task",
+                    "command": "echo\r",
+                    "args": ["a\r\nb\r", "c\r"],
+                },
+            ]"#
+            .replace('\n', "\r\n"),
+        );
+        assert_eq!(
+            lf_templates, crlf_templates,
+            "Trailing carriage returns should be stripped from the commands and the arguments"
+        );
+        assert_eq!(crlf_templates.0[0].command, "echo");
+        assert_eq!(
+            crlf_templates.0[0].args,
+            ["a\r\nb", "c"].map(TaskArg::from),
+            "Escaped line endings inside the values should be preserved"
+        );
+    }
+
+    #[test]
+    fn test_load_status_keeps_last_known_good_templates() {
+        let mut tracked_file = TrackedFile::<TaskTemplates> {
+            parsed_contents: TaskTemplates::default(),
+            last_load: None,
+        };
+        assert_eq!(tracked_file.load_status(), LoadStatus::NotLoaded);
+
+        let before_load = SystemTime::now();
+        tracked_file.record_load(
+            serde_json_lenient::from_str(r#"[{"label": "test", "command": "cargo"}]"#)
+                .context("parsing"),
+        );
+        let LoadStatus::Ok { at, count } = tracked_file.load_status() else {
+            panic!(
+                "Expected a successful load, got {:?}",
+                tracked_file.load_status()
+            );
+        };
+        assert!(at >= before_load);
+        assert_eq!(count, 1);
+        let loaded_templates = tracked_file.get().clone();
+
+        tracked_file.record_load(serde_json_lenient::from_str("[{").context("parsing"));
+        let LoadStatus::Failed {
+            at: failed_at,
+            error,
+        } = tracked_file.load_status()
+        else {
+            panic!(
+                "Expected a failed load, got {:?}",
+                tracked_file.load_status()
+            );
+        };
+        assert!(failed_at >= at);
+        assert!(error.starts_with("parsing"), "Unexpected error: {error}");
+        assert_eq!(
+            tracked_file.get(),
+            &loaded_templates,
+            "Failed reloads should keep the last successfully loaded templates"
+        );
+    }
+
+    #[test]
+    fn test_overlay_overrides_matching_fields() {
+        let tasks = serde_json_lenient::json!({
+            "version": 1,
+            "tasks": [
+                {"label": "serve", "command": "npm", "args": ["start"], "env": {"PORT": "3000"}},
+                {"label": "test", "command": "npm", "args": ["test"]},
+            ],
+        });
+        let overlay = parse_document(
+            r#"[
+                // Local port, not to be committed.
+                {"label": "serve", "env": {"PORT": "8080"}},
+                {"name": "debug", "command": "node", "args": ["--inspect"]},
+            ]"#,
+        )
+        .unwrap();
+        let merged = TaskTemplates::merge_overlay(tasks.clone(), Some(overlay)).unwrap();
+        let plain = TaskTemplates::merge_overlay(tasks, None).unwrap();
+        assert_eq!(
+            merged.0[0],
+            TaskTemplate {
+                env: HashMap::from_iter([("PORT".to_string(), "8080".into())]),
+                ..plain.0[0].clone()
+            },
+            "Only the field set in the overlay should change"
+        );
+        assert_eq!(merged.0[1], plain.0[1]);
+        assert_eq!(merged.0[2].label, "debug");
+        assert_eq!(merged.0.len(), 3);
+
+        assert!(TaskTemplates::merge_overlay(
+            serde_json_lenient::json!([{"label": "test", "command": "npm"}]),
+            Some(serde_json_lenient::json!([{"command": "yarn"}])),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! A compact form of the [`ResolvedTask`] to persist, e.g. in the task history, see [`ResolvedTaskSummary`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ResolvedTask, SpawnInTerminal, TaskArg, TaskId, TaskKind, TaskTemplate};
+
+/// A [`ResolvedTask`] without its [`ResolvedTask::original_task`], that takes most of the space when serialized,
+/// and without the [`ResolvedTask::last_run`], that task history stores on its own.
+/// Converts back into a [`ResolvedTask`] with a minimal template, describing the resolved task, see [`ResolvedTaskSummary::into_resolved_task`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedTaskSummary {
+    /// The [`ResolvedTask::id`].
+    pub id: TaskId,
+    /// The [`ResolvedTask::resolved_label`].
+    pub resolved_label: String,
+    /// The [`ResolvedTask::resolved`].
+    #[serde(default)]
+    pub resolved: Option<SpawnInTerminal>,
+    /// The [`ResolvedTask::kind`].
+    #[serde(default)]
+    pub kind: TaskKind,
+}
+
+impl From<&ResolvedTask> for ResolvedTaskSummary {
+    fn from(task: &ResolvedTask) -> Self {
+        Self {
+            id: task.id.clone(),
+            resolved_label: task.resolved_label.clone(),
+            resolved: task.resolved.clone(),
+            kind: task.kind.clone(),
+        }
+    }
+}
+
+impl ResolvedTaskSummary {
+    /// Restores the [`ResolvedTask`], with a template made of the resolved fields as its `original_task`:
+    /// the resolved label, command, args and cwd, or the action, with no variables left to substitute.
+    /// Everything else of the template, e.g. its `extends` or `tags`, is lost.
+    pub fn into_resolved_task(self) -> ResolvedTask {
+        let original_task = match (&self.resolved, &self.kind) {
+            (_, TaskKind::Action(action)) => TaskTemplate {
+                label: self.resolved_label.clone(),
+                action: Some(action.clone()),
+                ..TaskTemplate::default()
+            },
+            (Some(spawn), TaskKind::Spawn) => TaskTemplate {
+                label: self.resolved_label.clone(),
+                command: spawn.command.clone(),
+                args: spawn.args.iter().cloned().map(TaskArg::from).collect(),
+                cwd: spawn
+                    .cwd
+                    .as_ref()
+                    .map(|cwd| cwd.to_string_lossy().into_owned()),
+                ..TaskTemplate::default()
+            },
+            (None, TaskKind::Spawn) => TaskTemplate {
+                label: self.resolved_label.clone(),
+                ..TaskTemplate::default()
+            },
+        };
+        ResolvedTask {
+            id: self.id,
+            original_task,
+            resolved_label: self.resolved_label,
+            resolved: self.resolved,
+            last_run: None,
+            kind: self.kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use collections::HashMap;
+
+    use crate::{TaskContext, TaskVariables, VariableName};
+
+    use super::*;
+
+    fn resolved_task() -> ResolvedTask {
+        let mut task = TaskTemplate {
+            label: "test ${ZED_SYMBOL}".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["test".into(), "$ZED_SYMBOL".into()],
+            cwd: Some("/project".to_string()),
+            env: HashMap::from_iter([("RUST_LOG".to_string(), "debug".into())]),
+            tags: vec!["rust".to_string()],
+            ..TaskTemplate::default()
+        }
+        .resolve_task(
+            "test",
+            TaskContext {
+                cwd: None,
+                task_variables: TaskVariables::from_iter([(
+                    VariableName::Symbol,
+                    "my_test".to_string(),
+                )]),
+            },
+        )
+        .unwrap();
+        task.last_run = Some(SystemTime::UNIX_EPOCH);
+        task
+    }
+
+    #[test]
+    fn test_summary_round_trip() {
+        let task = resolved_task();
+        let summary = ResolvedTaskSummary::from(&task);
+        let json = serde_json_lenient::to_string(&summary).unwrap();
+        assert!(!json.contains("original_task"));
+        let restored = serde_json_lenient::from_str::<ResolvedTaskSummary>(&json)
+            .unwrap()
+            .into_resolved_task();
+        assert_eq!(restored.id, task.id);
+        assert_eq!(restored.resolved_label, "test my_test");
+        assert_eq!(restored.resolved, task.resolved);
+        assert_eq!(restored.kind, TaskKind::Spawn);
+        assert_eq!(restored.last_run, None);
+        let spawn = restored.resolved.as_ref().unwrap();
+        assert_eq!(spawn.env["RUST_LOG"], "debug");
+        assert_eq!(spawn.cwd, Some(PathBuf::from("/project")));
+        assert_eq!(restored.original_task.label, "test my_test");
+        assert_eq!(
+            restored.original_task.args,
+            ["test", "my_test"].map(TaskArg::from)
+        );
+        assert!(restored.original_task.tags.is_empty());
+
+        let action = ResolvedTaskSummary {
+            id: TaskId("action".to_string()),
+            resolved_label: "Format".to_string(),
+            resolved: None,
+            kind: TaskKind::Action("editor::Format".to_string()),
+        };
+        let restored = action.into_resolved_task();
+        assert_eq!(
+            restored.original_task.action.as_deref(),
+            Some("editor::Format")
+        );
+        assert!(restored.original_task.is_runnable());
+    }
+
+    #[test]
+    fn test_full_serialization() {
+        let task = resolved_task();
+        let full = serde_json_lenient::to_string(&task).unwrap();
+        let summary = serde_json_lenient::to_string(&ResolvedTaskSummary::from(&task)).unwrap();
+        assert!(full.contains("original_task"));
+        assert!(summary.len() < full.len());
+        let deserialized = serde_json_lenient::from_str::<ResolvedTask>(&full).unwrap();
+        assert_eq!(deserialized, task);
+    }
+}
@@ -1,4 +1,8 @@
-use std::path::PathBuf;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
 
 use anyhow::{bail, Context};
 use collections::HashMap;
@@ -7,10 +11,18 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use util::{truncate_and_remove_front, ResultExt};
 
-use crate::{ResolvedTask, SpawnInTerminal, TaskContext, TaskId, ZED_VARIABLE_NAME_PREFIX};
+use crate::{
+    condition::evaluate_condition,
+    flatten::{Platform, PlatformOverride},
+    posix_quote, serialize_sorted_map, ResolvedTask, SpawnInTerminal, TaskContext, TaskId,
+    TaskKind, VariableName, NUMERIC_VARIABLES, OPTIONAL_VARIABLES, ZED_VARIABLE_NAME_PREFIX,
+};
 
 /// A template definition of a Zed task to run.
 /// May use the [`VariableName`] to get the corresponding substitutions into its fields.
+/// Variable references may transform the substituted value with a modifier, e.g. `${ZED_SELECTED_TEXT:shellquote}` or `${ZED_FILE:unix}`;
+/// supported modifiers are `base64`, `urlencode`, `shellquote`, `json` and `unix`.
+/// Numeric variables may be referenced with an integer offset, e.g. `${ZED_ROW+1}` or `${ZED_COLUMN-1}`.
 ///
 /// Template itself is not ready to spawn a task, it needs to be resolved with a [`TaskContext`] first, that
 /// contains all relevant Zed state in task variables.
@@ -19,15 +31,21 @@ use crate::{ResolvedTask, SpawnInTerminal, TaskContext, TaskId, ZED_VARIABLE_NAM
 #[serde(rename_all = "snake_case")]
 pub struct TaskTemplate {
     /// Human readable name of the task to display in the UI.
+    /// Deprecated `name` field is accepted as an alias.
+    #[serde(alias = "name")]
     pub label: String,
     /// Executable command to spawn.
+    /// May be omitted in templates that inherit it via `extends`.
+    #[serde(default)]
     pub command: String,
     /// Arguments to the command.
+    /// An argument may be included conditionally, written as `{"value": "--verbose", "when": "verbose"}`, see [`TaskArg::Conditional`].
     #[serde(default)]
-    pub args: Vec<String>,
+    pub args: Vec<TaskArg>,
     /// Env overrides for the command, will be appended to the terminal's environment from the settings.
-    #[serde(default)]
-    pub env: HashMap<String, String>,
+    /// Values may be lists of strings, joined with the OS path list separator, see [`EnvValue`].
+    #[serde(default, serialize_with = "serialize_sorted_map")]
+    pub env: HashMap<String, EnvValue>,
     /// Current working directory to spawn the command into, defaults to current project root.
     #[serde(default)]
     pub cwd: Option<String>,
@@ -40,12 +58,289 @@ pub struct TaskTemplate {
     /// What to do with the terminal pane and tab, after the command was started:
     /// * `always` — always show the terminal pane, add and focus the corresponding task's tab in it (default)
     /// * `never` — avoid changing current terminal pane focus, but still add/reuse the task's tab there
+    /// * `reveal_then_refocus_editor` — show the terminal pane with the task's tab, but keep the focus in the editor
     #[serde(default)]
     pub reveal: RevealStrategy,
+    /// Label of another template in the same file to inherit the fields from.
+    /// Inherited fields are combined with the template's own ones as follows:
+    /// * `env` — merged, parent's entries first, template's own entries override them
+    /// * `command`, `args`, `cwd`, `cwd_candidates`, `inputs` — template's own value is used if set (non-empty), parent's otherwise
+    /// * all other fields — template's own value is used
+    ///
+    /// Use the `${inherit}` marker to position the inherited values precisely instead:
+    /// an `args` element `"${inherit}"` expands into all parent's arguments, e.g. `["${inherit}", "--release"]` appends to them;
+    /// in the `env` values, `${inherit}` expands into the parent's value of the same variable, e.g. `"/opt/bin:${inherit}"`,
+    /// and a list element `"${inherit}"` — into all parent's list elements. The marker expands into nothing if the parent has no such values.
+    ///
+    /// Parents may inherit from other templates too, the whole chain is merged from the topmost parent down.
+    #[serde(default)]
+    pub extends: Option<String>,
+    /// Whether to print the command line before running it.
+    #[serde(default)]
+    pub echo_command: bool,
+    /// Values to ask the user for before running the task, referenced in the other fields as `${input:<id>}`.
+    /// Supplying the values is up to the embedder, references are left as is during the resolution.
+    #[serde(default)]
+    pub inputs: Vec<TaskInput>,
+    /// Position of the template among the templates merged from multiple sources, see [`TaskTemplates::stable_sort`].
+    /// Not a part of the task file format.
+    #[serde(skip)]
+    pub order: Option<TemplateOrder>,
+    /// A message to ask the user to confirm before running the task, e.g. for destructive tasks.
+    /// No confirmation is asked for if not set.
+    #[serde(default)]
+    pub confirm: Option<String>,
+    /// A path to a file with env variables for the command, in dotenv (`KEY=VALUE`) or shell (`export KEY=VALUE`) format.
+    /// Relative paths are resolved against the task's working directory, and fail the resolution of the tasks without one;
+    /// values from `env` override the file's ones. Values are taken from the file literally, without any variable expansion.
+    /// The file is read right before the spawn, see [`SpawnInTerminal::apply_env_file`].
+    #[serde(default)]
+    pub env_file: Option<String>,
+    /// Name of an application action to run instead of spawning a command, e.g. `zed::OpenSettings`.
+    /// Templates with an action need no `command`, and resolve into tasks with nothing to spawn, see [`TaskKind::Action`].
+    #[serde(default)]
+    pub action: Option<String>,
+    /// A signal to send to the task process to stop it when the task is cancelled, see [`StopSignal`].
+    /// If the process is still alive after `stop_timeout_ms`, it gets killed.
+    #[serde(default)]
+    #[schemars(with = "Option<StopSignalRepr>")]
+    pub stop_signal: Option<StopSignal>,
+    /// How long to wait for the task process to exit after sending the `stop_signal`, before killing it.
+    #[serde(default)]
+    pub stop_timeout_ms: Option<u64>,
+    /// A user to run the command as, either by name or by uid, e.g. to drop root privileges in a container.
+    /// Only supported on Unix, ignored with a warning elsewhere.
+    #[serde(default)]
+    pub run_as_user: Option<String>,
+    /// A group to run the command as, either by name or by gid.
+    /// Defaults to the primary group of `run_as_user`, if that is set by name. Only supported on Unix, ignored with a warning elsewhere.
+    #[serde(default)]
+    pub run_as_group: Option<String>,
+    /// Where to show the output of the command:
+    /// * `terminal` — in a terminal tab, as configured by `reveal` and `use_new_terminal` (default)
+    /// * `open_buffer` — in a read-only buffer with the captured output; terminal-only settings do not apply then
+    #[serde(default)]
+    pub output_mode: OutputMode,
+    /// Name of a group of tasks that should never run simultaneously, e.g. ones writing into the same output directory.
+    /// A task of the group waits for the running ones of the same group to finish, even if those are different tasks.
+    #[serde(default)]
+    pub mutex_group: Option<String>,
+    /// A command to run in the same cwd and env after the task command exits successfully, e.g. a notification.
+    #[serde(default)]
+    pub on_success: Option<String>,
+    /// A command to run in the same cwd and env after the task command exits with a failure.
+    #[serde(default)]
+    pub on_failure: Option<String>,
+    /// Free-form tags to categorize the task with, e.g. `build` or `test`; compared case-insensitively.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Whether the task process should keep running after its terminal tab or the workspace is closed, e.g. for local servers.
+    #[serde(default)]
+    pub detached: bool,
+    /// Whether to create the working directory (with all its parents) on spawn, if it does not exist, e.g. for tasks writing into an output directory.
+    /// The resolution itself never creates it, see [`SpawnInTerminal::ensure_cwd`].
+    #[serde(default)]
+    pub create_cwd: bool,
+    /// Whether to always pass the arguments to the command in a response file, as a single `@path` argument,
+    /// e.g. for toolchains choking on long argument lists. The arguments over a length threshold get moved regardless,
+    /// see [`SpawnInTerminal::use_args_file`].
+    #[serde(default)]
+    pub args_file: bool,
+    /// The primary category of the task, e.g. `Build`, `Test`, `Run` or `Debug`, used for the section headers in the task pickers.
+    /// Unlike `tags`, a task has at most one category.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Exit codes of the command that mean success, `[0]` if empty.
+    #[serde(default)]
+    pub success_exit_codes: Vec<i32>,
+    /// A message to notify with when the task succeeds, instead of the default one, see [`crate::ResolvedTask::completion_message`].
+    /// May contain `{label}`, `{exit_code}` and `{duration}` placeholders.
+    #[serde(default)]
+    pub success_message: Option<String>,
+    /// A message to notify with when the task fails, instead of the default one, with the same placeholders as `success_message`.
+    #[serde(default)]
+    pub failure_message: Option<String>,
+    /// Where to put the terminal tab of the task:
+    /// * `dock` — in the terminal panel (default)
+    /// * `center` — in the center pane, next to the editors
+    #[serde(default)]
+    pub reveal_target: RevealTarget,
+    /// Labels of the tasks to run, one after another, before this task.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Whether to ask the command to flush its output line by line, for the tools buffering it when the output is not a terminal,
+    /// e.g. with the `open_buffer` output mode. See [`SpawnInTerminal::apply_line_buffering`] for what is done to achieve that.
+    #[serde(default)]
+    pub force_line_buffered: bool,
+    /// A path to a file to copy the task output into, e.g. to keep a log of the tasks run repeatedly.
+    /// Relative paths are resolved against the task's working directory.
+    #[serde(default)]
+    pub log_file: Option<String>,
+    /// Whether to append the output to the `log_file`, instead of overwriting it on every run.
+    #[serde(default)]
+    pub log_append: bool,
+    /// A name of the terminal tab for the task, e.g. `Run test: ${ZED_SYMBOL}`, instead of the (substituted) `label`.
+    /// The `label` is still used to name the task in the pickers.
+    #[serde(default)]
+    pub context_label: Option<String>,
+    /// A shell command printing `KEY=VALUE` lines (or `export KEY=VALUE` ones) to add to the task env, e.g. `aws configure export-credentials --format env`.
+    /// The command is not run during the resolution, but right before the spawn, see [`SpawnInTerminal::apply_env_command`];
+    /// the variables from `env`, `env_file` and the task context win over the ones printed by the command.
+    #[serde(default)]
+    pub env_command: Option<String>,
+    /// Whether to leave the task out of the default task list, see [`TaskTemplates::visible`].
+    /// Hidden tasks can still be run by their exact label.
+    #[serde(default)]
+    pub hidden: bool,
+    /// A message to show when the task is run, e.g. `Use "build all" instead`, marking it as deprecated.
+    #[serde(default)]
+    pub deprecated: Option<String>,
+    /// The encoding of the task output, as a WHATWG encoding label, e.g. `utf-16le` or `windows-1252`, for the spawner to decode it with.
+    /// The output is expected in UTF-8 if not set.
+    #[serde(default)]
+    pub output_encoding: Option<String>,
+    /// Env variables forcing the locale of the process, e.g. `{"LC_ALL": "C"}` to get the messages in English,
+    /// that override the same variables from `env` and `env_file` in the resolved task env.
+    #[serde(default)]
+    pub locale_env: Option<BTreeMap<String, String>>,
+    /// A name of the terminal profile (a named set of the shell, env and cwd defaults) to run the task with.
+    /// The terminal applies the profile settings first, then the task's own `env`, `cwd` and other overrides.
+    #[serde(default)]
+    pub terminal_profile: Option<String>,
+    /// Whether to run the task without the directories Zed prepends to the `PATH` for its own integrations,
+    /// e.g. the Python virtual environment's `bin`, so that the task gets the same `PATH` as outside of Zed.
+    /// The `PATH` set in the task's own `env` is kept.
+    #[serde(default)]
+    pub strip_zed_path_additions: bool,
+    /// Candidates for the `cwd`, tried in order, e.g. `["${env:HOME}", "${env:USERPROFILE}"]` to run in the home directory on every platform.
+    /// Besides the task variables, the candidates may reference the env variables of Zed as `${env:NAME}`:
+    /// the first candidate with all its env variables set and non-empty, that resolves to an existing directory, is used instead of `cwd`.
+    /// `cwd` is used if no candidate is suitable. The candidates are checked right before the spawn, see [`SpawnInTerminal::apply_cwd_candidates`].
+    #[serde(default)]
+    pub cwd_candidates: Vec<String>,
+    /// Axes of values to run the task with, e.g. `{"toolchain": ["stable", "nightly"], "target": ["x86_64", "wasm32"]}`,
+    /// referenced as `${matrix:toolchain}` in the template fields: [`TaskTemplates::for_platform`] produces a template for every combination.
+    #[serde(default)]
+    pub matrix: BTreeMap<String, Vec<String>>,
+    /// Fields to use on particular platforms instead of the common ones, e.g. `{"windows": {"command": "build.cmd"}}`,
+    /// applied with [`TaskTemplate::for_platform`] to the loaded templates, before their inheritance is flattened, and when resolving the task.
+    #[serde(default)]
+    pub platform_overrides: BTreeMap<Platform, PlatformOverride>,
+    /// A check telling when a background task, e.g. a dev server, becomes ready to use after the spawn, see [`ReadyProbe`].
+    /// Tasks depending on it, and other actions waiting for it, should wait for the probe to pass rather than for the spawn only.
+    #[serde(default)]
+    pub ready_when: Option<ReadyProbe>,
+    /// How the `PATH` set in the task `env` combines with the inherited one, see [`PathMode`].
+    #[serde(default)]
+    pub path_mode: PathMode,
+    /// Whether the `command` is a complete command line, e.g. `foo "bar baz" | qux` pasted from a terminal, to run verbatim by the shell.
+    /// This bypasses the safe quoting of the arguments, so that the shell interprets the quotes, pipes and expansions in the `command`,
+    /// and cannot be combined with `args`.
+    #[serde(default)]
+    pub raw_command: bool,
+    /// A command to run the task under, e.g. `["time"]`, `["nice", "-n", "10"]` or a sandbox runner:
+    /// its first element becomes the program to spawn, followed by the rest of its elements, then by the task command and arguments.
+    #[serde(default)]
+    pub wrapper: Option<Vec<String>>,
+    /// Whether the task is the default one of its `category`, e.g. to run with "run default build task",
+    /// imported from the `isDefault` of the Code task `group`, see [`TaskTemplates::default_for_category`].
+    #[serde(default)]
+    pub is_default_for_group: bool,
+    /// The shell to run the task in, e.g. `/bin/zsh`, instead of the one from the terminal settings.
+    #[serde(default)]
+    pub shell: Option<String>,
+    /// The maximum number of lines to keep in the task terminal scrollback, instead of the one from the terminal settings.
+    #[serde(default)]
+    pub scrollback: Option<usize>,
+}
+
+/// An ordering key of a template, merged from one of the multiple task sources: the source priority first, then the declaration index within the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TemplateOrder {
+    /// Priority of the source the template came from, sources with the lower values go first.
+    pub source_priority: u32,
+    /// Index of the template in its source.
+    pub declaration_index: usize,
+}
+
+/// A declaration of a value to ask the user for before running the task.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TaskInput {
+    /// Identifier of the input, to reference it as `${input:<id>}`.
+    pub id: String,
+    /// Human readable description of the input to show when asking for it.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// A value to propose by default.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// A prefix of the references to the task inputs, see [`TaskTemplate::inputs`].
+const INPUT_REFERENCE_PREFIX: &str = "${input:";
+
+/// A prefix of the references to the env variables in [`TaskTemplate::cwd_candidates`].
+const ENV_REFERENCE_PREFIX: &str = "${env:";
+
+/// A placeholder of the inherited values in `args` and `env`, see [`TaskTemplate::extends`].
+const INHERIT_MARKER: &str = "${inherit}";
+
+/// An argument of the command, see [`TaskTemplate::args`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum TaskArg {
+    /// An argument to always pass.
+    Plain(String),
+    /// An argument left out of the resolved task unless its condition holds for the task variables.
+    /// The condition is a variable name, e.g. `verbose` for `ZED_CUSTOM_verbose`, true if set to anything but an empty string, `0` or `false`,
+    /// possibly negated with `!`, compared with `==` or `!=`, e.g. `profile == release`, and combined with `&&` and `||`.
+    Conditional {
+        /// The argument.
+        value: String,
+        /// The condition to include the argument on.
+        when: String,
+    },
+}
+
+impl TaskArg {
+    /// The argument to pass, regardless of its condition.
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Plain(value) | Self::Conditional { value, .. } => value,
+        }
+    }
+
+    /// The argument to pass, regardless of its condition, for editing.
+    pub fn value_mut(&mut self) -> &mut String {
+        match self {
+            Self::Plain(value) | Self::Conditional { value, .. } => value,
+        }
+    }
+
+    /// The condition to include the argument on, `None` for the arguments passed unconditionally.
+    pub fn condition(&self) -> Option<&str> {
+        match self {
+            Self::Plain(_) => None,
+            Self::Conditional { when, .. } => Some(when),
+        }
+    }
+}
+
+impl From<String> for TaskArg {
+    fn from(value: String) -> Self {
+        Self::Plain(value)
+    }
+}
+
+impl From<&str> for TaskArg {
+    fn from(value: &str) -> Self {
+        Self::Plain(value.to_owned())
+    }
 }
 
 /// What to do with the terminal pane and tab, after the command was started.
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RevealStrategy {
     /// Always show the terminal pane, add and focus the corresponding task's tab in it.
@@ -53,12 +348,250 @@ pub enum RevealStrategy {
     Always,
     /// Do not change terminal pane focus, but still add/reuse the task's tab there.
     Never,
+    /// Show the terminal pane with the task's tab, but keep the focus in the editor, e.g. for quick tasks while typing.
+    RevealThenRefocusEditor,
+}
+
+/// Where to put the terminal tab of the task.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevealTarget {
+    /// In the terminal panel.
+    #[default]
+    Dock,
+    /// In the center pane, next to the editors.
+    Center,
+}
+
+/// A value of an env variable in the template.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(untagged)]
+pub enum EnvValue {
+    /// A plain string value.
+    String(String),
+    /// Values joined with the OS path list separator: `:` on Unix and `;` on Windows, e.g. for `PATH`-like variables.
+    List(Vec<String>),
+    /// Values joined with a custom separator.
+    Joined {
+        /// Values to join.
+        values: Vec<String>,
+        /// A separator to put between the values.
+        separator: String,
+    },
+}
+
+impl EnvValue {
+    /// All strings of the value, that may contain variable references.
+    pub(crate) fn parts(&self) -> &[String] {
+        match self {
+            Self::String(value) => std::slice::from_ref(value),
+            Self::List(values) | Self::Joined { values, .. } => values,
+        }
+    }
+
+    /// Replaces the `${inherit}` markers with the parent's value, see [`TaskTemplate::extends`].
+    fn expand_inherited(&mut self, parent: Option<&EnvValue>) {
+        let parent_parts = parent.map_or(&[][..], EnvValue::parts);
+        let parent_value = parent.map_or_else(String::new, |parent| {
+            parent.join(parent_parts, cfg!(windows))
+        });
+        match self {
+            Self::String(value) => *value = value.replace(INHERIT_MARKER, &parent_value),
+            Self::List(values) | Self::Joined { values, .. } => {
+                *values = std::mem::take(values)
+                    .into_iter()
+                    .flat_map(|value| {
+                        if value == INHERIT_MARKER {
+                            parent_parts.to_vec()
+                        } else {
+                            vec![value.replace(INHERIT_MARKER, &parent_value)]
+                        }
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    /// All strings of the value, to substitute the variable references in.
+    pub(crate) fn parts_mut(&mut self) -> &mut [String] {
+        match self {
+            Self::String(value) => std::slice::from_mut(value),
+            Self::List(values) | Self::Joined { values, .. } => values,
+        }
+    }
+
+    /// Joins the (substituted) parts of the value into the final env variable value.
+    pub(crate) fn join(&self, parts: &[String], is_windows: bool) -> String {
+        match self {
+            Self::String(_) => parts.concat(),
+            Self::List(_) => parts.join(if is_windows { ";" } else { ":" }),
+            Self::Joined { separator, .. } => parts.join(separator),
+        }
+    }
+}
+
+impl From<String> for EnvValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for EnvValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+/// Where to show the output of the task command.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// Run the command in a terminal tab.
+    #[default]
+    Terminal,
+    /// Capture the command output into a read-only buffer.
+    OpenBuffer,
+}
+
+/// How the `PATH` of the task `env` combines with the `PATH` the task inherits, see [`SpawnInTerminal::merge_env_from`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PathMode {
+    /// The task's directories go first, so that the tools found there win over the inherited ones (default).
+    #[default]
+    Prepend,
+    /// The task's directories go last, used only for the tools not found in the inherited ones.
+    Append,
+    /// The task's `PATH` is used as is, ignoring the inherited one.
+    Replace,
+}
+
+/// A readiness check of a spawned task, see [`TaskTemplate::ready_when`].
+/// Evaluated by the spawner, the resolution only passes it to the [`SpawnInTerminal::ready_when`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadyProbe {
+    /// The task is ready once its output contains the text given, e.g. `{"output_contains": "Listening on"}`.
+    OutputContains(String),
+    /// The task is ready once the local TCP port given accepts connections, e.g. `{"port_open": 8080}`.
+    PortOpen(u16),
+}
+
+/// A signal to stop the task process gracefully with.
+/// Declared either by name (`"term"`, `"int"`, `"kill"`, optionally with the `SIG` prefix) or by number.
+///
+/// On Windows, that has no signals, [`StopSignal::Kill`] terminates the process and all other signals are sent as Ctrl+C.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "StopSignalRepr", into = "StopSignalRepr")]
+pub enum StopSignal {
+    /// `SIGTERM`, a request to terminate.
+    #[default]
+    Term,
+    /// `SIGINT`, an interrupt, as on Ctrl+C.
+    Int,
+    /// `SIGKILL`, an immediate termination that the process cannot handle.
+    Kill,
+    /// Any other Unix signal by its number.
+    Number(i32),
+}
+
+/// The task file format of [`StopSignal`].
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum StopSignalRepr {
+    Name(String),
+    Number(i32),
+}
+
+impl StopSignal {
+    /// The Unix number of the signal.
+    pub fn number(&self) -> i32 {
+        match self {
+            Self::Term => 15,
+            Self::Int => 2,
+            Self::Kill => 9,
+            Self::Number(number) => *number,
+        }
+    }
+
+    /// Whether the signal lets the process clean up before exiting, see [`StopSignal`] for the Windows equivalents.
+    pub fn is_graceful(&self) -> bool {
+        self.number() != Self::Kill.number()
+    }
+}
+
+impl TryFrom<StopSignalRepr> for StopSignal {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: StopSignalRepr) -> anyhow::Result<Self> {
+        match repr {
+            StopSignalRepr::Name(name) => {
+                let name = name.to_ascii_uppercase();
+                match name.strip_prefix("SIG").unwrap_or(&name) {
+                    "TERM" => Ok(Self::Term),
+                    "INT" => Ok(Self::Int),
+                    "KILL" => Ok(Self::Kill),
+                    _ => bail!("Unknown stop signal {name:?}, expected `term`, `int`, `kill` or a signal number"),
+                }
+            }
+            StopSignalRepr::Number(number) => match number {
+                15 => Ok(Self::Term),
+                2 => Ok(Self::Int),
+                9 => Ok(Self::Kill),
+                1..=64 => Ok(Self::Number(number)),
+                _ => bail!("Invalid stop signal number {number}"),
+            },
+        }
+    }
+}
+
+impl From<StopSignal> for StopSignalRepr {
+    fn from(signal: StopSignal) -> Self {
+        match signal {
+            StopSignal::Term => Self::Name("term".to_string()),
+            StopSignal::Int => Self::Name("int".to_string()),
+            StopSignal::Kill => Self::Name("kill".to_string()),
+            StopSignal::Number(number) => Self::Number(number),
+        }
+    }
 }
 
 /// A group of Tasks defined in a JSON file.
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct TaskTemplates(pub Vec<TaskTemplate>);
 
+/// Templates arranged into a tree by their label segments, see [`TaskTemplates::grouped`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TaskGroup<'a> {
+    /// Templates which labels end at this level of the tree, in their declaration order.
+    pub templates: Vec<&'a TaskTemplate>,
+    /// Nested groups with their names, in the order of their first appearance.
+    pub subgroups: Vec<(&'a str, TaskGroup<'a>)>,
+}
+
+impl<'a> TaskGroup<'a> {
+    fn insert(&mut self, group_path: &[&'a str], template: &'a TaskTemplate) {
+        let Some((&group_name, rest)) = group_path.split_first() else {
+            self.templates.push(template);
+            return;
+        };
+        let subgroup_index = match self
+            .subgroups
+            .iter()
+            .position(|(name, _)| *name == group_name)
+        {
+            Some(index) => index,
+            None => {
+                self.subgroups.push((group_name, TaskGroup::default()));
+                self.subgroups.len() - 1
+            }
+        };
+        self.subgroups[subgroup_index].1.insert(rest, template);
+    }
+}
+
 impl TaskTemplates {
     /// Generates JSON schema of Tasks JSON template format.
     pub fn generate_json_schema() -> serde_json_lenient::Value {
@@ -69,17 +602,530 @@ impl TaskTemplates {
 
         serde_json_lenient::to_value(schema).unwrap()
     }
+
+    /// Sets the ordering keys of all templates: the priority given, and their current indices as declaration indices.
+    /// Call this for each source's templates before merging them, then sort the merged result with [`TaskTemplates::stable_sort`].
+    pub fn with_source_priority(mut self, source_priority: u32) -> Self {
+        for (declaration_index, template) in self.0.iter_mut().enumerate() {
+            template.order = Some(TemplateOrder {
+                source_priority,
+                declaration_index,
+            });
+        }
+        self
+    }
+
+    /// Sorts the templates by their ordering keys, so that the order does not depend on the order the sources were merged in.
+    /// Templates without the ordering key go last, preserving their relative order.
+    pub fn stable_sort(&mut self) {
+        self.0
+            .sort_by_key(|template| (template.order.is_none(), template.order));
+    }
+
+    /// Resolves the templates one by one, as the iterator gets consumed, skipping the ones that fail to resolve,
+    /// e.g. to resolve only the tasks a picker shows. See [`crate::ResolutionCache::iter_resolved`] to make the repeated iterations cheap.
+    pub fn iter_resolved<'a>(
+        &'a self,
+        id_base: &'a str,
+        cx: &'a TaskContext,
+    ) -> impl Iterator<Item = ResolvedTask> + 'a {
+        self.0
+            .iter()
+            .filter_map(move |template| template.resolve_task(id_base, cx.clone()))
+    }
+
+    /// The first template with the label given, see [`TaskTemplates::find_all_by_label`] for the labels repeated, e.g. across the sources merged.
+    pub fn find_by_label(&self, label: &str) -> Option<&TaskTemplate> {
+        self.0.iter().find(|template| template.label == label)
+    }
+
+    /// The templates to list by default, leaving the [`TaskTemplate::hidden`] ones out.
+    pub fn visible(&self) -> Vec<&TaskTemplate> {
+        self.0.iter().filter(|template| !template.hidden).collect()
+    }
+
+    /// All templates with the label given, in their order.
+    pub fn find_all_by_label(&self, label: &str) -> Vec<&TaskTemplate> {
+        self.0
+            .iter()
+            .filter(|template| template.label == label)
+            .collect()
+    }
+
+    /// Produces the templates with their `extends` chains flattened: each template gets the fields inherited from its parents,
+    /// according to the rules described in [`TaskTemplate::extends`].
+    ///
+    /// When multiple templates have the parent's label, the first one is used.
+    /// Templates with unknown parents or with cyclic inheritance chains inherit nothing from the problematic part of the chain.
+    pub fn resolve_inheritance(&self) -> Self {
+        let mut templates_by_label = HashMap::default();
+        for template in &self.0 {
+            templates_by_label
+                .entry(template.label.as_str())
+                .or_insert(template);
+        }
+        Self(
+            self.0
+                .iter()
+                .map(|template| flatten_inheritance(template, &templates_by_label, &mut Vec::new()))
+                .collect(),
+        )
+    }
+
+    /// Arranges the templates into a tree, using their [`TaskTemplate::label_path`] segments as group names, e.g.
+    /// `build/web/release` ends up in the `release` leaf of the `web` subgroup of the `build` group.
+    pub fn grouped(&self, separator: char) -> TaskGroup<'_> {
+        let mut root = TaskGroup::default();
+        for template in &self.0 {
+            let label_path = template.label_path(separator);
+            let group_path = &label_path[..label_path.len().saturating_sub(1)];
+            root.insert(group_path, template);
+        }
+        root
+    }
+
+    /// Serializes the templates into a tasks file contents, see [`TaskTemplate::to_authored_json`].
+    pub fn to_authored_json(&self) -> serde_json_lenient::Value {
+        self.0.iter().map(TaskTemplate::to_authored_json).collect()
+    }
+
+    /// A hash of the whole template set, that does not depend on the order of the templates, but changes with any change in any of them,
+    /// e.g. to cheaply check whether the tasks changed after a reload. Combines the [`TaskTemplate::content_hash`]es of all templates.
+    pub fn fingerprint(&self) -> u64 {
+        // Unlike XOR, the sum does not let the duplicate templates cancel each other out.
+        self.0.iter().fold(0, |fingerprint: u64, template| {
+            fingerprint.wrapping_add(template.content_hash())
+        })
+    }
+
+    /// Groups the templates by their [`TaskTemplate::category`], keeping the declaration order within each group.
+    /// The uncategorized templates are under the `None` key, which goes first.
+    pub fn group_by_category(&self) -> BTreeMap<Option<String>, Vec<&TaskTemplate>> {
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+        for template in &self.0 {
+            groups
+                .entry(template.category.clone())
+                .or_default()
+                .push(template);
+        }
+        groups
+    }
+
+    /// Resolves the templates carrying the tag, compared case-insensitively, e.g. to run all `precommit` tasks one after another:
+    /// the ones failing to resolve in the context are skipped, the rest are returned in the declaration order,
+    /// see [`TaskTemplates::stable_sort`].
+    pub fn resolve_tagged(&self, tag: &str, id_base: &str, cx: &TaskContext) -> Vec<ResolvedTask> {
+        let tag = tag.trim().to_lowercase();
+        let mut tagged = self
+            .0
+            .iter()
+            .filter(|template| {
+                template
+                    .tags
+                    .iter()
+                    .any(|template_tag| template_tag.trim().to_lowercase() == tag)
+            })
+            .collect::<Vec<_>>();
+        tagged.sort_by_key(|template| (template.order.is_none(), template.order));
+        tagged
+            .into_iter()
+            .filter_map(|template| template.resolve_task(id_base, cx.clone()))
+            .collect()
+    }
+
+    /// The first template marked as the default one of the category, compared case-insensitively, see [`TaskTemplate::is_default_for_group`].
+    pub fn default_for_category(&self, category: &str) -> Option<&TaskTemplate> {
+        self.0.iter().find(|template| {
+            template.is_default_for_group
+                && template
+                    .category
+                    .as_deref()
+                    .is_some_and(|template_category| {
+                        template_category.eq_ignore_ascii_case(category)
+                    })
+        })
+    }
+
+    /// Counts the templates carrying each tag, see [`TaskTemplate::tags`].
+    /// Tags are lowercased, a template with the same tag repeated is counted once.
+    pub fn tag_counts(&self) -> TagCounts {
+        let mut counts = TagCounts::default();
+        for template in &self.0 {
+            let tags = template
+                .tags
+                .iter()
+                .map(|tag| tag.trim().to_lowercase())
+                .filter(|tag| !tag.is_empty())
+                .collect::<BTreeSet<_>>();
+            if tags.is_empty() {
+                counts.untagged += 1;
+            }
+            for tag in tags {
+                *counts.by_tag.entry(tag).or_default() += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// Numbers of templates per tag, see [`TaskTemplates::tag_counts`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TagCounts {
+    /// Lowercased tags and the numbers of templates carrying them.
+    pub by_tag: BTreeMap<String, usize>,
+    /// Number of templates without any tags.
+    pub untagged: usize,
+}
+
+fn flatten_inheritance<'a>(
+    template: &'a TaskTemplate,
+    templates_by_label: &HashMap<&'a str, &'a TaskTemplate>,
+    visited_labels: &mut Vec<&'a str>,
+) -> TaskTemplate {
+    let mut flattened = template.clone();
+    flattened.extends = None;
+    let Some(parent_label) = template.extends.as_deref() else {
+        return flattened;
+    };
+    visited_labels.push(template.label.as_str());
+    if visited_labels.contains(&parent_label) {
+        flattened.inherit_from(&TaskTemplate::default());
+        return flattened;
+    }
+    let Some(parent) = templates_by_label.get(parent_label) else {
+        flattened.inherit_from(&TaskTemplate::default());
+        return flattened;
+    };
+    let parent = flatten_inheritance(parent, templates_by_label, visited_labels);
+    flattened.inherit_from(&parent);
+    flattened
 }
 
 impl TaskTemplate {
+    /// Fills the template fields from the parent one, according to the rules described in [`TaskTemplate::extends`].
+    fn inherit_from(&mut self, parent: &TaskTemplate) {
+        if self.command.trim().is_empty() {
+            self.command = parent.command.clone();
+        }
+        let is_inherit_marker =
+            |arg: &TaskArg| matches!(arg, TaskArg::Plain(arg) if arg == INHERIT_MARKER);
+        if self.args.iter().any(is_inherit_marker) {
+            self.args = std::mem::take(&mut self.args)
+                .into_iter()
+                .flat_map(|arg| {
+                    if is_inherit_marker(&arg) {
+                        parent.args.clone()
+                    } else {
+                        vec![arg]
+                    }
+                })
+                .collect();
+        } else if self.args.is_empty() {
+            self.args = parent.args.clone();
+        }
+        if self.cwd.is_none() {
+            self.cwd = parent.cwd.clone();
+        }
+        if self.cwd_candidates.is_empty() {
+            self.cwd_candidates = parent.cwd_candidates.clone();
+        }
+        if self.inputs.is_empty() {
+            self.inputs = parent.inputs.clone();
+        }
+        for (key, value) in &mut self.env {
+            value.expand_inherited(parent.env.get(key));
+        }
+        let mut env = parent.env.clone();
+        env.extend(std::mem::take(&mut self.env));
+        self.env = env;
+    }
+
+    /// Serializes the template the way a user would write it in a tasks file: without the in-memory only fields, such as `order`,
+    /// and without the fields that have their default values. Templates flattened with [`TaskTemplates::resolve_inheritance`]
+    /// get written with all inherited values and no `extends`.
+    pub fn to_authored_json(&self) -> serde_json_lenient::Value {
+        let defaults = serde_json_lenient::to_value(Self::default()).unwrap_or_default();
+        let mut authored = serde_json_lenient::to_value(self).unwrap_or_default();
+        if let Some(fields) = authored.as_object_mut() {
+            fields.retain(|field, value| field == "label" || defaults.get(field) != Some(value));
+        }
+        authored
+    }
+
+    /// Whether the command exit code means success, see [`TaskTemplate::success_exit_codes`].
+    pub fn is_success(&self, exit_code: i32) -> bool {
+        if self.success_exit_codes.is_empty() {
+            exit_code == 0
+        } else {
+            self.success_exit_codes.contains(&exit_code)
+        }
+    }
+
+    /// All template fields that may contain variable references, in no particular order.
+    pub(crate) fn template_strings(&self) -> impl Iterator<Item = &str> {
+        [self.label.as_str(), self.command.as_str()]
+            .into_iter()
+            .chain(self.args.iter().map(TaskArg::value))
+            .chain(self.env.iter().flat_map(|(key, value)| {
+                std::iter::once(key.as_str()).chain(value.parts().iter().map(String::as_str))
+            }))
+            .chain(self.cwd.as_deref())
+            .chain(self.cwd_candidates.iter().map(String::as_str))
+            .chain(self.confirm.as_deref())
+            .chain(self.env_file.as_deref())
+            .chain(self.on_success.as_deref())
+            .chain(self.on_failure.as_deref())
+            .chain(self.log_file.as_deref())
+            .chain(self.context_label.as_deref())
+            .chain(self.env_command.as_deref())
+            .chain(
+                self.platform_overrides
+                    .values()
+                    .flat_map(PlatformOverride::template_strings),
+            )
+            .chain(self.wrapper.iter().flatten().map(String::as_str))
+            .chain(self.shell.as_deref())
+    }
+
+    /// Ids of all inputs referenced as `${input:<id>}` in the template fields.
+    pub(crate) fn input_references(&self) -> Vec<&str> {
+        let mut references = Vec::new();
+        for mut template_str in self.template_strings() {
+            while let Some(start) = template_str.find(INPUT_REFERENCE_PREFIX) {
+                template_str = &template_str[start + INPUT_REFERENCE_PREFIX.len()..];
+                let Some(end) = template_str.find('}') else {
+                    break;
+                };
+                references.push(&template_str[..end]);
+                template_str = &template_str[end + 1..];
+            }
+        }
+        references
+    }
+
+    /// Splits the label into hierarchy segments, e.g. `group/subgroup/name` into `["group", "subgroup", "name"]`.
+    /// Segments are trimmed, blank ones are omitted; a label without separators is a single segment.
+    pub fn label_path(&self, separator: char) -> Vec<&str> {
+        self.label
+            .split(separator)
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .collect()
+    }
+
+    /// A hash of all template fields, that does not depend on the order of the `env` entries.
+    /// Equal templates have equal hashes, see [`crate::ResolutionCache`].
+    pub fn content_hash(&self) -> u64 {
+        let Self {
+            label,
+            command,
+            args,
+            env,
+            cwd,
+            use_new_terminal,
+            allow_concurrent_runs,
+            reveal,
+            extends,
+            echo_command,
+            inputs,
+            order,
+            confirm,
+            env_file,
+            action,
+            stop_signal,
+            stop_timeout_ms,
+            run_as_user,
+            run_as_group,
+            output_mode,
+            mutex_group,
+            on_success,
+            on_failure,
+            tags,
+            detached,
+            create_cwd,
+            args_file,
+            category,
+            success_exit_codes,
+            success_message,
+            failure_message,
+            reveal_target,
+            depends_on,
+            force_line_buffered,
+            log_file,
+            log_append,
+            context_label,
+            env_command,
+            hidden,
+            deprecated,
+            output_encoding,
+            locale_env,
+            terminal_profile,
+            strip_zed_path_additions,
+            cwd_candidates,
+            matrix,
+            platform_overrides,
+            ready_when,
+            path_mode,
+            raw_command,
+            wrapper,
+            is_default_for_group,
+            shell,
+            scrollback,
+        } = self;
+        let mut env = env.iter().collect::<Vec<_>>();
+        env.sort();
+        let mut hasher = DefaultHasher::new();
+        (label, command, args, env, cwd).hash(&mut hasher);
+        (use_new_terminal, allow_concurrent_runs, reveal, extends).hash(&mut hasher);
+        (echo_command, inputs, order, confirm, env_file, action).hash(&mut hasher);
+        (stop_signal, stop_timeout_ms, run_as_user, run_as_group).hash(&mut hasher);
+        (output_mode, mutex_group, on_success, on_failure, tags).hash(&mut hasher);
+        (detached, create_cwd, args_file, category).hash(&mut hasher);
+        (success_exit_codes, success_message, failure_message).hash(&mut hasher);
+        (reveal_target, depends_on, force_line_buffered).hash(&mut hasher);
+        (log_file, log_append, context_label, env_command).hash(&mut hasher);
+        (hidden, deprecated, output_encoding, locale_env).hash(&mut hasher);
+        (terminal_profile, strip_zed_path_additions, cwd_candidates).hash(&mut hasher);
+        (
+            matrix,
+            platform_overrides,
+            ready_when,
+            path_mode,
+            raw_command,
+            wrapper,
+            is_default_for_group,
+            shell,
+            scrollback,
+        )
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Names of all Zed variables referenced in the template fields, without modifiers and offsets, deduplicated.
+    pub(crate) fn variable_references(&self) -> Vec<String> {
+        let mut references = Vec::new();
+        for template_str in self.template_strings() {
+            shellexpand::env_with_context_no_errors(template_str, |variable| {
+                let mut variable_name = variable.split(':').next().unwrap_or_default();
+                if let Some((name, offset)) = variable_name.rsplit_once(['+', '-']) {
+                    if !offset.is_empty() && offset.bytes().all(|b| b.is_ascii_digit()) {
+                        variable_name = name;
+                    }
+                }
+                if variable_name.starts_with(ZED_VARIABLE_NAME_PREFIX)
+                    && !references
+                        .iter()
+                        .any(|reference| reference == variable_name)
+                {
+                    references.push(variable_name.to_string());
+                }
+                None::<String>
+            });
+        }
+        references
+    }
+
+    /// The built-in variables the template references, that are missing or empty in the context, in the order of the references,
+    /// e.g. to warn that the task needs a symbol under the cursor before running it, or to ask for the values.
+    /// Optional variables are reported too, as they resolve to empty strings; [`VariableName::Custom`] ones are not reported.
+    pub fn unsatisfied_variables(&self, context: &TaskContext) -> Vec<VariableName> {
+        let task_variables = context.task_variables.clone().into_env_variables();
+        self.variable_references()
+            .into_iter()
+            .filter(|reference| {
+                task_variables
+                    .get(reference)
+                    .map_or(true, |value| value.is_empty())
+            })
+            .filter_map(|reference| VariableName::from_env_name(&reference))
+            .filter(|variable| !matches!(variable, VariableName::Custom(_)))
+            .collect()
+    }
+
+    /// Whether the template has all fields required to run a task: non-blank label, and either non-blank command or an action.
+    pub fn is_runnable(&self) -> bool {
+        !self.label.trim().is_empty() && (!self.command.trim().is_empty() || self.action.is_some())
+    }
+
     /// Replaces all `VariableName` task variables in the task template string fields.
     /// If any replacement fails or the new string substitutions still have [`ZED_VARIABLE_NAME_PREFIX`],
     /// `None` is returned.
+    /// A relative [`TaskTemplate::env_file`] is resolved against the task cwd, `None` is returned if the task has no cwd.
     ///
     /// Every [`ResolvedTask`] gets a [`TaskId`], based on the `id_base` (to avoid collision with various task sources),
     /// and hashes of its template and [`TaskContext`], see [`ResolvedTask`] fields' documentation for more details.
     pub fn resolve_task(&self, id_base: &str, cx: TaskContext) -> Option<ResolvedTask> {
-        if self.label.trim().is_empty() || self.command.trim().is_empty() {
+        self.resolve_task_with_options(id_base, cx, &ResolveOptions::default())
+    }
+
+    /// Same as [`TaskTemplate::resolve_task`], with the `fallback` context layered under the `primary` one:
+    /// variables (and the cwd) missing in the `primary` context are taken from the `fallback`, e.g. the last editor context,
+    /// for the tasks spawned from the surfaces with no file context, such as the project panel.
+    pub fn resolve_with_fallback(
+        &self,
+        id_base: &str,
+        primary: &TaskContext,
+        fallback: &TaskContext,
+    ) -> Option<ResolvedTask> {
+        let mut task_variables = fallback.task_variables.clone();
+        task_variables.extend(primary.task_variables.clone());
+        let cx = TaskContext {
+            cwd: primary.cwd.clone().or_else(|| fallback.cwd.clone()),
+            task_variables,
+        };
+        self.resolve_task(id_base, cx)
+    }
+
+    /// The label the task gets displayed with when resolved in the context, e.g. in its terminal tab, with the display rules applied in order:
+    /// 1. the `context_label` is taken, or the `label`, if not set,
+    /// 2. the variables are substituted, the long values shortened from the front, keeping their ends,
+    ///    with the references to the variables missing in the context kept as is,
+    /// 3. the result is truncated to `max_width` characters, ending with `…`, if given.
+    pub fn effective_label(&self, context: &TaskContext, max_width: Option<usize>) -> String {
+        let options = ResolveOptions {
+            unresolved_placeholder: Some(UnresolvedPlaceholder::KeepToken),
+            ..ResolveOptions::default()
+        };
+        let task_variables = context.task_variables.clone().into_env_variables();
+        let label = self
+            .shortened_label(&task_variables, &options)
+            .unwrap_or_else(|| self.context_label.clone().unwrap_or(self.label.clone()));
+        match max_width {
+            Some(max_width) if label.chars().count() > max_width => {
+                format!("{}…", util::truncate(&label, max_width.saturating_sub(1)))
+            }
+            _ => label,
+        }
+    }
+
+    fn shortened_label(
+        &self,
+        task_variables: &HashMap<String, String>,
+        options: &ResolveOptions,
+    ) -> Option<String> {
+        substitute_all_template_variables_in_str(
+            self.context_label.as_deref().unwrap_or(&self.label),
+            &truncate_variables(task_variables),
+            options,
+        )
+    }
+
+    /// Same as [`TaskTemplate::resolve_task`], with the substitution adjusted by the [`ResolveOptions`].
+    pub fn resolve_task_with_options(
+        &self,
+        id_base: &str,
+        cx: TaskContext,
+        options: &ResolveOptions,
+    ) -> Option<ResolvedTask> {
+        if !self.platform_overrides.is_empty() {
+            let mut resolved = self
+                .clone()
+                .for_platform(Platform::current())
+                .resolve_task_with_options(id_base, cx, options)?;
+            resolved.original_task = self.clone();
+            return Some(resolved);
+        }
+        if !self.is_runnable() {
             return None;
         }
         let TaskContext {
@@ -87,51 +1133,475 @@ impl TaskTemplate {
             task_variables,
         } = cx;
         let task_variables = task_variables.into_env_variables();
-        let truncated_variables = truncate_variables(&task_variables);
-        let cwd = match self.cwd.as_deref() {
-            Some(cwd) => Some(substitute_all_template_variables_in_str(
-                cwd,
-                &task_variables,
-            )?),
-            None => None,
-        }
-        .map(PathBuf::from)
-        .or(cwd);
-        let shortened_label =
-            substitute_all_template_variables_in_str(&self.label, &truncated_variables)?;
-        let full_label = substitute_all_template_variables_in_str(&self.label, &task_variables)?;
-        let command = substitute_all_template_variables_in_str(&self.command, &task_variables)?;
-        let args = substitute_all_template_variables_in_vec(self.args.clone(), &task_variables)?;
-
+        let shortened_label = self.shortened_label(&task_variables, options)?;
+        let full_label =
+            substitute_all_template_variables_in_str(&self.label, &task_variables, options)?;
         let task_hash = to_hex_hash(&self)
             .context("hashing task template")
             .log_err()?;
-        let variables_hash = to_hex_hash(&task_variables)
+        let variables_hash = to_hex_hash(task_variables.iter().collect::<BTreeMap<_, _>>())
             .context("hashing task variables")
             .log_err()?;
         let id = TaskId(format!("{id_base}_{task_hash}_{variables_hash}"));
-        let mut env = substitute_all_template_variables_in_map(self.env.clone(), &task_variables)?;
-        env.extend(task_variables);
-        Some(ResolvedTask {
-            id: id.clone(),
-            original_task: self.clone(),
-            resolved_label: full_label.clone(),
-            resolved: Some(SpawnInTerminal {
+        if let Some(action) = &self.action {
+            return Some(ResolvedTask {
                 id,
+                original_task: self.clone(),
+                resolved_label: full_label,
+                resolved: None,
+                last_run: None,
+                kind: TaskKind::Action(action.clone()),
+            });
+        }
+
+        let template_cwd = match self.cwd.as_deref() {
+            Some(cwd) => Some(substitute_all_template_variables_in_str(
                 cwd,
-                full_label,
-                label: shortened_label,
-                command,
-                args,
-                env,
-                use_new_terminal: self.use_new_terminal,
-                allow_concurrent_runs: self.allow_concurrent_runs,
-                reveal: self.reveal,
-            }),
-        })
+                &task_variables,
+                options,
+            )?),
+            None => None,
+        }
+        .map(PathBuf::from);
+        let cwd = template_cwd.or(cwd);
+        let cwd_candidates = self
+            .cwd_candidates
+            .iter()
+            .filter_map(|candidate| {
+                substitute_all_template_variables_in_str(candidate, &task_variables, options)
+            })
+            .collect();
+        let command =
+            substitute_all_template_variables_in_str(&self.command, &task_variables, options)?;
+        let args = if self.raw_command {
+            Vec::new()
+        } else {
+            substitute_all_template_variables_in_vec(
+                included_args(&self.args, &task_variables)?,
+                &task_variables,
+                options,
+            )?
+        };
+        let wrapper = match &self.wrapper {
+            Some(wrapper) => Some(substitute_all_template_variables_in_vec(
+                wrapper.clone(),
+                &task_variables,
+                options,
+            )?),
+            None => None,
+        };
+        let substitute_optional = |value: &Option<String>| match value.as_deref() {
+            Some(value) => {
+                substitute_all_template_variables_in_str(value, &task_variables, options).map(Some)
+            }
+            None => Some(None),
+        };
+        let shell = substitute_optional(&self.shell)?;
+        let confirm = substitute_optional(&self.confirm)?;
+        let env_file = substitute_optional(&self.env_file)?;
+        let on_success = substitute_optional(&self.on_success)?;
+        let on_failure = substitute_optional(&self.on_failure)?;
+        let env_command = substitute_optional(&self.env_command)?;
+        let log_file = substitute_optional(&self.log_file)?
+            .map(PathBuf::from)
+            .map(|log_file| match cwd.as_deref() {
+                Some(cwd) if log_file.is_relative() => cwd.join(log_file),
+                _ => log_file,
+            });
+        let env_file = match env_file.map(PathBuf::from) {
+            Some(env_file) if env_file.is_relative() => Some(
+                cwd.as_deref()
+                    .map(|cwd| cwd.join(&env_file))
+                    .with_context(|| {
+                        format!("resolving relative env file {env_file:?} of a task without a cwd")
+                    })
+                    .log_err()?,
+            ),
+            env_file => env_file,
+        };
+        let mut env = HashMap::default();
+        env.extend(substitute_all_template_variables_in_map(
+            self.env.clone(),
+            &task_variables,
+            options,
+        )?);
+        env.extend(self.locale_env.clone().into_iter().flatten());
+        env.extend(task_variables);
+        let mut resolved = SpawnInTerminal {
+            id: id.clone(),
+            cwd,
+            full_label: full_label.clone(),
+            label: shortened_label,
+            command,
+            args,
+            env,
+            use_new_terminal: self.use_new_terminal,
+            allow_concurrent_runs: self.allow_concurrent_runs,
+            reveal: self.reveal,
+            echo_command: self.echo_command,
+            confirm,
+            stop_signal: self.stop_signal,
+            stop_timeout_ms: self.stop_timeout_ms,
+            run_as_user: self.run_as_user.clone(),
+            run_as_group: self.run_as_group.clone(),
+            output_mode: self.output_mode,
+            mutex_group: self.mutex_group.clone(),
+            on_success,
+            on_failure,
+            detached: self.detached,
+            create_cwd: self.create_cwd,
+            args_file: self.args_file,
+            force_line_buffered: self.force_line_buffered,
+            log_file,
+            log_append: self.log_append,
+            env_command,
+            output_encoding: self.output_encoding.clone(),
+            terminal_profile: self.terminal_profile.clone(),
+            strip_zed_path_additions: self.strip_zed_path_additions,
+            ready_when: self.ready_when.clone(),
+            path_mode: self.path_mode,
+            raw_command: self.raw_command,
+            shell,
+            scrollback: self.scrollback,
+            env_file,
+            cwd_candidates,
+            reveal_target: options.reveal_target.unwrap_or(self.reveal_target),
+        };
+        if let Some(wrapper) = wrapper {
+            resolved.wrap(wrapper);
+        }
+        Some(ResolvedTask {
+            id,
+            original_task: self.clone(),
+            resolved_label: full_label,
+            resolved: Some(resolved),
+            last_run: None,
+            kind: TaskKind::Spawn,
+        })
+    }
+}
+
+/// Options to adjust the way [`TaskTemplate::resolve_task_with_options`] substitutes the variables and fills the [`SpawnInTerminal`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResolveOptions {
+    /// How to render references to Zed variables, missing in the context.
+    /// When `None`, such references make the resolution fail, as in [`TaskTemplate::resolve_task`].
+    pub unresolved_placeholder: Option<UnresolvedPlaceholder>,
+    /// Where to show this run of the task, e.g. for "run in a center tab" commands.
+    /// When `None`, [`TaskTemplate::reveal_target`] is used.
+    pub reveal_target: Option<RevealTarget>,
+}
+
+/// A replacement for an unresolved Zed variable reference, e.g. to show what's missing in task previews.
+/// Variable modifiers are not applied to the placeholders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnresolvedPlaceholder {
+    /// Replace the reference with an empty string.
+    Empty,
+    /// Keep the reference as a `${ZED_VARIABLE}` token.
+    KeepToken,
+    /// Replace the reference with the bracketed variable name: `⟨ZED_VARIABLE⟩`.
+    Bracketed,
+}
+
+/// A step-by-step record of the substitutions made in the template fields by [`TaskTemplate::resolve_traced`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ResolutionTrace {
+    /// Every field that went through the substitution, in the order of the resolution.
+    pub fields: Vec<FieldTrace>,
+}
+
+/// How a single template field got substituted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FieldTrace {
+    /// The field name, e.g. `command`, `args[1]` or `env[RUST_LOG]`.
+    pub field: String,
+    /// The field value from the template.
+    pub original: String,
+    /// Variable references substituted in the field, in the order of occurrence.
+    pub substitutions: Vec<VariableSubstitution>,
+    /// The field value after the substitution.
+    pub result: String,
+}
+
+/// A single variable reference, substituted in a template field.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct VariableSubstitution {
+    /// The referenced variable name, without the modifier or default value.
+    pub variable: String,
+    /// The text the reference got replaced with.
+    pub value: String,
+}
+
+impl TaskTemplate {
+    /// Same as [`TaskTemplate::resolve_task`], also recording how each field got substituted, to debug complex templates.
+    pub fn resolve_traced(
+        &self,
+        id_base: &str,
+        cx: TaskContext,
+    ) -> Option<(ResolvedTask, ResolutionTrace)> {
+        // Trace the template the resolution actually uses, with the current platform's overrides applied.
+        if !self.platform_overrides.is_empty() {
+            let (mut resolved, trace) = self
+                .clone()
+                .for_platform(Platform::current())
+                .resolve_traced(id_base, cx)?;
+            resolved.original_task = self.clone();
+            return Some((resolved, trace));
+        }
+        let task_variables = cx.task_variables.clone().into_env_variables();
+        let resolved = self.resolve_task(id_base, cx)?;
+        // Only the arguments the resolution kept are traced, with their conditions stripped.
+        let args = if self.raw_command {
+            Vec::new()
+        } else {
+            included_args(&self.args, &task_variables)?
+        };
+
+        let mut fields = vec![("label".to_owned(), self.label.as_str())];
+        if resolved.resolved.is_some() {
+            fields.push(("command".to_owned(), self.command.as_str()));
+            fields.extend(
+                args.iter()
+                    .enumerate()
+                    .map(|(i, arg)| (format!("args[{i}]"), arg.as_str())),
+            );
+            fields.extend(self.cwd.as_deref().map(|cwd| ("cwd".to_owned(), cwd)));
+            fields.extend(
+                self.wrapper
+                    .iter()
+                    .flatten()
+                    .enumerate()
+                    .map(|(i, wrapper)| (format!("wrapper[{i}]"), wrapper.as_str())),
+            );
+            fields.extend(
+                self.shell
+                    .as_deref()
+                    .map(|shell| ("shell".to_owned(), shell)),
+            );
+            fields.extend(
+                self.confirm
+                    .as_deref()
+                    .map(|confirm| ("confirm".to_owned(), confirm)),
+            );
+            fields.extend(
+                self.env_file
+                    .as_deref()
+                    .map(|env_file| ("env_file".to_owned(), env_file)),
+            );
+            fields.extend(
+                self.on_success
+                    .as_deref()
+                    .map(|on_success| ("on_success".to_owned(), on_success)),
+            );
+            fields.extend(
+                self.on_failure
+                    .as_deref()
+                    .map(|on_failure| ("on_failure".to_owned(), on_failure)),
+            );
+            fields.extend(
+                self.log_file
+                    .as_deref()
+                    .map(|log_file| ("log_file".to_owned(), log_file)),
+            );
+            fields.extend(
+                self.context_label
+                    .as_deref()
+                    .map(|context_label| ("context_label".to_owned(), context_label)),
+            );
+            fields.extend(
+                self.env_command
+                    .as_deref()
+                    .map(|env_command| ("env_command".to_owned(), env_command)),
+            );
+            let mut env = self.env.iter().collect::<Vec<_>>();
+            env.sort();
+            for (key, value) in env {
+                if key.contains('$') {
+                    fields.push((format!("env[{key}] name"), key.as_str()));
+                }
+                match value {
+                    EnvValue::String(value) => fields.push((format!("env[{key}]"), value.as_str())),
+                    EnvValue::List(values) | EnvValue::Joined { values, .. } => fields.extend(
+                        values
+                            .iter()
+                            .enumerate()
+                            .map(|(i, value)| (format!("env[{key}][{i}]"), value.as_str())),
+                    ),
+                }
+            }
+        }
+
+        let fields = fields
+            .into_iter()
+            .map(|(field, original)| {
+                let mut substitutions = Vec::new();
+                let result = substitute_variables(
+                    original,
+                    &task_variables,
+                    &ResolveOptions::default(),
+                    Some(&mut substitutions),
+                )?;
+                Some(FieldTrace {
+                    field,
+                    original: original.to_owned(),
+                    substitutions,
+                    result,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((resolved, ResolutionTrace { fields }))
+    }
+
+    /// Same as [`TaskTemplate::resolve_task`], also splitting every resolved argument into the [`ArgSegment`]s it came from,
+    /// e.g. to render the substituted variables as separate chips when editing the resolved command.
+    /// The segments of each argument concatenate into the resolved argument, in the order of the resolved arguments.
+    pub fn resolve_arg_segments(
+        &self,
+        id_base: &str,
+        cx: TaskContext,
+    ) -> Option<(ResolvedTask, Vec<Vec<ArgSegment>>)> {
+        if !self.platform_overrides.is_empty() {
+            let (mut resolved, segments) = self
+                .clone()
+                .for_platform(Platform::current())
+                .resolve_arg_segments(id_base, cx)?;
+            resolved.original_task = self.clone();
+            return Some((resolved, segments));
+        }
+        let task_variables = cx.task_variables.clone().into_env_variables();
+        let resolved = self.resolve_task(id_base, cx)?;
+        let segments = if resolved.resolved.is_some() && !self.raw_command {
+            included_args(&self.args, &task_variables)?
+                .iter()
+                .map(|arg| arg_segments(arg, &task_variables))
+                .collect::<Option<Vec<_>>>()?
+        } else {
+            Vec::new()
+        };
+        Some((resolved, segments))
+    }
+}
+
+/// A part of a resolved argument, see [`TaskTemplate::resolve_arg_segments`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum ArgSegment {
+    /// Text taken from the template as is, including the references that are not task variables, e.g. `$HOME`.
+    Literal(String),
+    /// A task variable reference, substituted.
+    Variable {
+        /// The referenced variable.
+        variable: VariableName,
+        /// The text the reference got replaced with, after the modifiers and offsets applied.
+        value: String,
+    },
+}
+
+/// Splits the argument into the literal text and the variable references, shaped as `shellexpand` parses them, and substitutes the references.
+fn arg_segments(arg: &str, task_variables: &HashMap<String, String>) -> Option<Vec<ArgSegment>> {
+    let mut segments = Vec::new();
+    let push_literal = |segments: &mut Vec<ArgSegment>, text: &str| {
+        if text.is_empty() {
+            return;
+        }
+        match segments.last_mut() {
+            Some(ArgSegment::Literal(literal)) => literal.push_str(text),
+            _ => segments.push(ArgSegment::Literal(text.to_owned())),
+        }
+    };
+    let mut rest = arg;
+    while let Some(start) = rest.find('$') {
+        let (before, reference) = rest.split_at(start);
+        push_literal(&mut segments, before);
+        let reference_len = if reference[1..].starts_with('{') {
+            reference.find('}').map(|end| end + 1)
+        } else {
+            Some(
+                reference[1..]
+                    .find(|c: char| !c.is_alphanumeric() && c != '_')
+                    .map_or(reference.len(), |end| end + 1),
+            )
+        };
+        let Some(reference_len) = reference_len.filter(|&len| len > 1) else {
+            push_literal(&mut segments, &reference[..1]);
+            rest = &reference[1..];
+            continue;
+        };
+        let (reference, after) = reference.split_at(reference_len);
+        let mut substitutions = Vec::new();
+        let value = substitute_variables(
+            reference,
+            task_variables,
+            &ResolveOptions::default(),
+            Some(&mut substitutions),
+        )?;
+        let variable = substitutions
+            .first()
+            .and_then(|substitution| VariableName::from_env_name(&substitution.variable));
+        match variable {
+            Some(variable) => segments.push(ArgSegment::Variable { variable, value }),
+            None => push_literal(&mut segments, &value),
+        }
+        rest = after;
+    }
+    push_literal(&mut segments, rest);
+    Some(segments)
+}
+
+/// The arguments with the conditional ones whose conditions do not hold left out,
+/// `None` if any of the conditions is malformed.
+fn included_args(
+    args: &[TaskArg],
+    task_variables: &HashMap<String, String>,
+) -> Option<Vec<String>> {
+    args.iter()
+        .filter_map(|arg| {
+            let Some(condition) = arg.condition() else {
+                return Some(Ok(arg.value().to_owned()));
+            };
+            match evaluate_condition(condition, task_variables) {
+                Ok(true) => Some(Ok(arg.value().to_owned())),
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()
+        .log_err()
+}
+
+/// The first of the [`SpawnInTerminal::cwd_candidates`] that is an existing directory, with the `${env:NAME}` references
+/// looked up with the `env_var`. Candidates referencing the env variables that are not set, or empty, are skipped.
+pub(crate) fn first_existing_cwd(
+    candidates: &[String],
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Option<PathBuf> {
+    candidates.iter().find_map(|candidate| {
+        let candidate = PathBuf::from(expand_env_references(candidate, &env_var)?);
+        candidate.is_dir().then_some(candidate)
+    })
+}
+
+/// Replaces the `${env:NAME}` references with the values of the env variables, `None` if any of them is not set or empty.
+fn expand_env_references(
+    candidate: &str,
+    env_var: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    let mut expanded = String::with_capacity(candidate.len());
+    let mut rest = candidate;
+    while let Some(start) = rest.find(ENV_REFERENCE_PREFIX) {
+        let (before, reference) = rest.split_at(start);
+        let end = reference.find('}')?;
+        let value = env_var(&reference[ENV_REFERENCE_PREFIX.len()..end])
+            .filter(|value| !value.is_empty())?;
+        expanded.push_str(before);
+        expanded.push_str(&value);
+        rest = &reference[end + 1..];
     }
+    expanded.push_str(rest);
+    Some(expanded)
 }
 
+/// The maximum length of the variable values in the shortened task labels, see [`TaskTemplate::effective_label`].
 const MAX_DISPLAY_VARIABLE_LENGTH: usize = 15;
 
 fn truncate_variables(task_variables: &HashMap<String, String>) -> HashMap<String, String> {
@@ -153,9 +1623,68 @@ fn to_hex_hash(object: impl Serialize) -> anyhow::Result<String> {
     Ok(hex::encode(hasher.finalize()))
 }
 
+/// A transformation of a variable value, applied during the substitution when the variable is referenced as `${VARIABLE:modifier}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VariableModifier {
+    /// Standard base64 encoding, with padding.
+    Base64,
+    /// Percent-encoding of everything except the URL unreserved characters.
+    UrlEncode,
+    /// Quoting for POSIX shells, see [`posix_quote`].
+    ShellQuote,
+    /// A JSON string literal, with the surrounding quotes.
+    Json,
+    /// Path with all backslashes converted into forward slashes, for tools that expect Unix-style paths on Windows.
+    Unix,
+}
+
+impl VariableModifier {
+    fn parse(modifier: &str) -> Option<Self> {
+        match modifier {
+            "base64" => Some(Self::Base64),
+            "urlencode" => Some(Self::UrlEncode),
+            "shellquote" => Some(Self::ShellQuote),
+            "json" => Some(Self::Json),
+            "unix" => Some(Self::Unix),
+            _ => None,
+        }
+    }
+
+    fn apply(self, value: &str) -> String {
+        match self {
+            Self::Base64 => base64::encode(value),
+            Self::UrlEncode => {
+                let mut encoded = String::with_capacity(value.len());
+                for byte in value.bytes() {
+                    if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+                        encoded.push(byte as char);
+                    } else {
+                        encoded.push_str(&format!("%{byte:02X}"));
+                    }
+                }
+                encoded
+            }
+            Self::ShellQuote => posix_quote(value).into_owned(),
+            Self::Json => serde_json_lenient::Value::String(value.to_string()).to_string(),
+            Self::Unix => value.replace('\\', "/"),
+        }
+    }
+}
+
 fn substitute_all_template_variables_in_str(
     template_str: &str,
     task_variables: &HashMap<String, String>,
+    options: &ResolveOptions,
+) -> Option<String> {
+    substitute_variables(template_str, task_variables, options, None)
+}
+
+/// Substitutes the variables in the string, recording every substitution made, if `substitutions` are provided.
+fn substitute_variables(
+    template_str: &str,
+    task_variables: &HashMap<String, String>,
+    options: &ResolveOptions,
+    mut substitutions: Option<&mut Vec<VariableSubstitution>>,
 ) -> Option<String> {
     let substituted_string = shellexpand::env_with_context(&template_str, |var| {
         // Colons denote a default value in case the variable is not set. We want to preserve that default, as otherwise shellexpand will substitute it for us.
@@ -166,12 +1695,35 @@ fn substitute_all_template_variables_in_str(
                 ret.push_str(default);
             }
         };
-        if let Some(mut name) = task_variables.get(variable_name).cloned() {
+        let value = match task_variables.get(variable_name) {
+            Some(value) => Some(value.clone()),
+            None => offset_variable_value(variable_name, task_variables)?.or_else(|| {
+                OPTIONAL_VARIABLES
+                    .iter()
+                    .any(|optional| optional.to_string() == variable_name)
+                    .then(String::new)
+            }),
+        };
+        if let Some(mut name) = value {
             // Got a task variable hit
-            append_previous_default(&mut name);
+            match default.strip_prefix(':').and_then(VariableModifier::parse) {
+                Some(modifier) => name = modifier.apply(&name),
+                None => append_previous_default(&mut name),
+            }
+            if let Some(substitutions) = substitutions.as_deref_mut() {
+                substitutions.push(VariableSubstitution {
+                    variable: variable_name.to_owned(),
+                    value: name.clone(),
+                });
+            }
             return Ok(Some(name));
         } else if variable_name.starts_with(ZED_VARIABLE_NAME_PREFIX) {
-            bail!("Unknown variable name: {}", variable_name);
+            return match options.unresolved_placeholder {
+                Some(UnresolvedPlaceholder::Empty) => Ok(Some(String::new())),
+                Some(UnresolvedPlaceholder::KeepToken) => Ok(Some(format!("${{{var}}}"))),
+                Some(UnresolvedPlaceholder::Bracketed) => Ok(Some(format!("⟨{variable_name}⟩"))),
+                None => bail!("Unknown variable name: {}", variable_name),
+            };
         }
         // This is an unknown variable.
         // We should not error out, as they may come from user environment (e.g. $PATH). That means that the variable substitution might not be perfect.
@@ -186,25 +1738,64 @@ fn substitute_all_template_variables_in_str(
     Some(substituted_string.into_owned())
 }
 
+/// Resolves references like `${ZED_ROW+1}` or `${ZED_ROW-1}`: a known variable name, followed by an integer offset.
+/// Only [`NUMERIC_VARIABLES`] support the offsets, the results are clamped at zero.
+fn offset_variable_value(
+    variable_name: &str,
+    task_variables: &HashMap<String, String>,
+) -> anyhow::Result<Option<String>> {
+    let Some(sign_position) = variable_name.rfind(['+', '-']) else {
+        return Ok(None);
+    };
+    let (base_name, offset) = variable_name.split_at(sign_position);
+    let Some(base_value) = task_variables.get(base_name) else {
+        return Ok(None);
+    };
+    if offset.len() < 2 || !offset[1..].bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(None);
+    }
+    let offset = offset
+        .parse::<i64>()
+        .with_context(|| format!("parsing offset of variable {base_name}"))?;
+    if !NUMERIC_VARIABLES
+        .iter()
+        .any(|numeric| numeric.to_string() == base_name)
+    {
+        bail!("Offsets are not supported for non-numeric variable {base_name}");
+    }
+    let base_value = base_value
+        .parse::<i64>()
+        .with_context(|| format!("parsing numeric variable {base_name} value {base_value:?}"))?;
+    Ok(Some(base_value.saturating_add(offset).max(0).to_string()))
+}
+
 fn substitute_all_template_variables_in_vec(
     mut template_strs: Vec<String>,
     task_variables: &HashMap<String, String>,
+    options: &ResolveOptions,
 ) -> Option<Vec<String>> {
     for variable in template_strs.iter_mut() {
-        let new_value = substitute_all_template_variables_in_str(&variable, task_variables)?;
+        let new_value =
+            substitute_all_template_variables_in_str(&variable, task_variables, options)?;
         *variable = new_value;
     }
     Some(template_strs)
 }
 
 fn substitute_all_template_variables_in_map(
-    keys_and_values: HashMap<String, String>,
+    keys_and_values: HashMap<String, EnvValue>,
     task_variables: &HashMap<String, String>,
+    options: &ResolveOptions,
 ) -> Option<HashMap<String, String>> {
     let mut new_map: HashMap<String, String> = Default::default();
     for (key, value) in keys_and_values {
-        let new_value = substitute_all_template_variables_in_str(&value, task_variables)?;
-        let new_key = substitute_all_template_variables_in_str(&key, task_variables)?;
+        let new_parts = substitute_all_template_variables_in_vec(
+            value.parts().to_vec(),
+            task_variables,
+            options,
+        )?;
+        let new_value = value.join(&new_parts, cfg!(windows));
+        let new_key = substitute_all_template_variables_in_str(&key, task_variables, options)?;
         new_map.insert(new_key, new_value);
     }
     Some(new_map)
@@ -214,19 +1805,37 @@ fn substitute_all_template_variables_in_map(
 mod tests {
     use std::{borrow::Cow, path::Path};
 
-    use crate::{TaskVariables, VariableName};
+    use crate::{
+        prev_output_value, visual_column, GitInfo, TaskVariables, VariableName,
+        MAX_PREV_OUTPUT_LENGTH,
+    };
 
     use super::*;
 
     const TEST_ID_BASE: &str = "test_base";
 
+    fn resolve_args(template: &TaskTemplate, task_variables: TaskVariables) -> Vec<String> {
+        template
+            .resolve_task(
+                TEST_ID_BASE,
+                TaskContext {
+                    cwd: None,
+                    task_variables,
+                },
+            )
+            .unwrap()
+            .resolved
+            .unwrap()
+            .args
+    }
+
     #[test]
     fn test_resolving_templates_with_blank_command_and_label() {
         let task_with_all_properties = TaskTemplate {
             label: "test_label".to_string(),
             command: "test_command".to_string(),
-            args: vec!["test_arg".to_string()],
-            env: HashMap::from_iter([("test_env_key".to_string(), "test_env_var".to_string())]),
+            args: vec!["test_arg".into()],
+            env: HashMap::from_iter([("test_env_key".to_string(), "test_env_var".into())]),
             ..TaskTemplate::default()
         };
 
@@ -370,15 +1979,15 @@ mod tests {
                 VariableName::Symbol.template_value(),
             ),
             args: vec![
-                format!("arg1 {}", VariableName::SelectedText.template_value()),
-                format!("arg2 {}", VariableName::Column.template_value()),
-                format!("arg3 {}", VariableName::Symbol.template_value()),
+                format!("arg1 {}", VariableName::SelectedText.template_value()).into(),
+                format!("arg2 {}", VariableName::Column.template_value()).into(),
+                format!("arg3 {}", VariableName::Symbol.template_value()).into(),
             ],
             env: HashMap::from_iter([
-                ("test_env_key".to_string(), "test_env_var".to_string()),
+                ("test_env_key".to_string(), "test_env_var".into()),
                 (
                     "env_key_1".to_string(),
-                    VariableName::WorktreeRoot.template_value(),
+                    VariableName::WorktreeRoot.template_value().into(),
                 ),
                 (
                     "env_key_2".to_string(),
@@ -386,11 +1995,12 @@ mod tests {
                         "env_var_2_{}_{}",
                         custom_variable_1.template_value(),
                         custom_variable_2.template_value()
-                    ),
+                    )
+                    .into(),
                 ),
                 (
                     "env_key_3".to_string(),
-                    format!("env_var_3_{}", VariableName::Symbol.template_value()),
+                    format!("env_var_3_{}", VariableName::Symbol.template_value()).into(),
                 ),
             ]),
             ..TaskTemplate::default()
@@ -488,33 +2098,2343 @@ mod tests {
     }
 
     #[test]
-    fn test_can_resolve_free_variables() {
-        let task = TaskTemplate {
-            label: "My task".into(),
-            command: "echo".into(),
-            args: vec!["$PATH".into()],
-            ..Default::default()
+    fn test_inheritance_merges_env() {
+        let templates = TaskTemplates(vec![
+            TaskTemplate {
+                label: "child".to_string(),
+                env: HashMap::from_iter([
+                    ("RUST_LOG".to_string(), "debug".into()),
+                    ("PROFILE".to_string(), "child".into()),
+                ]),
+                extends: Some("parent".to_string()),
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "parent".to_string(),
+                env: HashMap::from_iter([
+                    ("PROFILE".to_string(), "parent".into()),
+                    ("CARGO_TARGET_DIR".to_string(), "target/parent".into()),
+                ]),
+                extends: Some("base".to_string()),
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "base".to_string(),
+                command: "cargo".to_string(),
+                args: vec!["build".into()],
+                env: HashMap::from_iter([
+                    ("PATH".to_string(), "/opt/tools/bin:$PATH".into()),
+                    ("PROFILE".to_string(), "base".into()),
+                ]),
+                ..TaskTemplate::default()
+            },
+        ]);
+
+        let resolved = templates.resolve_inheritance();
+        let child = &resolved.0[0];
+        assert_eq!(child.extends, None);
+        assert_eq!(child.command, "cargo");
+        assert_eq!(child.args, vec!["build".into()]);
+        assert_eq!(
+            child.env,
+            HashMap::from_iter([
+                ("RUST_LOG".to_string(), "debug".into()),
+                ("PATH".to_string(), "/opt/tools/bin:$PATH".into()),
+                ("CARGO_TARGET_DIR".to_string(), "target/parent".into()),
+                ("PROFILE".to_string(), "child".into()),
+            ]),
+            "Env should be merged across the whole chain, with the explicit child keys winning"
+        );
+        assert_eq!(
+            resolved.0[1].env.get("PROFILE"),
+            Some(&EnvValue::from("parent"))
+        );
+        assert_eq!(resolved.0[2], templates.0[2]);
+    }
+
+    #[test]
+    fn test_cyclic_and_unknown_inheritance() {
+        let templates = TaskTemplates(vec![
+            TaskTemplate {
+                label: "a".to_string(),
+                command: "echo a".to_string(),
+                env: HashMap::from_iter([("A".to_string(), "a".into())]),
+                extends: Some("b".to_string()),
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "b".to_string(),
+                command: "echo b".to_string(),
+                env: HashMap::from_iter([("B".to_string(), "b".into())]),
+                extends: Some("a".to_string()),
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "orphan".to_string(),
+                command: "echo orphan".to_string(),
+                extends: Some("missing".to_string()),
+                ..TaskTemplate::default()
+            },
+        ]);
+
+        let resolved = templates.resolve_inheritance();
+        assert_eq!(resolved.0[0].command, "echo a");
+        assert_eq!(resolved.0[0].env.len(), 2);
+        assert_eq!(resolved.0[1].command, "echo b");
+        assert_eq!(resolved.0[1].env.len(), 2);
+        assert_eq!(
+            resolved.0[2],
+            TaskTemplate {
+                extends: None,
+                ..templates.0[2].clone()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stable_sort_of_merged_sources() {
+        let source = |labels: &[&str], source_priority| {
+            TaskTemplates(
+                labels
+                    .iter()
+                    .map(|label| TaskTemplate {
+                        label: label.to_string(),
+                        command: "echo".to_string(),
+                        ..TaskTemplate::default()
+                    })
+                    .collect(),
+            )
+            .with_source_priority(source_priority)
         };
-        let resolved = task
-            .resolve_task(TEST_ID_BASE, TaskContext::default())
+        let merge = |sources: Vec<TaskTemplates>| {
+            let mut merged = TaskTemplates(sources.into_iter().flat_map(|s| s.0).collect());
+            merged.stable_sort();
+            merged
+        };
+        let global = source(&["b", "a"], 1);
+        let worktree = source(&["d", "c"], 0);
+        let unordered = TaskTemplates(vec![TaskTemplate {
+            label: "oneshot".to_string(),
+            ..TaskTemplate::default()
+        }]);
+
+        let merged = merge(vec![global.clone(), unordered.clone(), worktree.clone()]);
+        assert_eq!(
+            merged,
+            merge(vec![worktree, unordered, global]),
+            "Merging the sources in different order should produce the same result"
+        );
+        let labels = merged
+            .0
+            .iter()
+            .map(|template| template.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["d", "c", "b", "a", "oneshot"]);
+    }
+
+    #[test]
+    fn test_echo_command_defaults() {
+        let template: TaskTemplate =
+            serde_json_lenient::from_str(r#"{"label": "echo", "command": "echo"}"#).unwrap();
+        assert!(!template.echo_command);
+        let resolved = TaskTemplate {
+            echo_command: true,
+            ..template
+        }
+        .resolve_task(TEST_ID_BASE, TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert!(resolved.echo_command);
+        assert_eq!(resolved.command_echo().as_deref(), Some("echo"));
+    }
+
+    #[test]
+    fn test_variable_encoding_modifiers() {
+        let resolve_arg = |selected_text: &str, arg: &str| {
+            TaskTemplate {
+                label: "encode".to_string(),
+                command: "echo".to_string(),
+                args: vec![arg.into()],
+                ..TaskTemplate::default()
+            }
+            .resolve_task(
+                TEST_ID_BASE,
+                TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([(
+                        VariableName::SelectedText,
+                        selected_text.to_string(),
+                    )]),
+                },
+            )
+            .unwrap()
+            .resolved
+            .unwrap()
+            .args
+            .remove(0)
+        };
+
+        for (selected_text, modifier, expected) in [
+            ("hello world", "base64", "aGVsbG8gd29ybGQ="),
+            ("", "base64", ""),
+            ("héllo", "base64", "aMOpbGxv"),
+            ("a b&c=d/é", "urlencode", "a%20b%26c%3Dd%2F%C3%A9"),
+            ("", "urlencode", ""),
+            ("it's $HOME", "shellquote", r#"'it'\''s $HOME'"#),
+            ("", "shellquote", "''"),
+            ("plain", "shellquote", "plain"),
+            ("say \"hi\"\n", "json", r#""say \"hi\"\n""#),
+            ("", "json", r#""""#),
+            ("日本", "json", r#""日本""#),
+        ] {
+            assert_eq!(
+                resolve_arg(
+                    selected_text,
+                    &format!("${{{}:{modifier}}}", VariableName::SelectedText)
+                ),
+                expected,
+                "Unexpected result of applying `{modifier}` to {selected_text:?}"
+            );
+        }
+        assert_eq!(
+            resolve_arg("text", "prefix_${ZED_SELECTED_TEXT:base64}_suffix"),
+            "prefix_dGV4dA==_suffix"
+        );
+    }
+
+    #[test]
+    fn test_confirmation_message_resolution() {
+        let deploy_task = TaskTemplate {
+            label: "deploy".to_string(),
+            command: "deploy".to_string(),
+            ..TaskTemplate::default()
+        };
+        let task_cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(
+                VariableName::Custom(Cow::Borrowed("ENV")),
+                "production".to_string(),
+            )]),
+        };
+        let resolved = deploy_task
+            .resolve_task(TEST_ID_BASE, task_cx.clone())
             .unwrap()
             .resolved
             .unwrap();
-        assert_eq!(resolved.label, task.label);
-        assert_eq!(resolved.command, task.command);
-        assert_eq!(resolved.args, task.args);
+        assert_eq!(
+            resolved.confirm, None,
+            "No confirmation should be asked by default"
+        );
+
+        let confirmed_deploy_task = TaskTemplate {
+            confirm: Some("Deploy to ${ZED_CUSTOM_ENV}?".to_string()),
+            ..deploy_task
+        };
+        let resolved = confirmed_deploy_task
+            .resolve_task(TEST_ID_BASE, task_cx)
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(resolved.confirm.as_deref(), Some("Deploy to production?"));
+        assert_eq!(
+            confirmed_deploy_task.resolve_task(TEST_ID_BASE, TaskContext::default()),
+            None,
+            "Confirmation message with unknown Zed variables should fail the resolution"
+        );
     }
 
     #[test]
-    fn test_errors_on_missing_zed_variable() {
+    fn test_unix_path_modifier() {
         let task = TaskTemplate {
-            label: "My task".into(),
-            command: "echo".into(),
-            args: vec!["$ZED_VARIABLE".into()],
-            ..Default::default()
+            label: "git add".to_string(),
+            command: "git".to_string(),
+            args: vec!["add".into(), "${ZED_FILE:unix}".into()],
+            ..TaskTemplate::default()
         };
-        assert!(task
-            .resolve_task(TEST_ID_BASE, TaskContext::default())
-            .is_none());
+        let resolve_file_arg = |file: &str| {
+            resolve_args(
+                &task,
+                TaskVariables::from_iter([(VariableName::File, file.to_string())]),
+            )
+            .pop()
+            .unwrap()
+        };
+        assert_eq!(
+            resolve_file_arg(r"C:\Users\me\project\src\main.rs"),
+            "C:/Users/me/project/src/main.rs"
+        );
+        assert_eq!(
+            resolve_file_arg("/home/me/project/src/main.rs"),
+            "/home/me/project/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_tab_width_substitution() {
+        let task = TaskTemplate {
+            label: "format".to_string(),
+            command: "prettier".to_string(),
+            args: vec![format!("--tab-width={}", VariableName::TabWidth.template_value()).into()],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            resolve_args(
+                &task,
+                TaskVariables::from_iter([(VariableName::TabWidth, "2".to_string())])
+            ),
+            vec!["--tab-width=2"]
+        );
+        assert_eq!(
+            resolve_args(&task, TaskVariables::default()),
+            vec!["--tab-width="],
+            "Unknown tab width should resolve to an empty string"
+        );
+    }
+
+    #[test]
+    fn test_numeric_variable_offsets() {
+        let resolve_arg = |arg: &str| {
+            TaskTemplate {
+                label: "run from line".to_string(),
+                command: "run".to_string(),
+                args: vec![arg.into()],
+                ..TaskTemplate::default()
+            }
+            .resolve_task(
+                TEST_ID_BASE,
+                TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([
+                        (VariableName::Row, "1".to_string()),
+                        (VariableName::Column, "10".to_string()),
+                        (VariableName::Symbol, "main".to_string()),
+                    ]),
+                },
+            )
+            .map(|resolved_task| resolved_task.resolved.unwrap().args.pop().unwrap())
+        };
+        assert_eq!(
+            resolve_arg("--line=${ZED_ROW+1}").as_deref(),
+            Some("--line=2")
+        );
+        assert_eq!(
+            resolve_arg("--line=${ZED_ROW-1}").as_deref(),
+            Some("--line=0")
+        );
+        assert_eq!(
+            resolve_arg("--column=${ZED_COLUMN-1}").as_deref(),
+            Some("--column=9")
+        );
+        assert_eq!(
+            resolve_arg("--line=${ZED_ROW-5}").as_deref(),
+            Some("--line=0"),
+            "Negative rows should be clamped at zero"
+        );
+        assert_eq!(
+            resolve_arg("${ZED_ROW:-1}").as_deref(),
+            Some("1"),
+            "Default values should not be treated as offsets"
+        );
+        assert_eq!(
+            resolve_arg("${ZED_SYMBOL+1}"),
+            None,
+            "Offsets of non-numeric variables should fail the resolution"
+        );
+    }
+
+    #[test]
+    fn test_env_file_resolution() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(".envrc"),
+            "export RUST_LOG=debug\nDATABASE_URL=\"postgres://${HOST}/db\"\nOVERRIDDEN=file\n",
+        )
+        .unwrap();
+        let task = TaskTemplate {
+            label: "run".to_string(),
+            command: "cargo".to_string(),
+            env: HashMap::from_iter([("OVERRIDDEN".to_string(), "template".into())]),
+            env_file: Some(".envrc".to_string()),
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: Some(dir.path().to_path_buf()),
+            task_variables: TaskVariables::default(),
+        };
+
+        let mut spawn = task
+            .resolve_task(TEST_ID_BASE, context.clone())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.env_file, Some(dir.path().join(".envrc")));
+        assert_eq!(
+            spawn.env.get("RUST_LOG"),
+            None,
+            "The env file should not be read during the resolution"
+        );
+        spawn.apply_env_file().unwrap();
+        let env = spawn.env;
+        assert_eq!(env.get("RUST_LOG").map(String::as_str), Some("debug"));
+        assert_eq!(
+            env.get("DATABASE_URL").map(String::as_str),
+            Some("postgres://${HOST}/db"),
+            "Env file values should not be expanded"
+        );
+        assert_eq!(
+            env.get("OVERRIDDEN").map(String::as_str),
+            Some("template"),
+            "Template env should override the env file"
+        );
+
+        std::fs::write(dir.path().join(".envrc"), "RUST_LOG=trace\n").unwrap();
+        let mut spawn = task
+            .resolve_task(TEST_ID_BASE, context.clone())
+            .unwrap()
+            .resolved
+            .unwrap();
+        spawn.apply_env_file().unwrap();
+        assert_eq!(
+            spawn.env["RUST_LOG"], "trace",
+            "The env file changes should be picked up on the next spawn"
+        );
+
+        let missing_env_file = TaskTemplate {
+            env_file: Some("missing.env".to_string()),
+            ..task.clone()
+        };
+        let mut spawn = missing_env_file
+            .resolve_task(TEST_ID_BASE, context)
+            .unwrap()
+            .resolved
+            .unwrap();
+        let env_before = spawn.env.clone();
+        assert!(
+            spawn.apply_env_file().is_err(),
+            "Unreadable env files should fail the spawn"
+        );
+        assert_eq!(spawn.env, env_before);
+
+        assert_eq!(
+            task.resolve_task(TEST_ID_BASE, TaskContext::default()),
+            None,
+            "Relative env files should not resolve against the process cwd"
+        );
+    }
+
+    #[test]
+    fn test_action_templates_resolve_without_spawning() {
+        let action_task = TaskTemplate {
+            label: "Open settings for ${ZED_CUSTOM_PROJECT}".to_string(),
+            action: Some("zed::OpenSettings".to_string()),
+            ..TaskTemplate::default()
+        };
+        assert!(action_task.is_runnable());
+        let resolved = action_task
+            .resolve_task(
+                TEST_ID_BASE,
+                TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([(
+                        VariableName::Custom(Cow::Borrowed("PROJECT")),
+                        "zed".to_string(),
+                    )]),
+                },
+            )
+            .unwrap();
+        assert_eq!(resolved.resolved, None);
+        assert_eq!(
+            resolved.kind,
+            TaskKind::Action("zed::OpenSettings".to_string())
+        );
+        assert_eq!(resolved.resolved_label, "Open settings for zed");
+
+        let command_task = TaskTemplate {
+            label: "echo".to_string(),
+            command: "echo".to_string(),
+            ..TaskTemplate::default()
+        };
+        let resolved = command_task
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap();
+        assert!(resolved.resolved.is_some());
+        assert_eq!(resolved.kind, TaskKind::Spawn);
+
+        let blank_action_label = TaskTemplate {
+            label: " ".to_string(),
+            ..action_task
+        };
+        assert_eq!(
+            blank_action_label.resolve_task(TEST_ID_BASE, TaskContext::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_stop_signal_serde() {
+        let parse_signal = |signal: &str| {
+            serde_json_lenient::from_str::<TaskTemplate>(&format!(
+                r#"{{"label": "test", "command": "test", "stop_signal": {signal}}}"#
+            ))
+            .map(|template| template.stop_signal)
+        };
+        for (signal, expected) in [
+            (r#""term""#, StopSignal::Term),
+            (r#""SIGTERM""#, StopSignal::Term),
+            (r#""int""#, StopSignal::Int),
+            (r#""SIGINT""#, StopSignal::Int),
+            (r#""kill""#, StopSignal::Kill),
+            ("9", StopSignal::Kill),
+            ("10", StopSignal::Number(10)),
+        ] {
+            assert_eq!(parse_signal(signal).unwrap(), Some(expected), "{signal}");
+            let serialized = serde_json_lenient::to_string(&expected).unwrap();
+            assert_eq!(
+                serde_json_lenient::from_str::<StopSignal>(&serialized).unwrap(),
+                expected
+            );
+        }
+        assert_eq!(
+            serde_json_lenient::to_string(&StopSignal::Kill).unwrap(),
+            r#""kill""#
+        );
+        assert_eq!(
+            serde_json_lenient::to_string(&StopSignal::Number(10)).unwrap(),
+            "10"
+        );
+        for invalid_signal in [r#""hup?""#, "0", "-9", "1000"] {
+            assert!(parse_signal(invalid_signal).is_err(), "{invalid_signal}");
+        }
+
+        let default_template =
+            serde_json_lenient::from_str::<TaskTemplate>(r#"{"label": "test", "command": "test"}"#)
+                .unwrap();
+        assert_eq!(default_template.stop_signal, None);
+        assert_eq!(default_template.stop_timeout_ms, None);
+        assert_eq!(StopSignal::default(), StopSignal::Term);
+        assert!(StopSignal::default().is_graceful());
+        assert!(!StopSignal::Kill.is_graceful());
+
+        let resolved = TaskTemplate {
+            stop_signal: Some(StopSignal::Int),
+            stop_timeout_ms: Some(500),
+            ..default_template
+        }
+        .resolve_task(TEST_ID_BASE, TaskContext::default())
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert_eq!(resolved.stop_signal, Some(StopSignal::Int));
+        assert_eq!(resolved.stop_timeout_ms, Some(500));
+    }
+
+    #[test]
+    fn test_diagnostic_variables_substitution() {
+        let task = TaskTemplate {
+            label: "explain".to_string(),
+            command: "explain".to_string(),
+            args: vec![
+                VariableName::DiagnosticCode.template_value().into(),
+                VariableName::DiagnosticMessage.template_value().into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            resolve_args(
+                &task,
+                TaskVariables::from_iter([
+                    (VariableName::DiagnosticCode, "E0308".to_string()),
+                    (
+                        VariableName::DiagnosticMessage,
+                        "mismatched types".to_string()
+                    ),
+                ])
+            ),
+            vec!["E0308", "mismatched types"]
+        );
+        assert_eq!(
+            resolve_args(&task, TaskVariables::default()),
+            vec!["", ""],
+            "Missing diagnostic should resolve to empty strings"
+        );
+    }
+
+    #[test]
+    fn test_previous_exit_code_substitution() {
+        let task = TaskTemplate {
+            label: "report".to_string(),
+            command: "report".to_string(),
+            args: vec![format!("--status={}", VariableName::PrevExitCode.template_value()).into()],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            resolve_args(
+                &task,
+                TaskVariables::from_iter([(VariableName::PrevExitCode, "1".to_string())])
+            ),
+            vec!["--status=1"]
+        );
+        assert_eq!(
+            resolve_args(&task, TaskVariables::default()),
+            vec!["--status="],
+            "Standalone tasks should get an empty exit code"
+        );
+    }
+
+    #[test]
+    fn test_mutex_group_shared_between_templates() {
+        let templates = serde_json_lenient::from_str::<TaskTemplates>(
+            r#"[
+                {"label": "build", "command": "cargo", "args": ["build"], "mutex_group": "target"},
+                {"label": "clean", "command": "cargo", "args": ["clean"], "mutex_group": "target"},
+                {"label": "check", "command": "cargo", "args": ["check"]}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            templates
+                .0
+                .iter()
+                .map(|template| template.mutex_group.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("target"), Some("target"), None]
+        );
+        let serialized = serde_json_lenient::to_string(&templates.0[0]).unwrap();
+        assert_eq!(
+            serde_json_lenient::from_str::<TaskTemplate>(&serialized).unwrap(),
+            templates.0[0]
+        );
+
+        let resolved = templates
+            .0
+            .iter()
+            .map(|template| {
+                template
+                    .resolve_task("test", TaskContext::default())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_ne!(resolved[0].id, resolved[1].id);
+        let spawn_groups = resolved
+            .iter()
+            .map(|task| task.resolved.as_ref().unwrap().mutex_group.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(spawn_groups, vec![Some("target"), Some("target"), None]);
+        assert_ne!(
+            templates.0[0].content_hash(),
+            TaskTemplate {
+                mutex_group: None,
+                ..templates.0[0].clone()
+            }
+            .content_hash(),
+        );
+    }
+
+    #[test]
+    fn test_resolution_trace() {
+        let template = TaskTemplate {
+            label: "test ${ZED_SYMBOL}".to_string(),
+            command: "cargo".to_string(),
+            args: vec![
+                "test".into(),
+                "$ZED_SYMBOL".into(),
+                "--".into(),
+                "--line=${ZED_ROW+1}".into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::Symbol, "my_test".to_string()),
+                (VariableName::Row, "41".to_string()),
+            ]),
+        };
+        let (resolved, trace) = template.resolve_traced(TEST_ID_BASE, cx.clone()).unwrap();
+        assert_eq!(
+            Some(resolved),
+            template.resolve_task(TEST_ID_BASE, cx),
+            "Tracing should not affect the resolution"
+        );
+        assert_eq!(
+            trace
+                .fields
+                .iter()
+                .map(|field| field.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["label", "command", "args[0]", "args[1]", "args[2]", "args[3]"]
+        );
+        assert_eq!(
+            trace.fields[3],
+            FieldTrace {
+                field: "args[1]".to_string(),
+                original: "$ZED_SYMBOL".to_string(),
+                substitutions: vec![VariableSubstitution {
+                    variable: "ZED_SYMBOL".to_string(),
+                    value: "my_test".to_string(),
+                }],
+                result: "my_test".to_string(),
+            }
+        );
+        assert_eq!(
+            trace.fields[5].substitutions,
+            vec![VariableSubstitution {
+                variable: "ZED_ROW+1".to_string(),
+                value: "42".to_string(),
+            }]
+        );
+        assert_eq!(trace.fields[5].result, "--line=42");
+        assert!(trace.fields[1].substitutions.is_empty());
+    }
+
+    #[test]
+    fn test_list_env_values() {
+        let template = serde_json_lenient::from_str::<TaskTemplate>(
+            r#"{
+                "label": "test",
+                "command": "cargo",
+                "env": {
+                    "PLAIN": "${ZED_WORKTREE_ROOT}",
+                    "EXTRA_PATHS": ["${ZED_WORKTREE_ROOT}/bin", "/usr/local/bin"],
+                    "FEATURES": {"values": ["a", "$ZED_CUSTOM_FEATURE"], "separator": ","}
+                }
+            }"#,
+        )
+        .unwrap();
+        let list = EnvValue::List(vec![
+            "${ZED_WORKTREE_ROOT}/bin".to_string(),
+            "/usr/local/bin".to_string(),
+        ]);
+        assert_eq!(template.env.get("EXTRA_PATHS"), Some(&list));
+        assert_eq!(
+            template.env.get("FEATURES"),
+            Some(&EnvValue::Joined {
+                values: vec!["a".to_string(), "$ZED_CUSTOM_FEATURE".to_string()],
+                separator: ",".to_string(),
+            })
+        );
+
+        let parts = ["/a".to_string(), "/b".to_string()];
+        assert_eq!(list.join(&parts, false), "/a:/b");
+        assert_eq!(list.join(&parts, true), "/a;/b");
+
+        let cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::WorktreeRoot, "/project".to_string()),
+                (
+                    VariableName::Custom(Cow::Borrowed("FEATURE")),
+                    "b".to_string(),
+                ),
+            ]),
+        };
+        let env = template
+            .resolve_task(TEST_ID_BASE, cx)
+            .unwrap()
+            .resolved
+            .unwrap()
+            .env;
+        let separator = if cfg!(windows) { ";" } else { ":" };
+        assert_eq!(env.get("PLAIN").map(String::as_str), Some("/project"));
+        assert_eq!(
+            env.get("EXTRA_PATHS"),
+            Some(&format!("/project/bin{separator}/usr/local/bin"))
+        );
+        assert_eq!(env.get("FEATURES").map(String::as_str), Some("a,b"));
+    }
+
+    #[test]
+    fn test_completion_commands_substitution() {
+        let template = serde_json_lenient::from_str::<TaskTemplate>(
+            r#"{"label": "build", "command": "cargo", "args": ["build"]}"#,
+        )
+        .unwrap();
+        assert_eq!(template.on_success, None);
+        assert_eq!(template.on_failure, None);
+        let spawn = template
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.on_success, None);
+        assert_eq!(spawn.on_failure, None);
+
+        let template = TaskTemplate {
+            on_success: Some("notify-send 'built ${ZED_FILE}'".to_string()),
+            on_failure: Some("notify-send 'failed at ${ZED_ROW}'".to_string()),
+            ..template
+        };
+        let cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::File, "main.rs".to_string()),
+                (VariableName::Row, "7".to_string()),
+            ]),
+        };
+        let spawn = template
+            .resolve_task(TEST_ID_BASE, cx)
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(
+            spawn.on_success.as_deref(),
+            Some("notify-send 'built main.rs'")
+        );
+        assert_eq!(
+            spawn.on_failure.as_deref(),
+            Some("notify-send 'failed at 7'")
+        );
+
+        let template = TaskTemplate {
+            on_failure: Some("echo $ZED_SYMBOL".to_string()),
+            ..template
+        };
+        assert_eq!(
+            template.resolve_task(TEST_ID_BASE, TaskContext::default()),
+            None,
+            "Completion commands should fail the resolution on unknown variables, as other fields do"
+        );
+    }
+
+    #[test]
+    fn test_unresolved_placeholders() {
+        let template = TaskTemplate {
+            label: "test ${ZED_SYMBOL}".to_string(),
+            command: "cargo".to_string(),
+            args: vec![
+                "test".into(),
+                "$ZED_FILE".into(),
+                "--".into(),
+                "${ZED_SYMBOL:shellquote}".into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(VariableName::File, "main.rs".to_string())]),
+        };
+        assert_eq!(
+            template.resolve_task(TEST_ID_BASE, cx.clone()),
+            None,
+            "Missing variables should fail the resolution by default"
+        );
+
+        for (placeholder, expected_label, expected_last_arg) in [
+            (UnresolvedPlaceholder::Empty, "test ", ""),
+            (
+                UnresolvedPlaceholder::KeepToken,
+                "test ${ZED_SYMBOL}",
+                "${ZED_SYMBOL:shellquote}",
+            ),
+            (
+                UnresolvedPlaceholder::Bracketed,
+                "test ⟨ZED_SYMBOL⟩",
+                "⟨ZED_SYMBOL⟩",
+            ),
+        ] {
+            let options = ResolveOptions {
+                unresolved_placeholder: Some(placeholder),
+                ..ResolveOptions::default()
+            };
+            let resolved = template
+                .resolve_task_with_options(TEST_ID_BASE, cx.clone(), &options)
+                .unwrap_or_else(|| panic!("Failed to resolve with {placeholder:?}"));
+            assert_eq!(resolved.resolved_label, expected_label, "{placeholder:?}");
+            let spawn = resolved.resolved.unwrap();
+            assert_eq!(
+                spawn.args,
+                vec![
+                    "test".to_string(),
+                    "main.rs".to_string(),
+                    "--".to_string(),
+                    expected_last_arg.to_string(),
+                ],
+                "{placeholder:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tag_counts() {
+        let tagged = |label: &str, tags: &[&str]| TaskTemplate {
+            label: label.to_string(),
+            command: "cargo".to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..TaskTemplate::default()
+        };
+        let templates = TaskTemplates(vec![
+            tagged("build", &["build", "Rust"]),
+            tagged("test", &["test", "rust", "RUST"]),
+            tagged("bench", &["Build", "bench"]),
+            tagged("clean", &[]),
+            tagged("fmt", &[" "]),
+        ]);
+        assert_eq!(
+            templates.tag_counts(),
+            TagCounts {
+                by_tag: BTreeMap::from_iter([
+                    ("bench".to_string(), 1),
+                    ("build".to_string(), 2),
+                    ("rust".to_string(), 2),
+                    ("test".to_string(), 1),
+                ]),
+                untagged: 2,
+            }
+        );
+        assert_eq!(TaskTemplates::default().tag_counts(), TagCounts::default());
+    }
+
+    #[test]
+    fn test_previous_output_substitution() {
+        let task = TaskTemplate {
+            label: "publish".to_string(),
+            command: "publish".to_string(),
+            args: vec![format!("--version={}", VariableName::PrevOutput.template_value()).into()],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            resolve_args(
+                &task,
+                TaskVariables::from_iter([(
+                    VariableName::PrevOutput,
+                    prev_output_value("  1.2.3\n")
+                )])
+            ),
+            vec!["--version=1.2.3"]
+        );
+        assert_eq!(
+            resolve_args(&task, TaskVariables::default()),
+            vec!["--version="],
+            "Standalone tasks should get an empty previous output"
+        );
+
+        let large_output = "é".repeat(MAX_PREV_OUTPUT_LENGTH);
+        let truncated = prev_output_value(&large_output);
+        assert!(truncated.len() <= MAX_PREV_OUTPUT_LENGTH);
+        assert!(large_output.starts_with(&truncated));
+        assert!(!truncated.is_empty());
+    }
+
+    #[test]
+    fn test_creating_missing_cwd() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let template = TaskTemplate {
+            label: "bundle".to_string(),
+            command: "bundle".to_string(),
+            cwd: Some(format!(
+                "{}/out/${{ZED_CUSTOM_TARGET}}",
+                temp_dir.path().display()
+            )),
+            ..TaskTemplate::default()
+        };
+        let resolve = |template: &TaskTemplate| {
+            template
+                .resolve_task(
+                    TEST_ID_BASE,
+                    TaskContext {
+                        cwd: None,
+                        task_variables: TaskVariables::from_iter([(
+                            VariableName::Custom(Cow::Borrowed("TARGET")),
+                            "web".to_string(),
+                        )]),
+                    },
+                )
+                .unwrap()
+                .resolved
+                .unwrap()
+        };
+        let expected_cwd = temp_dir.path().join("out").join("web");
+
+        let spawn = resolve(&template);
+        assert_eq!(spawn.cwd.as_deref(), Some(expected_cwd.as_path()));
+        assert!(
+            !expected_cwd.exists(),
+            "Resolution should not create the cwd"
+        );
+        let error = spawn.ensure_cwd().unwrap_err();
+        assert!(
+            error.to_string().contains("does not exist"),
+            "Unexpected error: {error}"
+        );
+        assert!(!expected_cwd.exists());
+
+        let spawn = resolve(&TaskTemplate {
+            create_cwd: true,
+            ..template
+        });
+        assert!(
+            !expected_cwd.exists(),
+            "Resolution should not create the cwd"
+        );
+        spawn.ensure_cwd().unwrap();
+        assert!(expected_cwd.is_dir());
+        spawn.ensure_cwd().unwrap();
+    }
+
+    #[test]
+    fn test_language_substitution() {
+        let task = TaskTemplate {
+            label: "lint ${ZED_LANGUAGE}".to_string(),
+            command: "lint".to_string(),
+            args: vec![format!("--language={}", VariableName::Language.template_value()).into()],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(VariableName::Language.template_value(), "$ZED_LANGUAGE");
+        let resolve = |task_variables: TaskVariables| {
+            task.resolve_task(
+                TEST_ID_BASE,
+                TaskContext {
+                    cwd: None,
+                    task_variables,
+                },
+            )
+            .unwrap()
+        };
+        let resolved = resolve(TaskVariables::from_iter([(
+            VariableName::Language,
+            "rust".to_string(),
+        )]));
+        assert_eq!(resolved.resolved_label, "lint rust");
+        assert_eq!(resolved.resolved.unwrap().args, vec!["--language=rust"]);
+
+        let resolved = resolve(TaskVariables::default());
+        assert_eq!(
+            resolved.resolved_label, "lint ",
+            "Unknown language should resolve to an empty string"
+        );
+        assert_eq!(resolved.resolved.unwrap().args, vec!["--language="]);
+    }
+
+    #[test]
+    fn test_grouping_by_category() {
+        let template = |label: &str, category: Option<&str>| TaskTemplate {
+            label: label.to_string(),
+            command: label.to_string(),
+            category: category.map(str::to_string),
+            ..TaskTemplate::default()
+        };
+        let templates = TaskTemplates(vec![
+            template("cargo build", Some("Build")),
+            template("cargo test", Some("Test")),
+            template("open docs", None),
+            template("cargo build --release", Some("Build")),
+            template("clean", None),
+        ]);
+        let labels = |templates: &[&TaskTemplate]| {
+            templates
+                .iter()
+                .map(|template| template.label.clone())
+                .collect::<Vec<_>>()
+        };
+        let groups = templates.group_by_category();
+        assert_eq!(
+            groups.keys().collect::<Vec<_>>(),
+            vec![&None, &Some("Build".to_string()), &Some("Test".to_string())]
+        );
+        assert_eq!(labels(&groups[&None]), vec!["open docs", "clean"]);
+        assert_eq!(
+            labels(&groups[&Some("Build".to_string())]),
+            vec!["cargo build", "cargo build --release"]
+        );
+        assert!(TaskTemplates::default().group_by_category().is_empty());
+    }
+
+    #[test]
+    fn test_inherit_marker() {
+        let parent = TaskTemplate {
+            label: "parent".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["build".into(), "--workspace".into()],
+            env: HashMap::from_iter([
+                ("PATH".to_string(), "/usr/bin".into()),
+                (
+                    "EXTRA_PATHS".to_string(),
+                    EnvValue::List(vec!["a".to_string(), "b".to_string()]),
+                ),
+            ]),
+            ..TaskTemplate::default()
+        };
+        let child = |args: &[&str]| TaskTemplate {
+            label: "child".to_string(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+            extends: Some("parent".to_string()),
+            ..TaskTemplate::default()
+        };
+        let resolved_args = |child: TaskTemplate| {
+            TaskTemplates(vec![child, parent.clone()])
+                .resolve_inheritance()
+                .0
+                .remove(0)
+                .args
+                .iter()
+                .map(|arg| arg.value().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            resolved_args(child(&["${inherit}", "--release"])),
+            vec!["build", "--workspace", "--release"]
+        );
+        assert_eq!(
+            resolved_args(child(&["+nightly", "${inherit}", "--release"])),
+            vec!["+nightly", "build", "--workspace", "--release"]
+        );
+        assert_eq!(
+            resolved_args(child(&["--verbose", "${inherit}"])),
+            vec!["--verbose", "build", "--workspace"]
+        );
+        assert_eq!(
+            resolved_args(child(&["--verbose"])),
+            vec!["--verbose"],
+            "Own args without the marker should replace the parent ones"
+        );
+        assert_eq!(
+            resolved_args(TaskTemplate {
+                extends: Some("missing".to_string()),
+                ..child(&["${inherit}", "--verbose"])
+            }),
+            vec!["--verbose"],
+            "Marker should expand into nothing without the parent"
+        );
+
+        let resolved = TaskTemplates(vec![
+            TaskTemplate {
+                env: HashMap::from_iter([
+                    ("PATH".to_string(), "/opt/bin:${inherit}".into()),
+                    (
+                        "EXTRA_PATHS".to_string(),
+                        EnvValue::List(vec!["first".to_string(), "${inherit}".to_string()]),
+                    ),
+                    ("NEW".to_string(), "[${inherit}]".into()),
+                ]),
+                ..child(&[])
+            },
+            parent.clone(),
+        ])
+        .resolve_inheritance()
+        .0
+        .remove(0);
+        assert_eq!(resolved.args, parent.args);
+        assert_eq!(
+            resolved.env,
+            HashMap::from_iter([
+                ("PATH".to_string(), "/opt/bin:/usr/bin".into()),
+                (
+                    "EXTRA_PATHS".to_string(),
+                    EnvValue::List(vec!["first".to_string(), "a".to_string(), "b".to_string()]),
+                ),
+                ("NEW".to_string(), "[]".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_reveal_target_override() {
+        let template = TaskTemplate {
+            label: "server".to_string(),
+            command: "npm".to_string(),
+            args: vec!["start".into()],
+            ..TaskTemplate::default()
+        };
+        let reveal_target = |template: &TaskTemplate, reveal_target: Option<RevealTarget>| {
+            template
+                .resolve_task_with_options(
+                    "test",
+                    TaskContext::default(),
+                    &ResolveOptions {
+                        reveal_target,
+                        ..ResolveOptions::default()
+                    },
+                )
+                .unwrap()
+                .resolved
+                .unwrap()
+                .reveal_target
+        };
+        assert_eq!(reveal_target(&template, None), RevealTarget::Dock);
+        assert_eq!(
+            reveal_target(&template, Some(RevealTarget::Center)),
+            RevealTarget::Center
+        );
+
+        let center_template = TaskTemplate {
+            reveal_target: RevealTarget::Center,
+            ..template
+        };
+        assert_eq!(reveal_target(&center_template, None), RevealTarget::Center);
+        assert_eq!(
+            reveal_target(&center_template, Some(RevealTarget::Dock)),
+            RevealTarget::Dock,
+            "Override should win over the template value"
+        );
+    }
+
+    #[test]
+    fn test_templates_fingerprint() {
+        let build = TaskTemplate {
+            label: "build".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["build".into()],
+            ..TaskTemplate::default()
+        };
+        let test = TaskTemplate {
+            label: "test".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["test".into()],
+            ..TaskTemplate::default()
+        };
+        let fingerprint = TaskTemplates(vec![build.clone(), test.clone()]).fingerprint();
+        assert_eq!(
+            TaskTemplates(vec![test.clone(), build.clone()]).fingerprint(),
+            fingerprint,
+            "Reordering templates should not change the fingerprint"
+        );
+
+        let changed_sets = [
+            vec![build.clone()],
+            vec![build.clone(), test.clone(), test.clone()],
+            vec![
+                build.clone(),
+                TaskTemplate {
+                    label: "test all".to_string(),
+                    ..test.clone()
+                },
+            ],
+            vec![
+                build.clone(),
+                TaskTemplate {
+                    args: vec!["test".into(), "--workspace".into()],
+                    ..test.clone()
+                },
+            ],
+            vec![
+                TaskTemplate {
+                    use_new_terminal: true,
+                    ..build.clone()
+                },
+                test.clone(),
+            ],
+        ];
+        for (i, templates) in changed_sets.into_iter().enumerate() {
+            assert_ne!(
+                TaskTemplates(templates).fingerprint(),
+                fingerprint,
+                "Changed set {i} should have a different fingerprint"
+            );
+        }
+        assert_eq!(TaskTemplates::default().fingerprint(), 0);
+    }
+
+    #[test]
+    fn test_git_variables_substitution() {
+        let task = TaskTemplate {
+            label: "deploy".to_string(),
+            command: "deploy".to_string(),
+            args: vec![
+                format!("--branch={}", VariableName::GitUpstream.template_value()).into(),
+                format!("--repo={}", VariableName::GitRemoteUrl.template_value()).into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            VariableName::GitUpstream.template_value(),
+            "$ZED_GIT_UPSTREAM"
+        );
+        assert_eq!(VariableName::GitRemoteUrl.to_string(), "ZED_GIT_REMOTE_URL");
+        let resolve_git_args = |git_info: GitInfo| {
+            let mut task_variables = TaskVariables::default();
+            task_variables.insert_git_info(&git_info);
+            resolve_args(&task, task_variables)
+        };
+        assert_eq!(
+            resolve_git_args(GitInfo {
+                upstream: Some("origin/main".to_string()),
+                remote_url: Some("git@github.com:zed-industries/zed.git".to_string()),
+            }),
+            vec![
+                "--branch=origin/main",
+                "--repo=git@github.com:zed-industries/zed.git"
+            ]
+        );
+        assert_eq!(
+            resolve_git_args(GitInfo::default()),
+            vec!["--branch=", "--repo="],
+            "Unknown git state should resolve to empty strings"
+        );
+    }
+
+    #[test]
+    fn test_runnable_symbol_substitution() {
+        assert_eq!(
+            VariableName::RunnableSymbol.to_string(),
+            "ZED_RUNNABLE_SYMBOL"
+        );
+        assert_eq!(
+            VariableName::RunnableSymbol.template_value(),
+            "$ZED_RUNNABLE_SYMBOL"
+        );
+        let task = TaskTemplate {
+            label: "test ${ZED_RUNNABLE_SYMBOL}".to_string(),
+            command: "cargo".to_string(),
+            args: vec![
+                "test".into(),
+                VariableName::RunnableSymbol.template_value().into(),
+                "--".into(),
+                "--exact".into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let resolve = |task_variables: TaskVariables| {
+            let resolved = task
+                .resolve_task(
+                    TEST_ID_BASE,
+                    TaskContext {
+                        cwd: None,
+                        task_variables,
+                    },
+                )
+                .unwrap();
+            (resolved.resolved_label, resolved.resolved.unwrap().args)
+        };
+        assert_eq!(
+            resolve(TaskVariables::from_iter([
+                (VariableName::Symbol, "test_empty".to_string()),
+                (
+                    VariableName::RunnableSymbol,
+                    "tests::parsing::test_empty".to_string()
+                ),
+            ])),
+            (
+                "test tests::parsing::test_empty".to_string(),
+                vec!["test", "tests::parsing::test_empty", "--", "--exact"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+        assert_eq!(
+            resolve(TaskVariables::default()),
+            (
+                "test ".to_string(),
+                vec!["test", "", "--", "--exact"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            ),
+            "No runnable at the cursor should resolve to an empty string"
+        );
+    }
+
+    #[test]
+    fn test_visual_column_substitution() {
+        assert_eq!(visual_column("", 4), 1);
+        assert_eq!(visual_column("let x", 4), 6);
+        assert_eq!(visual_column("\t\tfoo(", 4), 13);
+        assert_eq!(
+            visual_column("ab\tc", 4),
+            6,
+            "Tabs should advance to the next tab stop"
+        );
+        assert_eq!(visual_column("\t", 0), 2);
+
+        let line_prefix = "\tif x";
+        let character_column = line_prefix.chars().count() + 1;
+        let task = TaskTemplate {
+            label: "lint".to_string(),
+            command: "lint".to_string(),
+            args: vec![
+                format!("--column={}", VariableName::Column.template_value()).into(),
+                format!(
+                    "--visual-column={}",
+                    VariableName::VisualColumn.template_value()
+                )
+                .into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let resolved = task
+            .resolve_task(
+                TEST_ID_BASE,
+                TaskContext {
+                    cwd: None,
+                    task_variables: TaskVariables::from_iter([
+                        (VariableName::Column, character_column.to_string()),
+                        (
+                            VariableName::VisualColumn,
+                            visual_column(&line_prefix, 4).to_string(),
+                        ),
+                    ]),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            resolved.resolved.unwrap().args,
+            vec!["--column=6", "--visual-column=9"]
+        );
+    }
+
+    #[test]
+    fn test_authored_json() {
+        let templates = TaskTemplates(vec![
+            TaskTemplate {
+                label: "base".to_string(),
+                command: "cargo".to_string(),
+                args: vec!["build".into()],
+                env: HashMap::from_iter([("RUST_LOG".to_string(), "info".into())]),
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "release".to_string(),
+                args: vec!["${inherit}".into(), "--release".into()],
+                extends: Some("base".to_string()),
+                reveal: RevealStrategy::Never,
+                order: Some(TemplateOrder {
+                    source_priority: 1,
+                    declaration_index: 1,
+                }),
+                ..TaskTemplate::default()
+            },
+        ]);
+        let flattened = templates.resolve_inheritance();
+        assert_eq!(
+            flattened.to_authored_json(),
+            serde_json_lenient::json!([
+                {
+                    "label": "base",
+                    "command": "cargo",
+                    "args": ["build"],
+                    "env": {"RUST_LOG": "info"},
+                },
+                {
+                    "label": "release",
+                    "command": "cargo",
+                    "args": ["build", "--release"],
+                    "env": {"RUST_LOG": "info"},
+                    "reveal": "never",
+                },
+            ])
+        );
+        let reparsed: TaskTemplates =
+            serde_json_lenient::from_value(flattened.to_authored_json()).unwrap();
+        assert_eq!(
+            reparsed.0[1],
+            TaskTemplate {
+                order: None,
+                ..flattened.0[1].clone()
+            }
+        );
+        assert_eq!(
+            TaskTemplate::default().to_authored_json(),
+            serde_json_lenient::json!({"label": ""})
+        );
+    }
+
+    #[test]
+    fn test_log_file_resolution() {
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "serve", "command": "npm", "log_file": "logs/${ZED_CUSTOM_PACKAGE}.log"}"#,
+        )
+        .unwrap();
+        assert!(!template.log_append);
+        let context = |cwd: Option<&str>| TaskContext {
+            cwd: cwd.map(PathBuf::from),
+            task_variables: TaskVariables::from_iter([(
+                VariableName::Custom(Cow::Borrowed("PACKAGE")),
+                "web".to_string(),
+            )]),
+        };
+        let spawn = template
+            .resolve_task(TEST_ID_BASE, context(Some("/project")))
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(
+            spawn.log_file,
+            Some(PathBuf::from("/project").join("logs").join("web.log"))
+        );
+        assert!(!spawn.log_append);
+
+        let spawn = template
+            .resolve_task(TEST_ID_BASE, context(None))
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.log_file, Some(PathBuf::from("logs/web.log")));
+
+        let absolute_log = std::env::temp_dir().join("serve.log");
+        let spawn = TaskTemplate {
+            log_file: Some(absolute_log.to_string_lossy().into_owned()),
+            log_append: true,
+            ..template.clone()
+        }
+        .resolve_task(TEST_ID_BASE, context(Some("/project")))
+        .unwrap()
+        .resolved
+        .unwrap();
+        assert_eq!(spawn.log_file, Some(absolute_log));
+        assert!(spawn.log_append);
+
+        assert_eq!(
+            template.resolve_task(TEST_ID_BASE, TaskContext::default()),
+            None,
+            "Unresolved variables in the log path should fail the resolution"
+        );
+    }
+
+    #[test]
+    fn test_context_label() {
+        let template = TaskTemplate {
+            label: "run test".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["test".into(), VariableName::Symbol.template_value().into()],
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(
+                VariableName::Symbol,
+                "my_test".to_string(),
+            )]),
+        };
+
+        let resolved = template
+            .resolve_task(TEST_ID_BASE, context.clone())
+            .unwrap();
+        let spawn = resolved.resolved.unwrap();
+        assert_eq!(resolved.resolved_label, "run test");
+        assert_eq!(spawn.label, "run test", "Tab should use the base label");
+
+        let resolved = TaskTemplate {
+            context_label: Some("Run test: ${ZED_SYMBOL}".to_string()),
+            ..template.clone()
+        }
+        .resolve_task(TEST_ID_BASE, context)
+        .unwrap();
+        let spawn = resolved.resolved.unwrap();
+        assert_eq!(spawn.label, "Run test: my_test");
+        assert_eq!(
+            resolved.resolved_label, "run test",
+            "Picker name should stay the base label"
+        );
+        assert_eq!(spawn.full_label, "run test");
+    }
+
+    #[test]
+    fn test_resolution_with_fallback_context() {
+        let template = TaskTemplate {
+            label: "check ${ZED_FILE}".to_string(),
+            command: "cargo".to_string(),
+            args: vec![
+                "check".into(),
+                VariableName::File.template_value().into(),
+                VariableName::WorktreeRoot.template_value().into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let fallback = TaskContext {
+            cwd: Some(PathBuf::from("/last")),
+            task_variables: TaskVariables::from_iter([
+                (VariableName::File, "/last/main.rs".to_string()),
+                (VariableName::WorktreeRoot, "/last".to_string()),
+            ]),
+        };
+        let primary = TaskContext::default();
+        assert_eq!(template.resolve_task(TEST_ID_BASE, primary.clone()), None);
+
+        let resolved = template
+            .resolve_with_fallback(TEST_ID_BASE, &primary, &fallback)
+            .unwrap();
+        assert_eq!(resolved.resolved_label, "check /last/main.rs");
+        let spawn = resolved.resolved.unwrap();
+        assert_eq!(spawn.args, vec!["check", "/last/main.rs", "/last"]);
+        assert_eq!(spawn.cwd, Some(PathBuf::from("/last")));
+
+        let primary = TaskContext {
+            cwd: Some(PathBuf::from("/current")),
+            task_variables: TaskVariables::from_iter([(
+                VariableName::WorktreeRoot,
+                "/current".to_string(),
+            )]),
+        };
+        let spawn = template
+            .resolve_with_fallback(TEST_ID_BASE, &primary, &fallback)
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(
+            spawn.args,
+            vec!["check", "/last/main.rs", "/current"],
+            "Primary context variables should take precedence"
+        );
+        assert_eq!(spawn.cwd, Some(PathBuf::from("/current")));
+    }
+
+    #[test]
+    fn test_reveal_then_refocus_editor_strategy() {
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "check", "command": "cargo", "reveal": "reveal_then_refocus_editor"}"#,
+        )
+        .unwrap();
+        assert_eq!(template.reveal, RevealStrategy::RevealThenRefocusEditor);
+        assert_ne!(template.reveal, RevealStrategy::Always);
+        assert_eq!(
+            serde_json_lenient::to_value(RevealStrategy::RevealThenRefocusEditor).unwrap(),
+            serde_json_lenient::json!("reveal_then_refocus_editor")
+        );
+
+        let resolved = template
+            .resolve_task("test", TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(resolved.reveal, RevealStrategy::RevealThenRefocusEditor);
+    }
+
+    #[test]
+    fn test_finding_templates_by_label() {
+        let template = |label: &str, command: &str| TaskTemplate {
+            label: label.to_string(),
+            command: command.to_string(),
+            ..TaskTemplate::default()
+        };
+        let templates = TaskTemplates(vec![
+            template("build", "cargo"),
+            template("test", "cargo"),
+            template("build", "make"),
+        ]);
+        assert_eq!(
+            templates
+                .find_all_by_label("build")
+                .into_iter()
+                .map(|template| template.command.as_str())
+                .collect::<Vec<_>>(),
+            vec!["cargo", "make"]
+        );
+        assert_eq!(
+            templates
+                .find_by_label("build")
+                .map(|template| template.command.as_str()),
+            Some("cargo")
+        );
+        assert_eq!(templates.find_all_by_label("test"), vec![&templates.0[1]]);
+        assert!(templates.find_all_by_label("lint").is_empty());
+        assert_eq!(templates.find_by_label("lint"), None);
+    }
+
+    #[test]
+    fn test_hidden_and_deprecated_templates() {
+        let templates: TaskTemplates = serde_json_lenient::from_str(
+            r#"[
+                {"label": "build", "command": "make"},
+                {"label": "old build", "command": "make", "hidden": true, "deprecated": "Use `build` instead"}
+            ]"#,
+        )
+        .unwrap();
+        assert!(!templates.0[0].hidden);
+        assert_eq!(templates.0[0].deprecated, None);
+        assert!(templates.0[1].hidden);
+        assert_eq!(
+            templates.visible(),
+            vec![&templates.0[0]],
+            "Hidden templates should be filtered out"
+        );
+        assert_eq!(
+            templates.find_by_label("old build"),
+            Some(&templates.0[1]),
+            "Hidden templates should still be found by their labels"
+        );
+
+        let resolved = templates.0[1]
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap();
+        assert_eq!(
+            resolved.deprecation_message().as_deref(),
+            Some("Task `old build` is deprecated: Use `build` instead")
+        );
+        let resolved = templates.0[0]
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap();
+        assert_eq!(resolved.deprecation_message(), None);
+    }
+
+    #[test]
+    fn test_output_encoding_and_locale() {
+        let json = r#"{
+            "label": "build",
+            "command": "msbuild",
+            "env": {"LC_ALL": "de_DE.UTF-8", "CONFIGURATION": "Release"},
+            "output_encoding": "utf-16le",
+            "locale_env": {"LC_ALL": "C", "LANG": "C"}
+        }"#;
+        let template: TaskTemplate = serde_json_lenient::from_str(json).unwrap();
+        assert_eq!(template.output_encoding.as_deref(), Some("utf-16le"));
+        let round_tripped: TaskTemplate =
+            serde_json_lenient::from_value(serde_json_lenient::to_value(&template).unwrap())
+                .unwrap();
+        assert_eq!(round_tripped, template);
+
+        let spawn = template
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.output_encoding.as_deref(), Some("utf-16le"));
+        assert_eq!(
+            spawn.env["LC_ALL"], "C",
+            "Locale env should win over the env"
+        );
+        assert_eq!(spawn.env["LANG"], "C");
+        assert_eq!(spawn.env["CONFIGURATION"], "Release");
+
+        let template: TaskTemplate =
+            serde_json_lenient::from_str(r#"{"label": "build", "command": "make"}"#).unwrap();
+        assert_eq!(template.output_encoding, None);
+        assert_eq!(template.locale_env, None);
+    }
+
+    #[test]
+    fn test_terminal_profile() {
+        let template: TaskTemplate =
+            serde_json_lenient::from_str(r#"{"label": "build", "command": "make"}"#).unwrap();
+        assert_eq!(template.terminal_profile, None);
+
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "build", "command": "make", "terminal_profile": "msys2"}"#,
+        )
+        .unwrap();
+        assert_eq!(template.terminal_profile.as_deref(), Some("msys2"));
+        let round_tripped: TaskTemplate =
+            serde_json_lenient::from_value(serde_json_lenient::to_value(&template).unwrap())
+                .unwrap();
+        assert_eq!(round_tripped, template);
+        let spawn = template
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.terminal_profile.as_deref(), Some("msys2"));
+    }
+
+    #[test]
+    fn test_cwd_candidates() {
+        let home = tempfile::tempdir().unwrap();
+        let candidates = vec!["${env:HOME}".to_string(), "${env:USERPROFILE}".to_string()];
+        let unix_env = |name: &str| (name == "HOME").then(|| home.path().display().to_string());
+        let windows_env = |name: &str| match name {
+            "HOME" => Some(String::new()),
+            "USERPROFILE" => Some(home.path().display().to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            first_existing_cwd(&candidates, unix_env),
+            Some(home.path().to_path_buf())
+        );
+        assert_eq!(
+            first_existing_cwd(&candidates, windows_env),
+            Some(home.path().to_path_buf()),
+            "Empty HOME should be skipped in favor of USERPROFILE"
+        );
+        assert_eq!(first_existing_cwd(&candidates, |_| None), None);
+
+        let project = home.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let template = TaskTemplate {
+            label: "build".to_string(),
+            command: "make".to_string(),
+            cwd: Some("/fallback".to_string()),
+            cwd_candidates: vec![
+                "${env:TASK_CWD_CANDIDATES_TEST_UNSET}/project".to_string(),
+                "$ZED_WORKTREE_ROOT/missing".to_string(),
+                "$ZED_WORKTREE_ROOT/project".to_string(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let resolve_cwd = |template: &TaskTemplate, worktree_root: &Path| {
+            let mut spawn = template
+                .resolve_task(
+                    TEST_ID_BASE,
+                    TaskContext {
+                        cwd: None,
+                        task_variables: TaskVariables::from_iter([(
+                            VariableName::WorktreeRoot,
+                            worktree_root.display().to_string(),
+                        )]),
+                    },
+                )
+                .unwrap()
+                .resolved
+                .unwrap();
+            assert_eq!(
+                spawn.cwd,
+                Some(PathBuf::from("/fallback")),
+                "Candidates should not be checked during the resolution"
+            );
+            spawn.apply_cwd_candidates();
+            spawn.cwd
+        };
+        assert_eq!(resolve_cwd(&template, home.path()), Some(project));
+        assert_eq!(
+            resolve_cwd(&template, Path::new("/nonexistent")),
+            Some(PathBuf::from("/fallback")),
+            "Template cwd should be used when no candidate exists"
+        );
+    }
+
+    #[test]
+    fn test_effective_label() {
+        let template = TaskTemplate {
+            label: "test ${ZED_SYMBOL} in ${ZED_FILE}".to_string(),
+            command: "cargo".to_string(),
+            context_label: Some("Test: ${ZED_SYMBOL} ${ZED_CUSTOM_MISSING}".to_string()),
+            ..TaskTemplate::default()
+        };
+        let context = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (
+                    VariableName::Symbol,
+                    "tests::a_very_long_test_name".to_string(),
+                ),
+                (VariableName::File, "/project/src/lib.rs".to_string()),
+            ]),
+        };
+        assert_eq!(
+            template.effective_label(&context, None),
+            "Test: …y_long_test_name ${ZED_CUSTOM_MISSING}"
+        );
+        assert_eq!(
+            template.effective_label(&context, Some(16)),
+            "Test: …y_long_t…"
+        );
+        assert_eq!(
+            template.effective_label(&context, Some(100)),
+            template.effective_label(&context, None)
+        );
+
+        let mut resolved_context = context.clone();
+        resolved_context
+            .task_variables
+            .insert(VariableName::Custom("MISSING".into()), "found".to_string());
+        let resolved = template
+            .resolve_task(TEST_ID_BASE, resolved_context.clone())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(
+            template.effective_label(&resolved_context, None),
+            resolved.label,
+            "Effective label should match the one of the resolved task"
+        );
+        assert_eq!(
+            TaskTemplate {
+                context_label: None,
+                ..template
+            }
+            .effective_label(&context, Some(12)),
+            "test …y_lon…"
+        );
+    }
+
+    #[test]
+    fn test_ready_probe() {
+        let template: TaskTemplate =
+            serde_json_lenient::from_str(r#"{"label": "serve", "command": "npm"}"#).unwrap();
+        assert_eq!(template.ready_when, None);
+
+        for (json, probe) in [
+            (
+                r#"{"output_contains": "Listening on"}"#,
+                ReadyProbe::OutputContains("Listening on".to_string()),
+            ),
+            (r#"{"port_open": 8080}"#, ReadyProbe::PortOpen(8080)),
+        ] {
+            let template: TaskTemplate = serde_json_lenient::from_str(&format!(
+                r#"{{"label": "serve", "command": "npm", "ready_when": {json}}}"#
+            ))
+            .unwrap();
+            assert_eq!(template.ready_when.as_ref(), Some(&probe));
+            assert_eq!(
+                serde_json_lenient::to_value(&probe).unwrap(),
+                serde_json_lenient::from_str::<serde_json_lenient::Value>(json).unwrap()
+            );
+            let spawn = template
+                .resolve_task(TEST_ID_BASE, TaskContext::default())
+                .unwrap()
+                .resolved
+                .unwrap();
+            assert_eq!(spawn.ready_when, Some(probe));
+        }
+
+        assert!(serde_json_lenient::from_str::<TaskTemplate>(
+            r#"{"label": "serve", "command": "npm", "ready_when": {"port_open": 70000}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_resolve_tagged() {
+        let template = |label: &str, tags: &[&str], declaration_index: usize| TaskTemplate {
+            label: label.to_string(),
+            command: "sh".to_string(),
+            args: vec![format!("{label}.sh").into()],
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            order: Some(TemplateOrder {
+                source_priority: 0,
+                declaration_index,
+            }),
+            ..TaskTemplate::default()
+        };
+        let templates = TaskTemplates(vec![
+            template("test", &["precommit", "ci"], 2),
+            template("deploy", &["release"], 3),
+            template("fmt", &["PreCommit"], 0),
+            TaskTemplate {
+                args: vec!["$ZED_FILE".into()],
+                ..template("lint-file", &["precommit"], 1)
+            },
+            TaskTemplate {
+                order: None,
+                ..template("spellcheck", &[" precommit "], 0)
+            },
+        ]);
+        let labels = |tag: &str| {
+            templates
+                .resolve_tagged(tag, TEST_ID_BASE, &TaskContext::default())
+                .into_iter()
+                .map(|task| task.resolved_label)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            labels("precommit"),
+            vec!["fmt", "test", "spellcheck"],
+            "Tasks not applicable in the context should be skipped"
+        );
+        assert_eq!(labels("release"), vec!["deploy"]);
+        assert!(labels("missing").is_empty());
+    }
+
+    #[test]
+    fn test_conditional_args_consumers() {
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "test", "command": "cargo", "args": ["test", {"value": "--verbose", "when": "verbose"}]}"#,
+        )
+        .unwrap();
+
+        let serialized = serde_json_lenient::to_value(&template).unwrap();
+        assert_eq!(
+            serialized["args"],
+            serde_json_lenient::json!(["test", {"value": "--verbose", "when": "verbose"}]),
+            "Conditional args should serialize in the object form the schema describes"
+        );
+        assert_eq!(
+            serde_json_lenient::from_value::<TaskTemplate>(serialized).unwrap(),
+            template
+        );
+
+        let (_, trace) = template
+            .resolve_traced(TEST_ID_BASE, TaskContext::default())
+            .unwrap();
+        let traced_args = trace
+            .fields
+            .iter()
+            .filter(|field| field.field.starts_with("args"))
+            .map(|field| (field.field.as_str(), field.original.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(traced_args, vec![("args[0]", "test")]);
+
+        let exported = serde_json_lenient::to_value(crate::VsCodeTaskFile::from_templates(
+            &TaskTemplates(vec![template]),
+        ))
+        .unwrap();
+        assert_eq!(
+            exported["tasks"][0]["args"],
+            serde_json_lenient::json!(["test"])
+        );
+        assert!(exported["tasks"][0]["detail"]
+            .as_str()
+            .unwrap()
+            .contains("`--verbose` (when `verbose`)"));
+    }
+
+    #[test]
+    fn test_conditional_args() {
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{
+                "label": "test",
+                "command": "cargo",
+                "args": [
+                    "test",
+                    {"value": "--verbose", "when": "verbose"},
+                    {"value": "--release", "when": "profile == release"},
+                    {"value": "--quiet", "when": "!verbose"},
+                    "${ZED_CUSTOM_package}"
+                ]
+            }"#,
+        )
+        .unwrap();
+        let custom_variables = |task_variables: &[(&str, &str)]| {
+            TaskVariables::from_iter(task_variables.iter().map(|(name, value)| {
+                (
+                    VariableName::Custom(name.to_string().into()),
+                    value.to_string(),
+                )
+            }))
+        };
+
+        assert_eq!(
+            resolve_args(
+                &template,
+                custom_variables(&[("verbose", "1"), ("package", "task")])
+            ),
+            vec!["test", "--verbose", "task"],
+            "Conditional args should be included when their conditions hold"
+        );
+        assert_eq!(
+            resolve_args(
+                &template,
+                custom_variables(&[("profile", "release"), ("package", "task")])
+            ),
+            vec!["test", "--release", "--quiet", "task"],
+            "Conditional args should be omitted when their conditions do not hold"
+        );
+
+        let malformed = TaskTemplate {
+            args: vec![TaskArg::Conditional {
+                value: "--verbose".to_string(),
+                when: "verbose &&".to_string(),
+            }],
+            ..template.clone()
+        };
+        assert!(malformed
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .is_none());
+
+        let literal = TaskTemplate {
+            args: vec!["${when:verbose}--verbose".into()],
+            ..template.clone()
+        };
+        assert_eq!(
+            resolve_args(&literal, custom_variables(&[])),
+            vec!["${when:verbose}--verbose"],
+            "Plain args should never be treated as conditional ones"
+        );
+        let quoted = TaskTemplate {
+            args: vec![TaskArg::Conditional {
+                value: "--release".to_string(),
+                when: "profile == '}'".to_string(),
+            }],
+            ..template.clone()
+        };
+        assert_eq!(
+            resolve_args(&quoted, custom_variables(&[("profile", "}")])),
+            vec!["--release"]
+        );
+    }
+
+    #[test]
+    fn test_arg_segments() {
+        let template = TaskTemplate {
+            label: "open".to_string(),
+            command: "zed".to_string(),
+            args: vec![
+                "prefix-${ZED_ROW}".into(),
+                "$ZED_FILE:${ZED_ROW+1}:$HOME".into(),
+                "${ZED_CUSTOM_NAME:shellquote}".into(),
+                "$".into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::File, "main.rs".to_string()),
+                (VariableName::Row, "41".to_string()),
+                (VariableName::Custom("NAME".into()), "a b".to_string()),
+            ]),
+        };
+        let (resolved, segments) = template
+            .resolve_arg_segments(TEST_ID_BASE, cx.clone())
+            .unwrap();
+        assert_eq!(
+            Some(resolved.clone()),
+            template.resolve_task(TEST_ID_BASE, cx)
+        );
+        assert_eq!(
+            segments[0],
+            vec![
+                ArgSegment::Literal("prefix-".to_string()),
+                ArgSegment::Variable {
+                    variable: VariableName::Row,
+                    value: "41".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            segments[1],
+            vec![
+                ArgSegment::Variable {
+                    variable: VariableName::File,
+                    value: "main.rs".to_string(),
+                },
+                ArgSegment::Literal(":".to_string()),
+                ArgSegment::Variable {
+                    variable: VariableName::Row,
+                    value: "42".to_string(),
+                },
+                ArgSegment::Literal(":$HOME".to_string()),
+            ]
+        );
+        assert_eq!(
+            segments[2],
+            vec![ArgSegment::Variable {
+                variable: VariableName::Custom("NAME".into()),
+                value: "'a b'".to_string(),
+            }]
+        );
+        assert_eq!(segments[3], vec![ArgSegment::Literal("$".to_string())]);
+
+        let concatenated = segments
+            .iter()
+            .map(|segments| {
+                segments
+                    .iter()
+                    .map(|segment| match segment {
+                        ArgSegment::Literal(text) => text.as_str(),
+                        ArgSegment::Variable { value, .. } => value.as_str(),
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(concatenated, resolved.resolved.unwrap().args);
+    }
+
+    #[test]
+    fn test_unsatisfied_variables() {
+        let template = TaskTemplate {
+            label: "test $ZED_SYMBOL".to_string(),
+            command: "cargo".to_string(),
+            args: vec![
+                "test".into(),
+                "${ZED_SYMBOL:shellquote}".into(),
+                "--file=$ZED_FILE_URI".into(),
+                "${ZED_CUSTOM_PACKAGE}".into(),
+            ],
+            ..TaskTemplate::default()
+        };
+        let context = |task_variables: TaskVariables| TaskContext {
+            cwd: None,
+            task_variables,
+        };
+
+        assert_eq!(
+            template.unsatisfied_variables(&context(TaskVariables::from_iter([(
+                VariableName::File,
+                "/project/main.rs".to_string()
+            )]))),
+            vec![VariableName::Symbol],
+            "File URI is derived from the file, custom variables are not reported"
+        );
+        assert_eq!(
+            template.unsatisfied_variables(&context(TaskVariables::from_iter([
+                (VariableName::File, "/project/main.rs".to_string()),
+                (VariableName::Symbol, String::new()),
+            ]))),
+            vec![VariableName::Symbol],
+            "Empty values should be reported"
+        );
+        assert_eq!(
+            template.unsatisfied_variables(&context(TaskVariables::from_iter([
+                (VariableName::File, "/project/main.rs".to_string()),
+                (VariableName::Symbol, "my_test".to_string()),
+            ]))),
+            Vec::new()
+        );
+        assert_eq!(
+            template.unsatisfied_variables(&TaskContext::default()),
+            vec![VariableName::Symbol, VariableName::FileUri]
+        );
+    }
+
+    #[test]
+    fn test_wrapper() {
+        let template: TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "build", "command": "cargo", "args": ["build"], "wrapper": ["time"]}"#,
+        )
+        .unwrap();
+        let spawn = template
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.command, "time");
+        assert_eq!(spawn.args, vec!["cargo", "build"]);
+
+        let raw = TaskTemplate {
+            command: "cargo build && cargo test".to_string(),
+            args: Vec::new(),
+            raw_command: true,
+            ..template.clone()
+        };
+        let spawn = raw
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.command, "time");
+        assert!(!spawn.raw_command);
+        assert_eq!(
+            spawn.args.last().map(String::as_str),
+            Some("cargo build && cargo test"),
+            "The raw command line should be passed to the shell under the wrapper"
+        );
+
+        let sandboxed = TaskTemplate {
+            wrapper: Some(vec![
+                "bwrap".to_string(),
+                "--bind".to_string(),
+                "$ZED_WORKTREE_ROOT".to_string(),
+                "/work".to_string(),
+            ]),
+            ..template
+        };
+        let cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([(
+                VariableName::WorktreeRoot,
+                "/project".to_string(),
+            )]),
+        };
+        let spawn = sandboxed
+            .resolve_task(TEST_ID_BASE, cx)
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(spawn.command, "bwrap");
+        assert_eq!(
+            spawn.args,
+            vec!["--bind", "/project", "/work", "cargo", "build"]
+        );
+        assert!(sandboxed
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_label_hierarchy() {
+        let template = |label: &str| TaskTemplate {
+            label: label.to_string(),
+            command: "echo".to_string(),
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            template("build/web / release").label_path('/'),
+            vec!["build", "web", "release"]
+        );
+        assert_eq!(
+            template("build//web/").label_path('/'),
+            vec!["build", "web"]
+        );
+        assert_eq!(template("cargo test").label_path('/'), vec!["cargo test"]);
+        assert_eq!(template("cargo test").label_path(':'), vec!["cargo test"]);
+
+        let templates = TaskTemplates(vec![
+            template("build/web/release"),
+            template("lint"),
+            template("build/native"),
+            template("build/web/debug"),
+        ]);
+        let grouped = templates.grouped('/');
+        assert_eq!(grouped.templates, vec![&templates.0[1]]);
+        assert_eq!(grouped.subgroups.len(), 1);
+        let (build_name, build) = &grouped.subgroups[0];
+        assert_eq!(*build_name, "build");
+        assert_eq!(build.templates, vec![&templates.0[2]]);
+        assert_eq!(build.subgroups.len(), 1);
+        let (web_name, web) = &build.subgroups[0];
+        assert_eq!(*web_name, "web");
+        assert_eq!(web.templates, vec![&templates.0[0], &templates.0[3]]);
+        assert!(web.subgroups.is_empty());
+
+        let flat = templates.grouped(':');
+        assert_eq!(flat.templates.len(), 4);
+        assert!(flat.subgroups.is_empty());
+    }
+
+    #[test]
+    fn test_can_resolve_free_variables() {
+        let task = TaskTemplate {
+            label: "My task".into(),
+            command: "echo".into(),
+            args: vec!["$PATH".into()],
+            ..Default::default()
+        };
+        let resolved = task
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        assert_eq!(resolved.label, task.label);
+        assert_eq!(resolved.command, task.command);
+        assert_eq!(resolved.args, vec!["$PATH"]);
+    }
+
+    #[test]
+    fn test_errors_on_missing_zed_variable() {
+        let task = TaskTemplate {
+            label: "My task".into(),
+            command: "echo".into(),
+            args: vec!["$ZED_VARIABLE".into()],
+            ..Default::default()
+        };
+        assert!(task
+            .resolve_task(TEST_ID_BASE, TaskContext::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_resolution_trace_uses_platform_overrides() {
+        let template = TaskTemplate {
+            label: "build".to_string(),
+            command: "make".to_string(),
+            args: vec!["all".into()],
+            wrapper: Some(vec!["time".to_string(), "-v".to_string()]),
+            shell: Some("${ZED_CUSTOM_SHELL}".to_string()),
+            platform_overrides: BTreeMap::from_iter([(
+                Platform::current(),
+                PlatformOverride {
+                    command: Some("cargo".to_string()),
+                    args: Some(vec!["build".into(), "$ZED_SYMBOL".into()]),
+                    ..PlatformOverride::default()
+                },
+            )]),
+            ..TaskTemplate::default()
+        };
+        let cx = TaskContext {
+            cwd: None,
+            task_variables: TaskVariables::from_iter([
+                (VariableName::Symbol, "my_crate".to_string()),
+                (VariableName::Custom("SHELL".into()), "bash".to_string()),
+            ]),
+        };
+        let (resolved, trace) = template.resolve_traced(TEST_ID_BASE, cx.clone()).unwrap();
+        assert_eq!(Some(resolved), template.resolve_task(TEST_ID_BASE, cx));
+        assert_eq!(
+            trace
+                .fields
+                .iter()
+                .map(|field| (field.field.as_str(), field.result.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("label", "build"),
+                ("command", "cargo"),
+                ("args[0]", "build"),
+                ("args[1]", "my_crate"),
+                ("wrapper[0]", "time"),
+                ("wrapper[1]", "-v"),
+                ("shell", "bash"),
+            ],
+            "The trace should describe the platform-applied template, including its wrapper and shell"
+        );
     }
 }
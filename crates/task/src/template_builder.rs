@@ -0,0 +1,474 @@
+//! A fluent way to construct [`TaskTemplate`]s in code, as an alternative to struct literals.
+
+use anyhow::{bail, Context as _};
+
+use crate::{
+    DiagnosticSeverity, EnvValue, OutputMode, PathMode, ReadyProbe, RevealStrategy, RevealTarget,
+    StopSignal, TaskArg, TaskInput, TaskTemplate, TaskTemplates,
+};
+
+impl TaskTemplate {
+    /// Starts building a new template, see [`TaskTemplateBuilder`].
+    pub fn builder() -> TaskTemplateBuilder {
+        TaskTemplateBuilder::default()
+    }
+}
+
+/// Builds a [`TaskTemplate`] field by field, the fields not set keep their default values.
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct TaskTemplateBuilder {
+    template: TaskTemplate,
+}
+
+impl TaskTemplateBuilder {
+    /// Sets [`TaskTemplate::label`].
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.template.label = label.into();
+        self
+    }
+
+    /// Sets [`TaskTemplate::command`].
+    pub fn command(mut self, command: impl Into<String>) -> Self {
+        self.template.command = command.into();
+        self
+    }
+
+    /// Appends an argument to [`TaskTemplate::args`].
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.template.args.push(TaskArg::Plain(arg.into()));
+        self
+    }
+
+    /// Appends an argument to [`TaskTemplate::args`], passed only if the condition holds.
+    pub fn arg_when(mut self, arg: impl Into<String>, condition: impl Into<String>) -> Self {
+        self.template.args.push(TaskArg::Conditional {
+            value: arg.into(),
+            when: condition.into(),
+        });
+        self
+    }
+
+    /// Appends multiple arguments to [`TaskTemplate::args`].
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.template
+            .args
+            .extend(args.into_iter().map(|arg| TaskArg::Plain(arg.into())));
+        self
+    }
+
+    /// Adds a variable to [`TaskTemplate::env`], overriding the previous value of the same variable.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<EnvValue>) -> Self {
+        self.template.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::cwd`].
+    pub fn cwd(mut self, cwd: impl Into<String>) -> Self {
+        self.template.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::use_new_terminal`].
+    pub fn use_new_terminal(mut self, use_new_terminal: bool) -> Self {
+        self.template.use_new_terminal = use_new_terminal;
+        self
+    }
+
+    /// Sets [`TaskTemplate::allow_concurrent_runs`].
+    pub fn allow_concurrent_runs(mut self, allow_concurrent_runs: bool) -> Self {
+        self.template.allow_concurrent_runs = allow_concurrent_runs;
+        self
+    }
+
+    /// Sets [`TaskTemplate::reveal`].
+    pub fn reveal(mut self, reveal: RevealStrategy) -> Self {
+        self.template.reveal = reveal;
+        self
+    }
+
+    /// Sets [`TaskTemplate::extends`].
+    pub fn extends(mut self, parent_label: impl Into<String>) -> Self {
+        self.template.extends = Some(parent_label.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::echo_command`].
+    pub fn echo_command(mut self, echo_command: bool) -> Self {
+        self.template.echo_command = echo_command;
+        self
+    }
+
+    /// Appends an input to [`TaskTemplate::inputs`].
+    pub fn input(mut self, input: TaskInput) -> Self {
+        self.template.inputs.push(input);
+        self
+    }
+
+    /// Sets [`TaskTemplate::confirm`].
+    pub fn confirm(mut self, confirm: impl Into<String>) -> Self {
+        self.template.confirm = Some(confirm.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::env_file`].
+    pub fn env_file(mut self, env_file: impl Into<String>) -> Self {
+        self.template.env_file = Some(env_file.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::action`].
+    pub fn action(mut self, action: impl Into<String>) -> Self {
+        self.template.action = Some(action.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::stop_signal`].
+    pub fn stop_signal(mut self, stop_signal: StopSignal) -> Self {
+        self.template.stop_signal = Some(stop_signal);
+        self
+    }
+
+    /// Sets [`TaskTemplate::stop_timeout_ms`].
+    pub fn stop_timeout_ms(mut self, stop_timeout_ms: u64) -> Self {
+        self.template.stop_timeout_ms = Some(stop_timeout_ms);
+        self
+    }
+
+    /// Sets [`TaskTemplate::run_as_user`].
+    pub fn run_as_user(mut self, user: impl Into<String>) -> Self {
+        self.template.run_as_user = Some(user.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::run_as_group`].
+    pub fn run_as_group(mut self, group: impl Into<String>) -> Self {
+        self.template.run_as_group = Some(group.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::output_mode`].
+    pub fn output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.template.output_mode = output_mode;
+        self
+    }
+
+    /// Sets [`TaskTemplate::mutex_group`].
+    pub fn mutex_group(mut self, mutex_group: impl Into<String>) -> Self {
+        self.template.mutex_group = Some(mutex_group.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::on_success`].
+    pub fn on_success(mut self, command: impl Into<String>) -> Self {
+        self.template.on_success = Some(command.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::on_failure`].
+    pub fn on_failure(mut self, command: impl Into<String>) -> Self {
+        self.template.on_failure = Some(command.into());
+        self
+    }
+
+    /// Appends a tag to [`TaskTemplate::tags`].
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.template.tags.push(tag.into());
+        self
+    }
+
+    /// Appends multiple tags to [`TaskTemplate::tags`].
+    pub fn tags(mut self, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.template.tags.extend(tags.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets [`TaskTemplate::detached`].
+    pub fn detached(mut self, detached: bool) -> Self {
+        self.template.detached = detached;
+        self
+    }
+
+    /// Sets [`TaskTemplate::create_cwd`].
+    pub fn create_cwd(mut self, create_cwd: bool) -> Self {
+        self.template.create_cwd = create_cwd;
+        self
+    }
+
+    /// Sets [`TaskTemplate::args_file`].
+    pub fn args_file(mut self, args_file: bool) -> Self {
+        self.template.args_file = args_file;
+        self
+    }
+
+    /// Sets [`TaskTemplate::category`].
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.template.category = Some(category.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::success_exit_codes`].
+    pub fn success_exit_codes(mut self, exit_codes: impl IntoIterator<Item = i32>) -> Self {
+        self.template.success_exit_codes = exit_codes.into_iter().collect();
+        self
+    }
+
+    /// Sets [`TaskTemplate::success_message`].
+    pub fn success_message(mut self, message: impl Into<String>) -> Self {
+        self.template.success_message = Some(message.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::failure_message`].
+    pub fn failure_message(mut self, message: impl Into<String>) -> Self {
+        self.template.failure_message = Some(message.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::reveal_target`].
+    pub fn reveal_target(mut self, reveal_target: RevealTarget) -> Self {
+        self.template.reveal_target = reveal_target;
+        self
+    }
+
+    /// Appends a label to [`TaskTemplate::depends_on`].
+    pub fn depends_on(mut self, label: impl Into<String>) -> Self {
+        self.template.depends_on.push(label.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::force_line_buffered`].
+    pub fn force_line_buffered(mut self, force_line_buffered: bool) -> Self {
+        self.template.force_line_buffered = force_line_buffered;
+        self
+    }
+
+    /// Sets [`TaskTemplate::log_file`].
+    pub fn log_file(mut self, log_file: impl Into<String>) -> Self {
+        self.template.log_file = Some(log_file.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::log_append`].
+    pub fn log_append(mut self, log_append: bool) -> Self {
+        self.template.log_append = log_append;
+        self
+    }
+
+    /// Sets [`TaskTemplate::context_label`].
+    pub fn context_label(mut self, context_label: impl Into<String>) -> Self {
+        self.template.context_label = Some(context_label.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::env_command`].
+    pub fn env_command(mut self, env_command: impl Into<String>) -> Self {
+        self.template.env_command = Some(env_command.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::hidden`].
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.template.hidden = hidden;
+        self
+    }
+
+    /// Sets [`TaskTemplate::deprecated`].
+    pub fn deprecated(mut self, message: impl Into<String>) -> Self {
+        self.template.deprecated = Some(message.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::output_encoding`].
+    pub fn output_encoding(mut self, encoding: impl Into<String>) -> Self {
+        self.template.output_encoding = Some(encoding.into());
+        self
+    }
+
+    /// Adds a variable to [`TaskTemplate::locale_env`], overriding the previous value of the same variable.
+    pub fn locale_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.template
+            .locale_env
+            .get_or_insert_with(Default::default)
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::terminal_profile`].
+    pub fn terminal_profile(mut self, profile: impl Into<String>) -> Self {
+        self.template.terminal_profile = Some(profile.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::strip_zed_path_additions`].
+    pub fn strip_zed_path_additions(mut self, strip_zed_path_additions: bool) -> Self {
+        self.template.strip_zed_path_additions = strip_zed_path_additions;
+        self
+    }
+
+    /// Appends a candidate to [`TaskTemplate::cwd_candidates`].
+    pub fn cwd_candidate(mut self, candidate: impl Into<String>) -> Self {
+        self.template.cwd_candidates.push(candidate.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::ready_when`].
+    pub fn ready_when(mut self, probe: ReadyProbe) -> Self {
+        self.template.ready_when = Some(probe);
+        self
+    }
+
+    /// Sets [`TaskTemplate::path_mode`].
+    pub fn path_mode(mut self, path_mode: PathMode) -> Self {
+        self.template.path_mode = path_mode;
+        self
+    }
+
+    /// Sets [`TaskTemplate::raw_command`].
+    pub fn raw_command(mut self, raw_command: bool) -> Self {
+        self.template.raw_command = raw_command;
+        self
+    }
+
+    /// Sets [`TaskTemplate::wrapper`].
+    pub fn wrapper(mut self, wrapper: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.template.wrapper = Some(wrapper.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets [`TaskTemplate::is_default_for_group`].
+    pub fn default_for_group(mut self, is_default: bool) -> Self {
+        self.template.is_default_for_group = is_default;
+        self
+    }
+
+    /// Sets [`TaskTemplate::shell`].
+    pub fn shell(mut self, shell: impl Into<String>) -> Self {
+        self.template.shell = Some(shell.into());
+        self
+    }
+
+    /// Sets [`TaskTemplate::scrollback`].
+    pub fn scrollback(mut self, scrollback: usize) -> Self {
+        self.template.scrollback = Some(scrollback);
+        self
+    }
+
+    /// Finishes the template, failing if its label is blank,
+    /// or if [`TaskTemplates::validate`] reports errors (not warnings) for it.
+    /// The checks relating the template to the others, e.g. of the `depends_on` and `extends` labels, are skipped,
+    /// as the other templates are not known to the builder.
+    pub fn build(self) -> anyhow::Result<TaskTemplate> {
+        let template = self.template;
+        if template.label.trim().is_empty() {
+            bail!("Task label should not be blank");
+        }
+        let templates = TaskTemplates(vec![template]);
+        let errors = templates
+            .validate_standalone()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error)
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            bail!("Invalid task template: {}", errors.join("; "));
+        }
+        templates
+            .0
+            .into_iter()
+            .next()
+            .context("missing the template built")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_building_templates() {
+        let template = TaskTemplate::builder()
+            .label("test ${ZED_SYMBOL}")
+            .command("cargo")
+            .arg("test")
+            .args(["--package", "task"])
+            .env("RUST_LOG", "debug")
+            .env("EXTRA_PATHS", EnvValue::List(vec!["bin".to_string()]))
+            .cwd("${ZED_WORKTREE_ROOT}")
+            .reveal(RevealStrategy::Never)
+            .allow_concurrent_runs(true)
+            .tag("test")
+            .tags(["rust"])
+            .build()
+            .unwrap();
+        assert_eq!(
+            template,
+            TaskTemplate {
+                label: "test ${ZED_SYMBOL}".to_string(),
+                command: "cargo".to_string(),
+                args: vec!["test".into(), "--package".into(), "task".into()],
+                env: HashMap::from_iter([
+                    ("RUST_LOG".to_string(), "debug".into()),
+                    (
+                        "EXTRA_PATHS".to_string(),
+                        EnvValue::List(vec!["bin".to_string()])
+                    ),
+                ]),
+                cwd: Some("${ZED_WORKTREE_ROOT}".to_string()),
+                reveal: RevealStrategy::Never,
+                allow_concurrent_runs: true,
+                tags: vec!["test".to_string(), "rust".to_string()],
+                ..TaskTemplate::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_building_invalid_templates() {
+        let error = TaskTemplate::builder()
+            .command("cargo")
+            .build()
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("label"),
+            "Unexpected error: {error}"
+        );
+        assert!(TaskTemplate::builder()
+            .label(" ")
+            .command("cargo")
+            .build()
+            .is_err());
+
+        let error = TaskTemplate::builder()
+            .label("deploy")
+            .command("deploy")
+            .arg("${input:region}")
+            .build()
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("`region`"),
+            "Unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn test_building_templates_referencing_others() {
+        let template = TaskTemplate::builder()
+            .label("test")
+            .command("cargo")
+            .depends_on("build")
+            .build()
+            .unwrap();
+        assert_eq!(template.depends_on, vec!["build".to_string()]);
+
+        let template = TaskTemplate::builder()
+            .label("release")
+            .extends("build")
+            .arg("--release")
+            .build()
+            .unwrap();
+        assert_eq!(template.extends.as_deref(), Some("build"));
+    }
+}
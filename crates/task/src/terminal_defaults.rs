@@ -0,0 +1,98 @@
+//! Layering of the global terminal settings under the task ones, see [`SpawnInTerminal::apply_defaults`].
+
+use std::path::PathBuf;
+
+use collections::HashMap;
+
+use crate::SpawnInTerminal;
+
+/// The terminal settings that apply to every task, unless the task sets its own values, e.g. from the terminal settings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TerminalDefaults {
+    /// The shell to run the tasks in.
+    pub shell: Option<String>,
+    /// Env variables to spawn the tasks with.
+    pub env: HashMap<String, String>,
+    /// The maximum number of lines to keep in the terminal scrollback.
+    pub scrollback: Option<usize>,
+    /// The working directory to spawn the tasks into.
+    pub cwd: Option<PathBuf>,
+}
+
+impl SpawnInTerminal {
+    /// Fills the fields the task does not set from the `defaults`: the values set by the task always win,
+    /// and the default env variables are added to the task env, unless the task sets the same variables.
+    pub fn apply_defaults(&mut self, defaults: &TerminalDefaults) {
+        if self.shell.is_none() {
+            self.shell = defaults.shell.clone();
+        }
+        if self.scrollback.is_none() {
+            self.scrollback = defaults.scrollback;
+        }
+        if self.cwd.is_none() {
+            self.cwd = defaults.cwd.clone();
+        }
+        for (key, value) in &defaults.env {
+            self.env.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_defaults() {
+        let defaults = TerminalDefaults {
+            shell: Some("/bin/zsh".to_string()),
+            env: HashMap::from_iter([
+                ("EDITOR".to_string(), "zed --wait".to_string()),
+                ("RUST_LOG".to_string(), "info".to_string()),
+            ]),
+            scrollback: Some(10_000),
+            cwd: Some(PathBuf::from("/project")),
+        };
+
+        let mut unset = SpawnInTerminal::default();
+        unset.apply_defaults(&defaults);
+        assert_eq!(unset.shell, defaults.shell);
+        assert_eq!(unset.scrollback, Some(10_000));
+        assert_eq!(unset.cwd, defaults.cwd);
+        assert_eq!(unset.env, defaults.env);
+
+        let mut set = SpawnInTerminal {
+            shell: Some("/bin/bash".to_string()),
+            scrollback: Some(100),
+            cwd: Some(PathBuf::from("/project/crates/task")),
+            env: HashMap::from_iter([("RUST_LOG".to_string(), "debug".to_string())]),
+            ..SpawnInTerminal::default()
+        };
+        set.apply_defaults(&defaults);
+        assert_eq!(set.shell.as_deref(), Some("/bin/bash"));
+        assert_eq!(set.scrollback, Some(100));
+        assert_eq!(set.cwd, Some(PathBuf::from("/project/crates/task")));
+        assert_eq!(set.env["RUST_LOG"], "debug");
+        assert_eq!(set.env["EDITOR"], "zed --wait");
+    }
+
+    #[test]
+    fn test_template_terminal_settings() {
+        let template: crate::TaskTemplate = serde_json_lenient::from_str(
+            r#"{"label": "build", "command": "make", "shell": "/bin/zsh", "scrollback": 500}"#,
+        )
+        .unwrap();
+        let mut spawn = template
+            .resolve_task("test", crate::TaskContext::default())
+            .unwrap()
+            .resolved
+            .unwrap();
+        spawn.apply_defaults(&TerminalDefaults {
+            shell: Some("/bin/bash".to_string()),
+            scrollback: Some(10_000),
+            ..TerminalDefaults::default()
+        });
+        assert_eq!(spawn.shell.as_deref(), Some("/bin/zsh"));
+        assert_eq!(spawn.scrollback, Some(500));
+    }
+}
@@ -0,0 +1,73 @@
+//! An extension point for embedders to adjust every template loaded from a source, e.g. to tag or prefix them.
+
+use crate::{TaskTemplate, TaskTemplates};
+
+/// A change applied to task templates in place, see [`TaskTemplates::transform_each`].
+/// Implemented for closures too, so that simple transforms need no dedicated types.
+pub trait TemplateTransform {
+    /// Changes the template.
+    fn apply(&self, template: &mut TaskTemplate);
+}
+
+impl<F: Fn(&mut TaskTemplate)> TemplateTransform for F {
+    fn apply(&self, template: &mut TaskTemplate) {
+        self(template)
+    }
+}
+
+impl TaskTemplates {
+    /// Applies the transforms to every template, one after another, in the order given.
+    pub fn transform_each(&mut self, transforms: &[Box<dyn TemplateTransform>]) {
+        for template in &mut self.0 {
+            for transform in transforms {
+                transform.apply(template);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct AddTag(&'static str);
+
+    impl TemplateTransform for AddTag {
+        fn apply(&self, template: &mut TaskTemplate) {
+            template.tags.push(self.0.to_string());
+        }
+    }
+
+    #[test]
+    fn test_transform_pipeline() {
+        let template = |label: &str| TaskTemplate {
+            label: label.to_string(),
+            command: "cargo".to_string(),
+            args: vec![label.into()],
+            ..TaskTemplate::default()
+        };
+        let mut templates = TaskTemplates(vec![template("build"), template("test")]);
+        // The label prefix depends on the tag added before it, checking the order of application.
+        let transforms: Vec<Box<dyn TemplateTransform>> = vec![
+            Box::new(AddTag("rust")),
+            Box::new(|template: &mut TaskTemplate| {
+                template.label = format!("{}: {}", template.tags.join(","), template.label);
+            }),
+        ];
+        templates.transform_each(&transforms);
+        assert_eq!(
+            templates
+                .0
+                .iter()
+                .map(|template| (template.label.as_str(), template.tags.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("rust: build", vec!["rust".to_string()]),
+                ("rust: test", vec!["rust".to_string()]),
+            ]
+        );
+
+        templates.transform_each(&[]);
+        assert_eq!(templates.0[0].label, "rust: build");
+    }
+}
@@ -0,0 +1,1031 @@
+//! Checks of task templates and task files, that are not required for the tasks to resolve, but help catching mistakes in them early.
+
+use std::path::{Path, PathBuf};
+
+use collections::{HashMap, HashSet};
+use serde::Serialize;
+use serde_path_to_error::Segment;
+
+use crate::{
+    args_file::{env_size_problem, DEFAULT_ENV_SIZE_LIMIT},
+    static_source::{normalize_line_endings, trim_trailing_carriage_returns},
+    OutputMode, RevealStrategy, RevealTarget, TaskTemplate, TaskTemplates, VariableName,
+    BUILTIN_VARIABLES, ZED_VARIABLE_NAME_PREFIX,
+};
+
+/// Fields of the task template that are still accepted, but should be replaced with their newer counterparts.
+/// Each entry is a pair of the deprecated field name and the field name to use instead.
+pub(crate) const DEPRECATED_TASK_FIELDS: &[(&str, &str)] = &[("name", "label")];
+
+/// How serious the issue found during validation is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    /// The task will work, but likely not in the way its author expects.
+    Warning,
+    /// The task will not work.
+    Error,
+}
+
+/// An issue found in a task template during validation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TaskDiagnostic {
+    /// How serious the issue is.
+    pub severity: DiagnosticSeverity,
+    /// Index of the problematic template in its [`TaskTemplates`], if the issue is related to a particular template.
+    pub task_index: Option<usize>,
+    /// Label of the problematic template, if the issue is related to a particular template.
+    pub label: Option<String>,
+    /// Human readable description of the issue.
+    pub message: String,
+}
+
+impl TaskDiagnostic {
+    fn for_template(
+        severity: DiagnosticSeverity,
+        task_index: usize,
+        template: &TaskTemplate,
+        message: String,
+    ) -> Self {
+        Self {
+            severity,
+            task_index: Some(task_index),
+            label: Some(template.label.clone()),
+            message,
+        }
+    }
+}
+
+impl TaskTemplates {
+    /// Checks the templates for issues that do not prevent them from being resolved, but are likely to be mistakes.
+    pub fn validate(&self) -> Vec<TaskDiagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut first_occurrences = HashMap::default();
+        for (task_index, template) in self.0.iter().enumerate() {
+            match first_occurrences.get(template.label.as_str()) {
+                Some(first_index) => diagnostics.push(TaskDiagnostic::for_template(
+                    DiagnosticSeverity::Warning,
+                    task_index,
+                    template,
+                    format!(
+                        "Duplicate label `{}`, already used by the task at index {first_index}",
+                        template.label
+                    ),
+                )),
+                None => {
+                    first_occurrences.insert(template.label.as_str(), task_index);
+                }
+            }
+            diagnostics.extend(validate_template(task_index, template));
+        }
+        diagnostics.extend(validate_dependencies(self, &first_occurrences));
+        diagnostics.extend(self.validate_variables());
+        diagnostics.extend(self.validate_env_size(DEFAULT_ENV_SIZE_LIMIT));
+        diagnostics
+    }
+
+    /// Same as [`TaskTemplates::validate`], but without the checks relating the templates to each other, such as duplicate labels
+    /// or dangling `depends_on` labels, e.g. for a template built in code, whose dependencies live elsewhere.
+    pub(crate) fn validate_standalone(&self) -> Vec<TaskDiagnostic> {
+        let mut diagnostics = self
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(task_index, template)| validate_template(task_index, template))
+            .collect::<Vec<_>>();
+        diagnostics.extend(self.validate_variables());
+        diagnostics.extend(self.validate_env_size(DEFAULT_ENV_SIZE_LIMIT));
+        diagnostics
+    }
+
+    /// Warns about the templates which `env` takes more than `limit` bytes as `KEY=value` strings, usually [`DEFAULT_ENV_SIZE_LIMIT`],
+    /// naming the largest variables. The values are measured before the variable substitution, and the `env_file` is not read,
+    /// see [`crate::SpawnInTerminal::validate_env_size`] to check the env of a resolved task.
+    pub fn validate_env_size(&self, limit: usize) -> Vec<TaskDiagnostic> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(task_index, template)| {
+                let env = template.env.iter().map(|(key, value)| {
+                    (key.as_str(), value.join(value.parts(), cfg!(windows)).len())
+                });
+                let message = env_size_problem(env, limit)?;
+                Some(TaskDiagnostic::for_template(
+                    DiagnosticSeverity::Warning,
+                    task_index,
+                    template,
+                    message,
+                ))
+            })
+            .collect()
+    }
+
+    /// Reports the references to the `ZED_`-prefixed variables that do not exist, e.g. a typo in `${ZED_SYMBL}`,
+    /// as the templates referencing them never resolve. [`VariableName::Custom`] variables (`ZED_CUSTOM_*`) are not checked,
+    /// as their providers are only known at the resolution time; references to other variables, like `$HOME`, are left alone too.
+    pub fn validate_variables(&self) -> Vec<TaskDiagnostic> {
+        let custom_prefix = VariableName::Custom("".into()).to_string();
+        let mut diagnostics = Vec::new();
+        for (task_index, template) in self.0.iter().enumerate() {
+            for reference in template.variable_references() {
+                let is_known = reference.starts_with(&custom_prefix)
+                    || BUILTIN_VARIABLES
+                        .iter()
+                        .any(|variable| variable.to_string() == reference);
+                if !is_known {
+                    diagnostics.push(TaskDiagnostic::for_template(
+                        DiagnosticSeverity::Error,
+                        task_index,
+                        template,
+                        format!(
+                            "Unknown variable `{reference}`: variables prefixed with `{ZED_VARIABLE_NAME_PREFIX}` \
+                            should be built-in ones, or custom ones prefixed with `{custom_prefix}`"
+                        ),
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+/// The checks of a single template, that do not need the other templates.
+fn validate_template(task_index: usize, template: &TaskTemplate) -> Vec<TaskDiagnostic> {
+    let mut diagnostics = validate_inputs(task_index, template);
+    diagnostics.extend(validate_run_as(task_index, template));
+    diagnostics.extend(validate_output_mode(task_index, template));
+    diagnostics.extend(validate_detached(task_index, template));
+    diagnostics.extend(validate_raw_command(task_index, template));
+    diagnostics
+}
+
+/// Reports dependencies on unknown labels, and cycles in the dependency graph, that would never let their tasks start.
+/// As for `extends`, a label is resolved to the first template having it.
+fn validate_dependencies(
+    templates: &TaskTemplates,
+    first_occurrences: &HashMap<&str, usize>,
+) -> Vec<TaskDiagnostic> {
+    let mut diagnostics = Vec::new();
+    for (task_index, template) in templates.0.iter().enumerate() {
+        for dependency in &template.depends_on {
+            if !first_occurrences.contains_key(dependency.as_str()) {
+                diagnostics.push(TaskDiagnostic::for_template(
+                    DiagnosticSeverity::Error,
+                    task_index,
+                    template,
+                    format!("Dependency `{dependency}` does not match any task label"),
+                ));
+            }
+        }
+    }
+
+    let mut visited = vec![false; templates.0.len()];
+    let mut path = Vec::new();
+    for task_index in 0..templates.0.len() {
+        find_dependency_cycles(
+            templates,
+            first_occurrences,
+            task_index,
+            &mut visited,
+            &mut path,
+            &mut diagnostics,
+        );
+    }
+    diagnostics
+}
+
+/// Walks the dependencies depth-first, reporting a cycle each time a dependency on a template from the current `path` is found.
+fn find_dependency_cycles(
+    templates: &TaskTemplates,
+    first_occurrences: &HashMap<&str, usize>,
+    task_index: usize,
+    visited: &mut [bool],
+    path: &mut Vec<usize>,
+    diagnostics: &mut Vec<TaskDiagnostic>,
+) {
+    if visited[task_index] {
+        return;
+    }
+    visited[task_index] = true;
+    path.push(task_index);
+    for dependency in &templates.0[task_index].depends_on {
+        let Some(&dependency_index) = first_occurrences.get(dependency.as_str()) else {
+            continue;
+        };
+        if let Some(cycle_start) = path.iter().position(|&index| index == dependency_index) {
+            let cycle = path[cycle_start..]
+                .iter()
+                .chain(Some(&dependency_index))
+                .map(|&index| templates.0[index].label.as_str())
+                .collect::<Vec<_>>();
+            diagnostics.push(TaskDiagnostic::for_template(
+                DiagnosticSeverity::Error,
+                dependency_index,
+                &templates.0[dependency_index],
+                format!("Circular dependency: {}", cycle.join(" -> ")),
+            ));
+        } else {
+            find_dependency_cycles(
+                templates,
+                first_occurrences,
+                dependency_index,
+                visited,
+                path,
+                diagnostics,
+            );
+        }
+    }
+    path.pop();
+}
+
+fn validate_inputs(task_index: usize, template: &TaskTemplate) -> Vec<TaskDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let references = template.input_references();
+    for input in &template.inputs {
+        if !references.contains(&input.id.as_str()) {
+            diagnostics.push(TaskDiagnostic::for_template(
+                DiagnosticSeverity::Warning,
+                task_index,
+                template,
+                format!("Input `{}` is declared but never used", input.id),
+            ));
+        }
+    }
+    let mut reported_references = HashSet::default();
+    for reference in references {
+        if !template.inputs.iter().any(|input| input.id == reference)
+            && reported_references.insert(reference)
+        {
+            diagnostics.push(TaskDiagnostic::for_template(
+                DiagnosticSeverity::Error,
+                task_index,
+                template,
+                format!("Input `{reference}` is referenced but not declared"),
+            ));
+        }
+    }
+    diagnostics
+}
+
+fn validate_run_as(task_index: usize, template: &TaskTemplate) -> Vec<TaskDiagnostic> {
+    [
+        ("run_as_user", template.run_as_user.as_deref()),
+        ("run_as_group", template.run_as_group.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(field, value)| {
+        let value = value?;
+        let is_valid = !value.trim().is_empty() && !value.contains('\0');
+        (!is_valid).then(|| {
+            TaskDiagnostic::for_template(
+                DiagnosticSeverity::Error,
+                task_index,
+                template,
+                format!("Field `{field}` should be a non-blank name or a numeric id"),
+            )
+        })
+    })
+    .collect()
+}
+
+/// Reports terminal-only settings, that have no effect when the output goes into a buffer.
+fn validate_output_mode(task_index: usize, template: &TaskTemplate) -> Vec<TaskDiagnostic> {
+    if template.output_mode != OutputMode::OpenBuffer {
+        return Vec::new();
+    }
+    [
+        (template.use_new_terminal, "use_new_terminal"),
+        (template.reveal != RevealStrategy::default(), "reveal"),
+        (
+            template.reveal_target != RevealTarget::default(),
+            "reveal_target",
+        ),
+        (template.terminal_profile.is_some(), "terminal_profile"),
+        (template.shell.is_some(), "shell"),
+        (template.scrollback.is_some(), "scrollback"),
+    ]
+    .into_iter()
+    .filter(|(is_set, _)| *is_set)
+    .map(|(_, field)| {
+        TaskDiagnostic::for_template(
+            DiagnosticSeverity::Warning,
+            task_index,
+            template,
+            format!("Field `{field}` has no effect with the `open_buffer` output mode"),
+        )
+    })
+    .collect()
+}
+
+/// Reports the arguments of the raw commands, which are not passed to the command.
+fn validate_raw_command(task_index: usize, template: &TaskTemplate) -> Option<TaskDiagnostic> {
+    (template.raw_command && !template.args.is_empty()).then(|| {
+        TaskDiagnostic::for_template(
+            DiagnosticSeverity::Error,
+            task_index,
+            template,
+            "Field `args` cannot be used with `raw_command`, put the arguments into the `command` instead"
+                .to_string(),
+        )
+    })
+}
+
+/// Reports settings that need the editor to observe the task process until it exits, which a detached process may outlive.
+fn validate_detached(task_index: usize, template: &TaskTemplate) -> Vec<TaskDiagnostic> {
+    if !template.detached {
+        return Vec::new();
+    }
+    [
+        (template.on_success.is_some(), "on_success"),
+        (template.on_failure.is_some(), "on_failure"),
+        (
+            template.output_mode == OutputMode::OpenBuffer,
+            "output_mode",
+        ),
+    ]
+    .into_iter()
+    .filter(|(is_set, _)| *is_set)
+    .map(|(_, field)| {
+        TaskDiagnostic::for_template(
+            DiagnosticSeverity::Warning,
+            task_index,
+            template,
+            format!(
+                "Field `{field}` will not take effect if the detached task outlives the editor"
+            ),
+        )
+    })
+    .collect()
+}
+
+/// A machine-readable result of [`validate_task_file`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    /// A path to the file validated.
+    pub path: PathBuf,
+    /// An error that prevented the file from being read or parsed, if any.
+    /// No other checks are made in this case.
+    pub parse_error: Option<ParseError>,
+    /// All issues found in the file.
+    pub diagnostics: Vec<TaskDiagnostic>,
+}
+
+impl ValidationReport {
+    /// Whether the file is readable and none of its issues is an error.
+    pub fn is_valid(&self) -> bool {
+        self.parse_error.is_none()
+            && self
+                .diagnostics
+                .iter()
+                .all(|diagnostic| diagnostic.severity != DiagnosticSeverity::Error)
+    }
+}
+
+/// An error of reading or parsing a tasks file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ParseError {
+    /// Human readable description of the error.
+    pub message: String,
+    /// A path to the offending value in the file, e.g. `tasks[2].args`, with the root list of tasks named `tasks`.
+    /// `None` if the error is not related to a particular value, e.g. the file is not a valid JSON.
+    pub path: Option<String>,
+    /// The kind of value expected at the `path`, for type mismatch errors, e.g. `a sequence`.
+    pub expected: Option<String>,
+    /// The value found at the `path` instead, for type mismatch errors, e.g. `string "build"`.
+    pub found: Option<String>,
+}
+
+impl ParseError {
+    pub(crate) fn new(message: String) -> Self {
+        Self {
+            message,
+            path: None,
+            expected: None,
+            found: None,
+        }
+    }
+
+    fn at(path: String, error: serde_json_lenient::Error) -> Self {
+        let error = error.to_string();
+        let (found, expected) = match error
+            .strip_prefix("invalid type: ")
+            .or_else(|| error.strip_prefix("invalid value: "))
+            .and_then(|mismatch| mismatch.split_once(", expected "))
+        {
+            Some((found, expected)) => (Some(found.to_string()), Some(expected.to_string())),
+            None => (None, None),
+        };
+        Self {
+            message: format!("Failed to parse `{path}`: {error}"),
+            path: Some(path),
+            expected,
+            found,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses the contents of a Zed tasks file,
+/// pointing at the offending value in the file if some template cannot be deserialized, e.g. has a field of a wrong type.
+pub fn parse_task_file(contents: &str) -> Result<TaskTemplates, ParseError> {
+    let contents = normalize_line_endings(contents);
+    let raw_tasks = serde_json_lenient::from_str::<serde_json_lenient::Value>(&contents)
+        .map_err(|e| ParseError::new(format!("Failed to parse the file: {e}")))?;
+    parse_raw_templates(&raw_tasks)
+}
+
+/// Deserializes the templates of the document, checking its format version and migrating it first, see [`TaskTemplates::migrate`].
+fn parse_raw_templates(document: &serde_json_lenient::Value) -> Result<TaskTemplates, ParseError> {
+    TaskTemplates::validate_against_schema_version(document)
+        .map_err(|e| ParseError::new(e.to_string()))?;
+    let mut raw_tasks = document.clone();
+    TaskTemplates::migrate(&mut raw_tasks);
+    deserialize_templates(raw_tasks)
+}
+
+/// Deserializes the migrated templates, pointing at the offending value if some template cannot be deserialized,
+/// e.g. `tasks[2].args` for a field of a wrong type, with the root list of tasks named `tasks`.
+pub(crate) fn deserialize_templates(
+    raw_tasks: serde_json_lenient::Value,
+) -> Result<TaskTemplates, ParseError> {
+    let mut templates = serde_path_to_error::deserialize(raw_tasks).map_err(|error| {
+        let mut path = "tasks".to_string();
+        for segment in error.path().iter() {
+            match segment {
+                Segment::Seq { index } => path.push_str(&format!("[{index}]")),
+                Segment::Map { key } => path.push_str(&format!(".{key}")),
+                Segment::Enum { .. } => {}
+                Segment::Unknown => break,
+            }
+        }
+        ParseError::at(path, error.into_inner())
+    })?;
+    trim_trailing_carriage_returns(&mut templates);
+    Ok(templates)
+}
+
+/// Loads and checks a Zed tasks file, reporting all issues found, for external tooling and CI to use.
+pub fn validate_task_file(path: &Path) -> ValidationReport {
+    let mut report = ValidationReport {
+        path: path.to_path_buf(),
+        parse_error: None,
+        diagnostics: Vec::new(),
+    };
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            report.parse_error = Some(ParseError::new(format!("Failed to read the file: {e}")));
+            return report;
+        }
+    };
+    let contents = normalize_line_endings(&contents);
+    let raw_tasks = match serde_json_lenient::from_str::<serde_json_lenient::Value>(&contents) {
+        Ok(raw_tasks) => raw_tasks,
+        Err(e) => {
+            report.parse_error = Some(ParseError::new(format!("Failed to parse the file: {e}")));
+            return report;
+        }
+    };
+    let templates = match parse_raw_templates(&raw_tasks) {
+        Ok(templates) => templates,
+        Err(e) => {
+            report.parse_error = Some(e);
+            return report;
+        }
+    };
+
+    // Warn about the fields as written, before the migration renames the deprecated ones.
+    let unmigrated_tasks = match raw_tasks.get("tasks") {
+        Some(tasks) if raw_tasks.is_object() => tasks,
+        _ => &raw_tasks,
+    };
+    report
+        .diagnostics
+        .extend(parse_warnings(unmigrated_tasks, &templates));
+    report.diagnostics.extend(templates.validate());
+    for (task_index, template) in templates.0.iter().enumerate() {
+        if !template.is_runnable() {
+            report.diagnostics.push(TaskDiagnostic::for_template(
+                DiagnosticSeverity::Error,
+                task_index,
+                template,
+                "Task is not runnable: label should not be blank, and either command or action should be set"
+                    .to_string(),
+            ));
+        }
+    }
+    report
+        .diagnostics
+        .sort_by_key(|diagnostic| diagnostic.task_index);
+    report
+}
+
+/// Reports fields of the raw task definitions that are deprecated or not known, and hence ignored.
+fn parse_warnings(
+    raw_tasks: &serde_json_lenient::Value,
+    templates: &TaskTemplates,
+) -> Vec<TaskDiagnostic> {
+    let known_fields = known_task_fields();
+    let mut warnings = Vec::new();
+    let Some(raw_tasks) = raw_tasks.as_array() else {
+        return warnings;
+    };
+    for (task_index, (raw_task, template)) in raw_tasks.iter().zip(&templates.0).enumerate() {
+        let Some(raw_task) = raw_task.as_object() else {
+            continue;
+        };
+        for field in raw_task.keys() {
+            let message = if let Some((_, replacement)) = DEPRECATED_TASK_FIELDS
+                .iter()
+                .find(|(deprecated, _)| deprecated == field)
+            {
+                format!("Field `{field}` is deprecated, use `{replacement}` instead")
+            } else if !known_fields.contains(field.as_str()) {
+                format!("Unknown field `{field}` is ignored")
+            } else {
+                continue;
+            };
+            warnings.push(TaskDiagnostic::for_template(
+                DiagnosticSeverity::Warning,
+                task_index,
+                template,
+                message,
+            ));
+        }
+    }
+    warnings
+}
+
+fn known_task_fields() -> HashSet<String> {
+    let schema = TaskTemplates::generate_json_schema();
+    schema
+        .pointer("/definitions/TaskTemplate/properties")
+        .and_then(|properties| properties.as_object())
+        .map(|properties| properties.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnvValue, TaskInput};
+
+    use super::*;
+
+    #[test]
+    fn test_task_file_validation_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+        std::fs::write(
+            &path,
+            r#"[
+                {
+                    "label": "build",
+                    "command": "cargo",
+                    "args": ["build"]
+                },
+                {
+                    "label": "build",
+                    "command": "make"
+                },
+                {
+                    "label": "empty",
+                    "command": "  "
+                },
+                {
+                    "name": "old style",
+                    "command": "echo"
+                },
+            ]"#,
+        )
+        .unwrap();
+
+        let report = validate_task_file(&path);
+        assert_eq!(report.parse_error, None);
+        assert!(!report.is_valid());
+        let diagnostics = report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.severity,
+                    diagnostic.task_index,
+                    diagnostic.label.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (DiagnosticSeverity::Warning, Some(1), Some("build")),
+                (DiagnosticSeverity::Error, Some(2), Some("empty")),
+                (DiagnosticSeverity::Warning, Some(3), Some("old style")),
+            ]
+        );
+        assert!(report.diagnostics[0].message.contains("Duplicate label"));
+        assert!(report.diagnostics[1].message.contains("not runnable"));
+        assert!(report.diagnostics[2].message.contains("deprecated"));
+        assert!(serde_json_lenient::to_string(&report).is_ok());
+    }
+
+    #[test]
+    fn test_input_declarations_and_references() {
+        let input = |id: &str| TaskInput {
+            id: id.to_string(),
+            ..TaskInput::default()
+        };
+        let matched = TaskTemplate {
+            label: "deploy".to_string(),
+            command: "deploy".to_string(),
+            args: vec!["--env=${input:environment}".into()],
+            inputs: vec![input("environment")],
+            ..TaskTemplate::default()
+        };
+        assert_eq!(TaskTemplates(vec![matched.clone()]).validate(), Vec::new());
+
+        let unused = TaskTemplate {
+            inputs: vec![input("environment"), input("region")],
+            ..matched.clone()
+        };
+        let diagnostics = TaskTemplates(vec![unused]).validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert!(diagnostics[0].message.contains("`region`"));
+
+        let undeclared = TaskTemplate {
+            env: HashMap::from_iter([("REGION".to_string(), "${input:region}".into())]),
+            ..matched
+        };
+        let diagnostics = TaskTemplates(vec![undeclared]).validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert!(diagnostics[0].message.contains("`region`"));
+    }
+
+    #[test]
+    fn test_unreadable_task_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = validate_task_file(&dir.path().join("missing.json"));
+        assert!(report.parse_error.is_some());
+        assert!(report.diagnostics.is_empty());
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn test_blank_run_as_values() {
+        let template = TaskTemplate {
+            label: "serve".to_string(),
+            command: "serve".to_string(),
+            run_as_user: Some("www-data".to_string()),
+            run_as_group: Some("1000".to_string()),
+            ..TaskTemplate::default()
+        };
+        assert_eq!(TaskTemplates(vec![template.clone()]).validate(), Vec::new());
+
+        let blank = TaskTemplate {
+            run_as_user: Some(" ".to_string()),
+            ..template
+        };
+        let diagnostics = TaskTemplates(vec![blank]).validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert!(diagnostics[0].message.contains("`run_as_user`"));
+    }
+
+    #[test]
+    fn test_terminal_settings_with_buffer_output() {
+        let buffer_template = TaskTemplate {
+            label: "outdated".to_string(),
+            command: "cargo".to_string(),
+            args: vec!["outdated".into()],
+            output_mode: OutputMode::OpenBuffer,
+            ..TaskTemplate::default()
+        };
+        assert_eq!(
+            TaskTemplates(vec![buffer_template.clone()]).validate(),
+            Vec::new()
+        );
+
+        let contradictory = TaskTemplate {
+            use_new_terminal: true,
+            reveal: RevealStrategy::Never,
+            reveal_target: RevealTarget::Center,
+            terminal_profile: Some("ssh".to_string()),
+            shell: Some("/bin/zsh".to_string()),
+            scrollback: Some(100),
+            ..buffer_template
+        };
+        let diagnostics = TaskTemplates(vec![contradictory.clone()]).validate();
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == DiagnosticSeverity::Warning));
+        let fields = [
+            "use_new_terminal",
+            "reveal",
+            "reveal_target",
+            "terminal_profile",
+            "shell",
+            "scrollback",
+        ];
+        assert_eq!(diagnostics.len(), fields.len());
+        for (diagnostic, field) in diagnostics.iter().zip(fields) {
+            assert!(
+                diagnostic.message.contains(&format!("`{field}`")),
+                "Expected a warning about `{field}`, got: {}",
+                diagnostic.message
+            );
+        }
+
+        let terminal_template = TaskTemplate {
+            output_mode: OutputMode::Terminal,
+            ..contradictory
+        };
+        assert_eq!(
+            TaskTemplates(vec![terminal_template]).validate(),
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_point_at_values() {
+        let error = parse_task_file(
+            r#"[
+                {"label": "build", "command": "cargo"},
+                {"label": "test", "command": "cargo", "args": ["test"]},
+                {"label": "run", "command": "cargo", "args": "run"}
+            ]"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.path.as_deref(), Some("tasks[2].args"));
+        assert_eq!(error.expected.as_deref(), Some("a sequence"));
+        assert_eq!(error.found.as_deref(), Some(r#"string "run""#));
+        assert!(
+            error.message.contains("tasks[2].args"),
+            "Unexpected message: {error}"
+        );
+
+        let error =
+            parse_task_file(r#"[{"label": "run", "command": "cargo", "env": {"A": "a", "B": 2}}]"#)
+                .unwrap_err();
+        assert_eq!(error.path.as_deref(), Some("tasks[0].env.B"));
+
+        let error = parse_task_file(r#"[{"command": "cargo"}]"#).unwrap_err();
+        assert_eq!(error.path.as_deref(), Some("tasks[0]"));
+        assert!(
+            error.message.contains("label"),
+            "Unexpected message: {error}"
+        );
+
+        let error = parse_task_file(r#"{"label": "run"}"#).unwrap_err();
+        assert_eq!(error.path.as_deref(), Some("tasks"));
+
+        let error = parse_task_file("[{").unwrap_err();
+        assert_eq!(error.path, None);
+
+        let load_error = TaskTemplates::try_from(serde_json_lenient::json!([
+            {"label": "run", "command": "cargo", "args": "run"}
+        ]))
+        .unwrap_err();
+        assert!(
+            format!("{load_error:#}").contains("tasks[0].args"),
+            "The load status should point at the offending value: {load_error:#}"
+        );
+
+        assert_eq!(
+            parse_task_file(r#"[{"name": "old", "command": "echo", "args": ["a"]}]"#)
+                .unwrap()
+                .0[0]
+                .label,
+            "old"
+        );
+    }
+
+    #[test]
+    fn test_parsing_versioned_files() {
+        let templates = parse_task_file(
+            r#"{"version": 1, "tasks": [{"name": "build", "command": "cargo", "args": ["build"]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(templates.0.len(), 1);
+        assert_eq!(templates.0[0].label, "build");
+
+        let error = parse_task_file(
+            r#"{"version": 1, "tasks": [{"label": "run", "command": "cargo", "args": "run"}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.path.as_deref(), Some("tasks[0].args"));
+
+        let error = parse_task_file(r#"{"version": 99, "tasks": []}"#).unwrap_err();
+        assert!(
+            error.message.contains("requires a newer Zed"),
+            "Unexpected message: {error}"
+        );
+        assert_eq!(error.path, None);
+
+        let summary = crate::task_files_equivalent(
+            r#"[{"label": "build", "command": "cargo"}]"#,
+            r#"{"version": 1, "tasks": [{"label": "build", "command": "cargo"}]}"#,
+            crate::TaskFileFormat::Zed,
+        )
+        .unwrap();
+        assert!(summary.is_equivalent());
+    }
+
+    #[test]
+    fn test_detached_tasks_with_completion_handling() {
+        let template = serde_json_lenient::from_str::<TaskTemplate>(
+            r#"{"label": "serve", "command": "python", "args": ["-m", "http.server"]}"#,
+        )
+        .unwrap();
+        assert!(!template.detached);
+        let detached = serde_json_lenient::from_str::<TaskTemplate>(
+            r#"{"label": "serve", "command": "python", "args": ["-m", "http.server"], "detached": true}"#,
+        )
+        .unwrap();
+        assert!(detached.detached);
+        assert_eq!(TaskTemplates(vec![detached.clone()]).validate(), Vec::new());
+
+        let contradictory = TaskTemplate {
+            on_failure: Some("notify-send 'server died'".to_string()),
+            output_mode: OutputMode::OpenBuffer,
+            ..detached
+        };
+        let diagnostics = TaskTemplates(vec![contradictory.clone()]).validate();
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == DiagnosticSeverity::Warning));
+        assert!(diagnostics[0].message.contains("`on_failure`"));
+        assert!(diagnostics[1].message.contains("`output_mode`"));
+
+        let attached = TaskTemplate {
+            detached: false,
+            output_mode: OutputMode::Terminal,
+            ..contradictory
+        };
+        assert_eq!(TaskTemplates(vec![attached]).validate(), Vec::new());
+    }
+
+    #[test]
+    fn test_raw_command_with_args() {
+        let raw = serde_json_lenient::from_str::<TaskTemplate>(
+            r#"{"label": "count", "command": "ls | wc -l", "raw_command": true}"#,
+        )
+        .unwrap();
+        assert!(raw.raw_command);
+        assert_eq!(TaskTemplates(vec![raw.clone()]).validate(), Vec::new());
+
+        let with_args = TaskTemplate {
+            args: vec!["-a".into()],
+            ..raw
+        };
+        let diagnostics = TaskTemplates(vec![with_args]).validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert!(diagnostics[0].message.contains("`raw_command`"));
+    }
+
+    #[test]
+    fn test_dependency_cycles_and_unknown_dependencies() {
+        let template = |label: &str, depends_on: &[&str]| TaskTemplate {
+            label: label.to_string(),
+            command: "make".to_string(),
+            args: vec![label.into()],
+            depends_on: depends_on.iter().map(|label| label.to_string()).collect(),
+            ..TaskTemplate::default()
+        };
+        let messages = |templates: Vec<TaskTemplate>| {
+            TaskTemplates(templates)
+                .validate()
+                .into_iter()
+                .map(|diagnostic| {
+                    assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
+                    (diagnostic.task_index.unwrap(), diagnostic.message)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            messages(vec![
+                template("lint", &[]),
+                template("build", &["lint"]),
+                template("test", &["build", "lint"]),
+            ]),
+            Vec::new()
+        );
+        assert_eq!(
+            messages(vec![template("a", &["b"]), template("b", &["a"])]),
+            vec![(0, "Circular dependency: a -> b -> a".to_string())]
+        );
+        assert_eq!(
+            messages(vec![
+                template("lint", &[]),
+                template("build", &["lint", "deploy"]),
+                template("test", &["build"]),
+                template("deploy", &["test"]),
+            ]),
+            vec![(
+                1,
+                "Circular dependency: build -> deploy -> test -> build".to_string()
+            )]
+        );
+        assert_eq!(
+            messages(vec![template("self", &["self"])]),
+            vec![(0, "Circular dependency: self -> self".to_string())]
+        );
+        assert_eq!(
+            messages(vec![template("build", &["codegen"])]),
+            vec![(
+                0,
+                "Dependency `codegen` does not match any task label".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unknown_variables() {
+        let templates = TaskTemplates(vec![
+            TaskTemplate {
+                label: "run ${ZED_SYMBL}".to_string(),
+                command: "cargo".to_string(),
+                args: vec![
+                    "--line=${ZED_ROW}".into(),
+                    "--next=${ZED_ROW+1}".into(),
+                    "--home=${HOME}".into(),
+                    "$PATH".into(),
+                    "${ZED_CUSTOM_PACKAGE}".into(),
+                    "${ZED_FILE:unix}".into(),
+                ],
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "open".to_string(),
+                command: "open".to_string(),
+                cwd: Some("$ZED_WORKTREE_ROOT".to_string()),
+                ..TaskTemplate::default()
+            },
+        ]);
+        let diagnostics = templates.validate_variables();
+        assert_eq!(
+            diagnostics.len(),
+            1,
+            "Unexpected diagnostics: {diagnostics:?}"
+        );
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert_eq!(diagnostics[0].task_index, Some(0));
+        assert!(
+            diagnostics[0].message.contains("`ZED_SYMBL`"),
+            "Unexpected message: {}",
+            diagnostics[0].message
+        );
+        assert_eq!(templates.validate(), diagnostics);
+    }
+
+    #[test]
+    fn test_env_size() {
+        let templates = TaskTemplates(vec![
+            TaskTemplate {
+                label: "normal".to_string(),
+                command: "make".to_string(),
+                env: HashMap::from_iter([("CC".to_string(), "clang".into())]),
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "oversized".to_string(),
+                command: "make".to_string(),
+                env: HashMap::from_iter([
+                    ("CC".to_string(), "clang".into()),
+                    (
+                        "INCLUDE_DIRS".to_string(),
+                        EnvValue::List(vec!["/usr/include/very/long/path".to_string(); 1000]),
+                    ),
+                ]),
+                ..TaskTemplate::default()
+            },
+        ]);
+        let diagnostics = templates.validate_env_size(16_000);
+        assert_eq!(
+            diagnostics.len(),
+            1,
+            "Unexpected diagnostics: {diagnostics:?}"
+        );
+        assert_eq!(diagnostics[0].task_index, Some(1));
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert!(
+            diagnostics[0]
+                .message
+                .contains("`INCLUDE_DIRS` (28013 bytes), `CC` (9 bytes)"),
+            "Unexpected message: {}",
+            diagnostics[0].message
+        );
+        assert!(templates
+            .validate_env_size(DEFAULT_ENV_SIZE_LIMIT)
+            .is_empty());
+    }
+}
@@ -1,30 +1,41 @@
+use std::collections::BTreeSet;
+
 use anyhow::bail;
 use collections::HashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use util::ResultExt;
 
-use crate::{TaskTemplate, TaskTemplates, VariableName};
+use crate::{
+    serialize_sorted_map, EnvValue, RevealStrategy, TaskArg, TaskTemplate, TaskTemplates,
+    VariableName, ZED_VARIABLE_NAME_PREFIX,
+};
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 struct TaskOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
     cwd: Option<String>,
-    #[serde(default)]
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted_map"
+    )]
     env: HashMap<String, String>,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 struct VsCodeTaskDefinition {
     label: String,
     #[serde(flatten)]
     command: Option<Command>,
-    #[serde(flatten)]
+    #[serde(flatten, serialize_with = "serialize_sorted_map")]
     other_attributes: HashMap<String, serde_json_lenient::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<TaskOptions>,
 }
 
-#[derive(Clone, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Deserialize, Serialize, PartialEq, Debug)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 enum Command {
@@ -85,16 +96,25 @@ impl EnvVariableReplacer {
 
 impl VsCodeTaskDefinition {
     fn to_zed_format(self, replacer: &EnvVariableReplacer) -> anyhow::Result<TaskTemplate> {
-        if self.other_attributes.contains_key("dependsOn") {
-            bail!("Encountered unsupported `dependsOn` key during deserialization");
-        }
         // `type` might not be set in e.g. tasks that use `dependsOn`; we still want to deserialize the whole object though (hence command is an Option),
         // as that way we can provide more specific description of why deserialization failed.
-        // E.g. if the command is missing due to `dependsOn` presence, we can check other_attributes first before doing this (and provide nice error message)
-        // catch-all if on value.command presence.
+        // E.g. if the command is missing due to `dependsOn` presence, we can check other_attributes first (and provide nice error message)
+        // before the catch-all error on value.command presence.
         let Some(command) = self.command else {
+            if self.other_attributes.contains_key("dependsOn") {
+                bail!("Encountered unsupported `dependsOn` key without a command during deserialization");
+            }
             bail!("Missing `type` field in task");
         };
+        // Code accepts both a single label and a list of them.
+        let depends_on = match self.other_attributes.get("dependsOn") {
+            Some(serde_json_lenient::Value::String(label)) => vec![label.clone()],
+            Some(serde_json_lenient::Value::Array(labels)) => labels
+                .iter()
+                .filter_map(|label| Some(label.as_str()?.to_owned()))
+                .collect(),
+            _ => Vec::new(),
+        };
 
         let (command, args) = match command {
             Command::Npm { script } => ("npm".to_owned(), vec!["run".to_string(), script]),
@@ -103,43 +123,213 @@ impl VsCodeTaskDefinition {
         };
         // Per VSC docs, only `command`, `args` and `options` support variable substitution.
         let command = replacer.replace(&command);
-        let args = args.into_iter().map(|arg| replacer.replace(&arg)).collect();
+        let args = args
+            .into_iter()
+            .map(|arg| TaskArg::Plain(replacer.replace(&arg)))
+            .collect();
+        let group = self.other_attributes.get("group");
+        let category = group.and_then(category_from_group);
+        let is_default_for_group = category.is_some() && group.is_some_and(is_default_group);
         let mut ret = TaskTemplate {
             label: self.label,
             command,
             args,
+            category,
+            is_default_for_group,
+            depends_on,
             ..Default::default()
         };
         if let Some(options) = self.options {
             ret.cwd = options.cwd.map(|cwd| replacer.replace(&cwd));
-            ret.env = options.env;
+            ret.env = options
+                .env
+                .into_iter()
+                .map(|(key, value)| (key, EnvValue::String(value)))
+                .collect();
         }
         Ok(ret)
     }
 }
 
+/// Code task `group` is either a kind name or an object with the `kind` field, e.g. `{"kind": "build", "isDefault": true}`.
+fn category_from_group(group: &serde_json_lenient::Value) -> Option<String> {
+    let kind = match group {
+        serde_json_lenient::Value::Object(group) => group.get("kind")?.as_str()?,
+        group => group.as_str()?,
+    };
+    if kind.is_empty() || kind == "none" {
+        return None;
+    }
+    let mut chars = kind.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+/// Whether the Code task `group` object marks the default task of its kind; the kind name form is never the default.
+fn is_default_group(group: &serde_json_lenient::Value) -> bool {
+    group
+        .get("isDefault")
+        .and_then(serde_json_lenient::Value::as_bool)
+        .unwrap_or(false)
+}
+
+impl VsCodeTaskDefinition {
+    fn from_zed_format(template: &TaskTemplate, replacer: &ZedVariableReplacer) -> Self {
+        let mut untranslated = BTreeSet::new();
+        let mut replace = |input: &str| replacer.replace(input, &mut untranslated);
+        // Code has no conditional arguments, so those are left out rather than passed unconditionally.
+        let mut conditional_args = Vec::new();
+        let mut args = Vec::new();
+        for arg in &template.args {
+            match arg {
+                TaskArg::Plain(arg) => args.push(replace(arg)),
+                TaskArg::Conditional { value, when } => {
+                    conditional_args.push(format!("`{value}` (when `{when}`)"))
+                }
+            }
+        }
+        let command = Command::Shell {
+            command: replace(&template.command),
+            args,
+        };
+        let cwd = template.cwd.as_deref().map(&mut replace);
+        let options = (cwd.is_some() || !template.env.is_empty()).then(|| TaskOptions {
+            cwd,
+            // Code has no list values, so those are joined the same way Zed would do on this platform.
+            env: template
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.join(value.parts(), cfg!(windows))))
+                .collect(),
+        });
+
+        let mut other_attributes = HashMap::default();
+        let mut presentation = serde_json_lenient::Map::new();
+        if template.reveal == RevealStrategy::Never {
+            presentation.insert("reveal".to_owned(), "never".into());
+        }
+        if template.use_new_terminal {
+            presentation.insert("panel".to_owned(), "new".into());
+        }
+        // Code only knows the build and test groups.
+        if let Some(group) = template
+            .category
+            .as_deref()
+            .map(str::to_lowercase)
+            .filter(|category| category == "build" || category == "test")
+        {
+            other_attributes.insert("group".to_owned(), group.into());
+        }
+        if !presentation.is_empty() {
+            other_attributes.insert("presentation".to_owned(), presentation.into());
+        }
+        // JSON has no comments, hence the task description is the closest place to point at the variables Code won't substitute.
+        let mut details = Vec::new();
+        if !untranslated.is_empty() {
+            let untranslated = untranslated.into_iter().collect::<Vec<_>>().join(", ");
+            details.push(format!(
+                "Zed variables without Code equivalents: {untranslated}"
+            ));
+        }
+        if !conditional_args.is_empty() {
+            details.push(format!(
+                "Conditional arguments left out: {}",
+                conditional_args.join(", ")
+            ));
+        }
+        if !details.is_empty() {
+            other_attributes.insert("detail".to_owned(), details.join("; ").into());
+        }
+        Self {
+            label: template.label.clone(),
+            command: Some(command),
+            other_attributes,
+            options,
+        }
+    }
+}
+
+/// Converts `$ZED_*` variable references into their Code counterparts, the reverse of [`EnvVariableReplacer`].
+struct ZedVariableReplacer {
+    variables: HashMap<ZedEnvVariable, VsCodeEnvVariable>,
+}
+
+impl ZedVariableReplacer {
+    fn replace(&self, input: &str, untranslated: &mut BTreeSet<String>) -> String {
+        shellexpand::env_with_context_no_errors(&input, |var: &str| {
+            let variable_name = var.split(':').next().unwrap_or(var);
+            if !variable_name.starts_with(ZED_VARIABLE_NAME_PREFIX) {
+                return None;
+            }
+            // Code has no default values for its variables, so those are dropped.
+            if let Some(substitution) = self.variables.get(variable_name) {
+                return Some(format!("${{{substitution}}}"));
+            }
+            untranslated.insert(variable_name.to_owned());
+            Some(format!("${{{var}}}"))
+        })
+        .into_owned()
+    }
+}
+
+/// Code's predefined variables along with the Zed variables they correspond to.
+fn variable_mapping() -> [(VsCodeEnvVariable, ZedEnvVariable); 5] {
+    [
+        (
+            "workspaceFolder".to_owned(),
+            VariableName::WorktreeRoot.to_string(),
+        ),
+        ("file".to_owned(), VariableName::File.to_string()),
+        (
+            "relativeFile".to_owned(),
+            VariableName::RelativeFile.to_string(),
+        ),
+        ("lineNumber".to_owned(), VariableName::Row.to_string()),
+        (
+            "selectedText".to_owned(),
+            VariableName::SelectedText.to_string(),
+        ),
+    ]
+}
+
 /// [`VsCodeTaskFile`] is a superset of Code's task definition format.
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct VsCodeTaskFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
     tasks: Vec<VsCodeTaskDefinition>,
 }
 
+impl VsCodeTaskFile {
+    /// Exports the templates into Code's `tasks.json` format, as shell tasks.
+    /// Zed variables are replaced with Code's predefined ones where possible, the rest are kept as is and listed in the task's `detail`.
+    /// Templates that invoke Zed actions have no Code counterpart and are skipped.
+    pub fn from_templates(templates: &TaskTemplates) -> Self {
+        let replacer = ZedVariableReplacer {
+            variables: HashMap::from_iter(
+                variable_mapping()
+                    .into_iter()
+                    .map(|(vscode_variable, zed_variable)| (zed_variable, vscode_variable)),
+            ),
+        };
+        let tasks = templates
+            .0
+            .iter()
+            .filter(|template| template.action.is_none())
+            .map(|template| VsCodeTaskDefinition::from_zed_format(template, &replacer))
+            .collect();
+        Self {
+            version: Some("2.0.0".to_owned()),
+            tasks,
+        }
+    }
+}
+
 impl TryFrom<VsCodeTaskFile> for TaskTemplates {
     type Error = anyhow::Error;
 
     fn try_from(value: VsCodeTaskFile) -> Result<Self, Self::Error> {
-        let replacer = EnvVariableReplacer::new(HashMap::from_iter([
-            (
-                "workspaceFolder".to_owned(),
-                VariableName::WorktreeRoot.to_string(),
-            ),
-            ("file".to_owned(), VariableName::File.to_string()),
-            ("lineNumber".to_owned(), VariableName::Row.to_string()),
-            (
-                "selectedText".to_owned(),
-                VariableName::SelectedText.to_string(),
-            ),
-        ]));
+        let replacer = EnvVariableReplacer::new(HashMap::from_iter(variable_mapping()));
         let templates = value
             .tasks
             .into_iter()
@@ -155,7 +345,7 @@ mod tests {
 
     use crate::{
         vscode_format::{Command, VsCodeTaskDefinition},
-        TaskTemplate, TaskTemplates, VsCodeTaskFile,
+        RevealStrategy, TaskTemplate, TaskTemplates, VsCodeTaskFile,
     };
 
     use super::EnvVariableReplacer;
@@ -256,30 +446,34 @@ mod tests {
             TaskTemplate {
                 label: "gulp: tests".to_string(),
                 command: "npm".to_string(),
-                args: vec!["run".to_string(), "build:tests:notypecheck".to_string()],
+                args: vec!["run".into(), "build:tests:notypecheck".into()],
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
             TaskTemplate {
                 label: "tsc: watch ./src".to_string(),
                 command: "node".to_string(),
                 args: vec![
-                    "${ZED_WORKTREE_ROOT}/node_modules/typescript/lib/tsc.js".to_string(),
-                    "--build".to_string(),
-                    "${ZED_WORKTREE_ROOT}/src".to_string(),
-                    "--watch".to_string(),
+                    "${ZED_WORKTREE_ROOT}/node_modules/typescript/lib/tsc.js".into(),
+                    "--build".into(),
+                    "${ZED_WORKTREE_ROOT}/src".into(),
+                    "--watch".into(),
                 ],
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
             TaskTemplate {
                 label: "npm: build:compiler".to_string(),
                 command: "npm".to_string(),
-                args: vec!["run".to_string(), "build:compiler".to_string()],
+                args: vec!["run".into(), "build:compiler".into()],
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
             TaskTemplate {
                 label: "npm: build:tests".to_string(),
                 command: "npm".to_string(),
-                args: vec!["run".to_string(), "build:tests:notypecheck".to_string()],
+                args: vec!["run".into(), "build:tests:notypecheck".into()],
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
         ];
@@ -359,33 +553,219 @@ mod tests {
             TaskTemplate {
                 label: "Build Extension in Background".to_string(),
                 command: "npm".to_string(),
-                args: vec!["run".to_string(), "watch".to_string()],
+                args: vec!["run".into(), "watch".into()],
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
             TaskTemplate {
                 label: "Build Extension".to_string(),
                 command: "npm".to_string(),
-                args: vec!["run".to_string(), "build".to_string()],
+                args: vec!["run".into(), "build".into()],
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
             TaskTemplate {
                 label: "Build Server".to_string(),
                 command: "cargo build --package rust-analyzer".to_string(),
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
             TaskTemplate {
                 label: "Build Server (Release)".to_string(),
                 command: "cargo build --release --package rust-analyzer".to_string(),
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
             TaskTemplate {
                 label: "Pretest".to_string(),
                 command: "npm".to_string(),
-                args: vec!["run".to_string(), "pretest".to_string()],
+                args: vec!["run".into(), "pretest".into()],
+                category: Some("Build".to_string()),
                 ..Default::default()
             },
         ];
         let tasks: TaskTemplates = vscode_definitions.try_into().unwrap();
         assert_eq!(tasks.0, expected);
     }
+
+    #[test]
+    fn test_export_round_trip() {
+        let templates = TaskTemplates(vec![
+            TaskTemplate {
+                label: "test current file".to_string(),
+                command: "cargo".to_string(),
+                args: vec![
+                    "test".into(),
+                    "${ZED_FILE}:${ZED_ROW}".into(),
+                    "--".into(),
+                    "${ZED_SYMBOL}".into(),
+                ],
+                cwd: Some("${ZED_WORKTREE_ROOT}/crates".to_string()),
+                env: HashMap::from_iter([("RUST_LOG".to_string(), "$RUST_LOG".into())]),
+                use_new_terminal: true,
+                reveal: RevealStrategy::Never,
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "echo selection".to_string(),
+                command: "echo".to_string(),
+                args: vec!["$ZED_SELECTED_TEXT".into()],
+                ..TaskTemplate::default()
+            },
+            TaskTemplate {
+                label: "open terminal".to_string(),
+                action: Some("workspace::NewTerminal".to_string()),
+                ..TaskTemplate::default()
+            },
+        ]);
+
+        let exported = VsCodeTaskFile::from_templates(&templates);
+        let serialized = serde_json_lenient::to_value(&exported).unwrap();
+        assert_eq!(serialized["version"], "2.0.0");
+        assert_eq!(
+            serialized["tasks"][0],
+            serde_json_lenient::json!({
+                "label": "test current file",
+                "type": "shell",
+                "command": "cargo",
+                "args": ["test", "${file}:${lineNumber}", "--", "${ZED_SYMBOL}"],
+                "options": {
+                    "cwd": "${workspaceFolder}/crates",
+                    "env": {"RUST_LOG": "$RUST_LOG"},
+                },
+                "presentation": {"reveal": "never", "panel": "new"},
+                "detail": "Zed variables without Code equivalents: ZED_SYMBOL",
+            })
+        );
+        assert_eq!(
+            serialized["tasks"].as_array().unwrap().len(),
+            2,
+            "Action templates should not be exported"
+        );
+
+        let reimported: VsCodeTaskFile =
+            serde_json_lenient::from_str(&serde_json_lenient::to_string(&exported).unwrap())
+                .unwrap();
+        let reimported: TaskTemplates = reimported.try_into().unwrap();
+        assert_eq!(
+            reimported.0,
+            vec![
+                TaskTemplate {
+                    use_new_terminal: false,
+                    reveal: RevealStrategy::default(),
+                    ..templates.0[0].clone()
+                },
+                TaskTemplate {
+                    args: vec!["${ZED_SELECTED_TEXT}".into()],
+                    ..templates.0[1].clone()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_to_category_mapping() {
+        let vscode_definitions: VsCodeTaskFile = serde_json_lenient::from_str(
+            r#"{
+                "version": "2.0.0",
+                "tasks": [
+                    {"label": "build", "type": "shell", "command": "make", "group": "build"},
+                    {"label": "test", "type": "shell", "command": "make test", "group": {"kind": "test", "isDefault": true}},
+                    {"label": "clean", "type": "shell", "command": "make clean", "group": "none"},
+                    {"label": "run", "type": "shell", "command": "make run"},
+                ],
+            }"#,
+        )
+        .unwrap();
+        let tasks: TaskTemplates = vscode_definitions.try_into().unwrap();
+        assert_eq!(
+            tasks
+                .0
+                .iter()
+                .map(|task| task.category.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("Build"), Some("Test"), None, None]
+        );
+
+        let exported =
+            serde_json_lenient::to_value(VsCodeTaskFile::from_templates(&tasks)).unwrap();
+        assert_eq!(exported["tasks"][0]["group"], "build");
+        assert_eq!(exported["tasks"][1]["group"], "test");
+        assert!(exported["tasks"][2].get("group").is_none());
+    }
+
+    #[test]
+    fn test_default_group_tasks() {
+        let vscode_definitions: VsCodeTaskFile = serde_json_lenient::from_str(
+            r#"{
+                "version": "2.0.0",
+                "tasks": [
+                    {"label": "build", "type": "shell", "command": "make", "group": "build"},
+                    {"label": "build all", "type": "shell", "command": "make all", "group": {"kind": "build", "isDefault": true}},
+                    {"label": "test", "type": "shell", "command": "make test", "group": {"kind": "test", "isDefault": true}},
+                    {"label": "test slow", "type": "shell", "command": "make slow", "group": {"kind": "test", "isDefault": false}},
+                    {"label": "clean", "type": "shell", "command": "make clean", "group": {"kind": "none", "isDefault": true}},
+                ],
+            }"#,
+        )
+        .unwrap();
+        let tasks: TaskTemplates = vscode_definitions.try_into().unwrap();
+        assert_eq!(
+            tasks
+                .0
+                .iter()
+                .map(|task| task.is_default_for_group)
+                .collect::<Vec<_>>(),
+            vec![false, true, true, false, false]
+        );
+        for (category, label) in [("build", "build all"), ("Test", "test")] {
+            let defaults = tasks
+                .0
+                .iter()
+                .filter(|task| {
+                    task.is_default_for_group
+                        && task.category.as_deref().map(str::to_lowercase)
+                            == Some(category.to_lowercase())
+                })
+                .count();
+            assert_eq!(defaults, 1, "Exactly one default {category} task expected");
+            assert_eq!(
+                tasks
+                    .default_for_category(category)
+                    .map(|task| task.label.as_str()),
+                Some(label)
+            );
+        }
+        assert_eq!(tasks.default_for_category("run"), None);
+    }
+
+    #[test]
+    fn test_depends_on_mapping() {
+        let vscode_definitions: VsCodeTaskFile = serde_json_lenient::from_str(
+            r#"{
+                "version": "2.0.0",
+                "tasks": [
+                    {"label": "lint", "type": "shell", "command": "eslint"},
+                    {"label": "build", "type": "shell", "command": "tsc", "dependsOn": "lint"},
+                    {"label": "test", "type": "shell", "command": "jest", "dependsOn": ["lint", "build"]},
+                    {"label": "all", "dependsOn": ["test"]},
+                ],
+            }"#,
+        )
+        .unwrap();
+        let tasks: TaskTemplates = vscode_definitions.try_into().unwrap();
+        assert_eq!(
+            tasks
+                .0
+                .iter()
+                .map(|task| (task.label.as_str(), task.depends_on.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("lint", Vec::new()),
+                ("build", vec!["lint".to_string()]),
+                ("test", vec!["lint".to_string(), "build".to_string()]),
+            ],
+            "Tasks with dependencies only should be skipped, as there is nothing to run"
+        );
+    }
 }
@@ -1,14 +1,20 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use ::settings::Settings;
 use editor::Editor;
-use gpui::{AppContext, ViewContext, WindowContext};
-use language::{Language, Point};
+use gpui::{AppContext, PromptLevel, ViewContext, WindowContext};
+use language::{language_settings::language_settings, Language, Point};
 use modal::{Spawn, TasksModal};
 use project::{Location, TaskSourceKind, WorktreeId};
-use task::{ResolvedTask, TaskContext, TaskTemplate, TaskVariables, VariableName};
+use task::{
+    relative_file_path, visual_column, ResolvedTask, TaskContext, TaskKind, TaskTemplate,
+    TaskVariables, VariableName,
+};
 use util::ResultExt;
-use workspace::Workspace;
+use workspace::{notifications::NotificationId, Toast, Workspace};
 
 mod modal;
 mod settings;
@@ -182,8 +188,19 @@ fn task_context(
                     .display_snapshot
                     .buffer_snapshot
                     .offset_to_point(selection_range.start);
+                let line_prefix = snapshot
+                    .display_snapshot
+                    .buffer_snapshot
+                    .text_for_range(Point::new(row, 0)..Point::new(row, column))
+                    .collect::<String>();
                 let row = row + 1;
                 let column = column + 1;
+                let diagnostic = buffer
+                    .read(cx)
+                    .snapshot()
+                    .diagnostics_in_range::<_, usize>(start..start, false)
+                    .find(|entry| entry.diagnostic.is_primary)
+                    .map(|entry| entry.diagnostic);
                 let location = Location {
                     buffer: buffer.clone(),
                     range: start..end,
@@ -215,18 +232,53 @@ fn task_context(
                 });
 
                 let selected_text = buffer.read(cx).chars_for_range(selection_range).collect();
+                let tab_width = {
+                    let buffer = buffer.read(cx);
+                    language_settings(buffer.language(), buffer.file(), cx).tab_size
+                };
+                let language_id = buffer
+                    .read(cx)
+                    .language()
+                    .map(|language| language.code_fence_block_name())
+                    .filter(|language_id| !language_id.is_empty());
 
                 let mut task_variables = TaskVariables::from_iter([
                     (VariableName::Row, row.to_string()),
                     (VariableName::Column, column.to_string()),
                     (VariableName::SelectedText, selected_text),
+                    (VariableName::TabWidth, tab_width.to_string()),
+                    (
+                        VariableName::VisualColumn,
+                        visual_column(&line_prefix, tab_width.get()).to_string(),
+                    ),
                 ]);
+                let relative_file = current_file
+                    .as_deref()
+                    .zip(worktree_path.as_deref())
+                    .and_then(|(file, worktree_root)| {
+                        relative_file_path(Path::new(file), Path::new(worktree_root))
+                    });
                 if let Some(path) = current_file {
                     task_variables.insert(VariableName::File, path);
                 }
+                if let Some(relative_file) = relative_file {
+                    task_variables.insert(
+                        VariableName::RelativeFile,
+                        relative_file.to_string_lossy().to_string(),
+                    );
+                }
                 if let Some(worktree_path) = worktree_path {
                     task_variables.insert(VariableName::WorktreeRoot, worktree_path);
                 }
+                if let Some(language_id) = language_id {
+                    task_variables.insert(VariableName::Language, language_id.to_string());
+                }
+                if let Some(diagnostic) = diagnostic {
+                    task_variables.insert(VariableName::DiagnosticMessage, diagnostic.message);
+                    if let Some(code) = diagnostic.code {
+                        task_variables.insert(VariableName::DiagnosticCode, code);
+                    }
+                }
                 if let Some(language_context) = context {
                     task_variables.extend(language_context);
                 }
@@ -271,12 +323,81 @@ fn schedule_task(
 }
 
 fn schedule_resolved_task(
+    workspace: &Workspace,
+    task_source_kind: TaskSourceKind,
+    resolved_task: ResolvedTask,
+    omit_history: bool,
+    cx: &mut ViewContext<'_, Workspace>,
+) {
+    if let Some(message) = resolved_task.deprecation_message() {
+        cx.defer(move |workspace, cx| {
+            struct DeprecatedTask;
+
+            workspace.show_toast(
+                Toast::new(NotificationId::unique::<DeprecatedTask>(), message),
+                cx,
+            )
+        });
+    }
+    let Some(confirm) = resolved_task
+        .resolved
+        .as_ref()
+        .and_then(|spawn_in_terminal| spawn_in_terminal.confirm.clone())
+    else {
+        spawn_resolved_task(workspace, task_source_kind, resolved_task, omit_history, cx);
+        return;
+    };
+    let answer = cx.prompt(PromptLevel::Warning, &confirm, None, &["Run", "Cancel"]);
+    cx.spawn(|workspace, mut cx| async move {
+        if answer.await.log_err() == Some(0) {
+            workspace
+                .update(&mut cx, |workspace, cx| {
+                    spawn_resolved_task(
+                        workspace,
+                        task_source_kind,
+                        resolved_task,
+                        omit_history,
+                        cx,
+                    )
+                })
+                .ok();
+        }
+    })
+    .detach();
+}
+
+fn spawn_resolved_task(
     workspace: &Workspace,
     task_source_kind: TaskSourceKind,
     mut resolved_task: ResolvedTask,
     omit_history: bool,
     cx: &mut ViewContext<'_, Workspace>,
 ) {
+    if let TaskKind::Action(action_name) = &resolved_task.kind {
+        match cx.build_action(action_name, None) {
+            Ok(action) => cx.dispatch_action(action),
+            Err(e) => {
+                let message = format!("Cannot run task action `{action_name}`: {e}");
+                cx.defer(move |workspace, cx| {
+                    struct UnknownTaskAction;
+
+                    workspace.show_toast(
+                        Toast::new(NotificationId::unique::<UnknownTaskAction>(), message),
+                        cx,
+                    )
+                });
+                return;
+            }
+        }
+        if !omit_history {
+            workspace.project().update(cx, |project, cx| {
+                project.task_inventory().update(cx, |inventory, _| {
+                    inventory.task_scheduled(task_source_kind, resolved_task);
+                })
+            });
+        }
+        return;
+    }
     if let Some(spawn_in_terminal) = resolved_task.resolved.take() {
         if !omit_history {
             resolved_task.resolved = Some(spawn_in_terminal.clone());
@@ -438,10 +559,13 @@ mod tests {
                     cwd: Some("/dir".into()),
                     task_variables: TaskVariables::from_iter([
                         (VariableName::File, "/dir/rust/b.rs".into()),
+                        (VariableName::RelativeFile, "rust/b.rs".into()),
                         (VariableName::WorktreeRoot, "/dir".into()),
                         (VariableName::Row, "1".into()),
                         (VariableName::Column, "1".into()),
-                        (VariableName::SelectedText, "".into())
+                        (VariableName::VisualColumn, "1".into()),
+                        (VariableName::SelectedText, "".into()),
+                        (VariableName::TabWidth, "4".into()),
                     ])
                 }
             );
@@ -455,10 +579,13 @@ mod tests {
                     cwd: Some("/dir".into()),
                     task_variables: TaskVariables::from_iter([
                         (VariableName::File, "/dir/rust/b.rs".into()),
+                        (VariableName::RelativeFile, "rust/b.rs".into()),
                         (VariableName::WorktreeRoot, "/dir".into()),
                         (VariableName::Row, "1".into()),
                         (VariableName::Column, "15".into()),
+                        (VariableName::VisualColumn, "15".into()),
                         (VariableName::SelectedText, "is_i".into()),
+                        (VariableName::TabWidth, "4".into()),
                         (VariableName::Symbol, "this_is_a_rust_file".into()),
                     ])
                 }
@@ -472,10 +599,13 @@ mod tests {
                     cwd: Some("/dir".into()),
                     task_variables: TaskVariables::from_iter([
                         (VariableName::File, "/dir/a.ts".into()),
+                        (VariableName::RelativeFile, "a.ts".into()),
                         (VariableName::WorktreeRoot, "/dir".into()),
                         (VariableName::Row, "1".into()),
                         (VariableName::Column, "1".into()),
+                        (VariableName::VisualColumn, "1".into()),
                         (VariableName::SelectedText, "".into()),
+                        (VariableName::TabWidth, "4".into()),
                         (VariableName::Symbol, "this_is_a_test".into()),
                     ])
                 }
@@ -223,7 +223,11 @@ impl PickerDelegate for TasksModalDelegate {
                             };
 
                             let mut new_candidates = used;
-                            new_candidates.extend(current);
+                            new_candidates.extend(
+                                current
+                                    .into_iter()
+                                    .filter(|(_, task)| !task.original_task.hidden),
+                            );
                             picker.delegate.candidates.insert(new_candidates)
                         }
                     };
@@ -294,6 +294,8 @@ pub struct SpawnTask {
     pub args: Vec<String>,
     pub env: HashMap<String, String>,
     pub reveal: RevealStrategy,
+    pub strip_zed_path_additions: bool,
+    pub scrollback: Option<usize>,
 }
 
 // https://github.com/alacritty/alacritty/blob/cb3a79dbf6472740daca8440d5166c1d4af5029e/extra/man/alacritty.5.scd?plain=1#L207-L213
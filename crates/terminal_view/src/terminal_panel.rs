@@ -14,13 +14,16 @@ use project::{Fs, ProjectEntryId};
 use search::{buffer_search::DivRegistrar, BufferSearchBar};
 use serde::{Deserialize, Serialize};
 use settings::Settings;
-use task::{RevealStrategy, SpawnInTerminal, TaskId};
+use task::{
+    QuotingDialect, RevealStrategy, ShellEnvCommandRunner, SpawnInTerminal, TaskId,
+    TerminalDefaults, DEFAULT_ARGS_FILE_THRESHOLD, DEFAULT_ENV_COMMAND_TIMEOUT,
+};
 use terminal::{
     terminal_settings::{Shell, TerminalDockPosition, TerminalSettings},
     SpawnTask,
 };
 use ui::{h_flex, ButtonCommon, Clickable, IconButton, IconSize, Selectable, Tooltip};
-use util::{ResultExt, TryFutureExt};
+use util::{paths, ResultExt, TryFutureExt};
 use workspace::{
     dock::{DockPosition, Panel, PanelEvent},
     item::Item,
@@ -29,7 +32,7 @@ use workspace::{
     DraggedTab, NewTerminal, Pane, Workspace,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Context as _, Result};
 
 const TERMINAL_PANEL_KEY: &str = "TerminalPanel";
 
@@ -297,22 +300,111 @@ impl TerminalPanel {
     }
 
     fn spawn_task(&mut self, spawn_in_terminal: &SpawnInTerminal, cx: &mut ViewContext<Self>) {
+        // The terminal shell always runs as the current user, so the tasks asking for other users are refused rather than silently run as it.
+        if spawn_in_terminal.run_as_user.is_some() || spawn_in_terminal.run_as_group.is_some() {
+            let error = anyhow!(
+                "Task `{}` is configured to run as a different user or group, which terminal tasks do not support",
+                spawn_in_terminal.full_label
+            );
+            self.workspace
+                .update(cx, |workspace, cx| workspace.show_error(&error, cx))
+                .ok();
+            return;
+        }
+        let mut spawn_in_terminal = spawn_in_terminal.clone();
+        spawn_in_terminal.apply_line_buffering();
+        spawn_in_terminal.apply_cwd_candidates();
+        let use_args_file = spawn_in_terminal.args_file
+            || spawn_in_terminal.args_length() > DEFAULT_ARGS_FILE_THRESHOLD;
+        if spawn_in_terminal.env_file.is_none()
+            && spawn_in_terminal.env_command.is_none()
+            && !use_args_file
+            && !spawn_in_terminal.create_cwd
+        {
+            if let Err(error) = spawn_in_terminal.ensure_cwd() {
+                self.workspace
+                    .update(cx, |workspace, cx| workspace.show_error(&error, cx))
+                    .ok();
+                return;
+            }
+            self.spawn_prepared_task(spawn_in_terminal, cx);
+            return;
+        }
+
+        // Creating the working directory, reading the env file, running the env command, e.g. to fetch the credentials,
+        // and writing the arguments file may take a while, so all of them happen in the background.
+        let prepared_task = cx.background_executor().spawn(async move {
+            spawn_in_terminal.ensure_cwd()?;
+            spawn_in_terminal.apply_env_file()?;
+            spawn_in_terminal
+                .apply_env_command(&ShellEnvCommandRunner, DEFAULT_ENV_COMMAND_TIMEOUT)?;
+            if use_args_file {
+                std::fs::create_dir_all(&*paths::TEMP_DIR)
+                    .context("creating the task arguments file dir")?;
+                spawn_in_terminal.use_args_file(DEFAULT_ARGS_FILE_THRESHOLD, &paths::TEMP_DIR)?;
+            }
+            anyhow::Ok(spawn_in_terminal)
+        });
+        let workspace = self.workspace.clone();
+        cx.spawn(|terminal_panel, mut cx| async move {
+            match prepared_task.await {
+                Ok(spawn_in_terminal) => terminal_panel.update(&mut cx, |terminal_panel, cx| {
+                    terminal_panel.spawn_prepared_task(spawn_in_terminal, cx)
+                })?,
+                Err(e) => workspace.update(&mut cx, |workspace, cx| {
+                    workspace.show_error(&e.context("preparing the task"), cx)
+                })?,
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn spawn_prepared_task(
+        &mut self,
+        mut spawn_in_terminal: SpawnInTerminal,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let settings = TerminalSettings::get_global(cx);
+        let (default_shell, default_shell_args) = match settings.shell.clone() {
+            Shell::System => (std::env::var("SHELL").ok(), Vec::new()),
+            Shell::Program(shell) => (Some(shell), Vec::new()),
+            Shell::WithArguments { program, args } => (Some(program), args),
+        };
+        // The shell arguments from the settings are meant for the settings' shell only.
+        let mut user_args = if spawn_in_terminal.shell.is_some() {
+            Vec::new()
+        } else {
+            default_shell_args
+        };
+        spawn_in_terminal.apply_defaults(&TerminalDefaults {
+            shell: default_shell,
+            env: settings.env.clone(),
+            scrollback: settings.max_scroll_history_lines,
+            cwd: None,
+        });
+        // Set up shell args unconditionally, as tasks are always spawned inside of a shell.
+        let Some(shell) = spawn_in_terminal.shell.clone() else {
+            return;
+        };
+
+        // The terminal inherits Zed's env, so the task `PATH` is combined with Zed's one, unless the settings replace it.
+        let mut inherited_env = settings.env.clone();
+        if !inherited_env.contains_key("PATH") {
+            if let Ok(path) = std::env::var("PATH") {
+                inherited_env.insert("PATH".to_owned(), path);
+            }
+        }
         let mut spawn_task = SpawnTask {
             id: spawn_in_terminal.id.clone(),
             full_label: spawn_in_terminal.full_label.clone(),
             label: spawn_in_terminal.label.clone(),
             command: spawn_in_terminal.command.clone(),
             args: spawn_in_terminal.args.clone(),
-            env: spawn_in_terminal.env.clone(),
+            env: spawn_in_terminal.merge_env_from(&inherited_env),
             reveal: spawn_in_terminal.reveal,
-        };
-        // Set up shell args unconditionally, as tasks are always spawned inside of a shell.
-        let Some((shell, mut user_args)) = (match TerminalSettings::get_global(cx).shell.clone() {
-            Shell::System => std::env::var("SHELL").ok().map(|shell| (shell, vec![])),
-            Shell::Program(shell) => Some((shell, vec![])),
-            Shell::WithArguments { program, args } => Some((program, args)),
-        }) else {
-            return;
+            strip_zed_path_additions: spawn_in_terminal.strip_zed_path_additions,
+            scrollback: spawn_in_terminal.scrollback,
         };
 
         let mut command = std::mem::take(&mut spawn_task.command);
@@ -321,6 +413,10 @@ impl TerminalPanel {
             command.push(' ');
             command.push_str(&arg);
         }
+        if let Some(output_banner) = spawn_in_terminal.output_banner() {
+            let output_banner = QuotingDialect::for_shell(&shell).quote(&output_banner);
+            command = format!("echo {output_banner}; {command}");
+        }
         spawn_task.command = shell;
         user_args.extend(["-i".to_owned(), "-c".to_owned(), command]);
         spawn_task.args = user_args;
@@ -394,6 +490,9 @@ impl TerminalPanel {
                     })
                     .detach();
                 }
+                RevealStrategy::RevealThenRefocusEditor => {
+                    self.open_panel_without_focus(Some(existing_item_index), cx);
+                }
                 RevealStrategy::Never => {}
             }
         }
@@ -417,10 +516,27 @@ impl TerminalPanel {
                 })
                 .detach();
             }
+            RevealStrategy::RevealThenRefocusEditor => self.open_panel_without_focus(None, cx),
             RevealStrategy::Never => {}
         }
     }
 
+    /// Shows the panel with the terminal given (or the active one), keeping the focus where it is, e.g. in the editor.
+    fn open_panel_without_focus(&self, item_index: Option<usize>, cx: &mut ViewContext<Self>) {
+        if let Some(item_index) = item_index {
+            self.pane.update(cx, |pane, cx| {
+                pane.activate_item(item_index, false, false, cx)
+            });
+        }
+        let task_workspace = self.workspace.clone();
+        cx.spawn(|_, mut cx| async move {
+            task_workspace
+                .update(&mut cx, |workspace, cx| workspace.open_panel::<Self>(cx))
+                .ok()
+        })
+        .detach();
+    }
+
     ///Create a new Terminal in the current working directory or the user's home directory
     fn new_terminal(
         workspace: &mut Workspace,
@@ -587,6 +703,9 @@ impl TerminalPanel {
                 })
                 .detach();
             }
+            RevealStrategy::RevealThenRefocusEditor => {
+                self.open_panel_without_focus(Some(terminal_item_index), cx);
+            }
             RevealStrategy::Never => {}
         }
 
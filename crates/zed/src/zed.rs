@@ -176,7 +176,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                                 fs,
                                 paths::TASKS.clone(),
                             );
-                            StaticSource::new(TrackedFile::new(tasks_file_rx, cx), cx)
+                            StaticSource::new(TrackedFile::new_tasks_file(tasks_file_rx, cx), cx)
                         },
                         cx,
                     );